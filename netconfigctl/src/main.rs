@@ -13,6 +13,8 @@ struct Cli {
 enum Commands {
     ListInterfaces,
     ListAddresses,
+    #[cfg(target_os = "linux")]
+    ListRoutes,
     #[cfg(unix)]
     SetIfParam {
         iface: String,
@@ -51,6 +53,11 @@ fn main() {
                         println!("LUID: {:?}", handle.luid());
                     }
                 }
+                cfg_if::cfg_if! {
+                    if #[cfg(target_os = "linux")] {
+                        println!("Kind: {}", handle.kind().unwrap());
+                    }
+                }
                 println!("MTU: {}", handle.mtu().unwrap());
 
                 for address in handle.addresses().unwrap() {
@@ -64,6 +71,25 @@ fn main() {
                 println!();
             }
         }
+        #[cfg(target_os = "linux")]
+        Commands::ListRoutes => {
+            for route in netconfig::sys::list_routes(&Default::default()).unwrap() {
+                print!("{}", route.destination);
+                if let Some(gateway) = route.gateway {
+                    print!(" via {gateway}");
+                }
+                if let Some(interface) = &route.interface {
+                    print!(" dev {}", interface.name().unwrap());
+                }
+                if let Some(source) = route.source {
+                    print!(" src {source}");
+                }
+                if let Some(metric) = route.metric {
+                    print!(" metric {metric}");
+                }
+                println!(" table {} {:?}", route.table, route.route_type);
+            }
+        }
         #[cfg(unix)]
         Commands::SetIfParam { iface, param } => {
             let handle = Interface::try_from_name(&iface).unwrap();
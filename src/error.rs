@@ -11,12 +11,66 @@ pub enum Error {
     UnexpectedMetadata,
     #[error("interface not found")]
     InterfaceNotFound,
+    #[error("timed out")]
+    Timeout,
     #[error("unknown error: {0}")]
     Unknown(Box<dyn StdError>),
+    /// On Linux, the error of a request, rejected by the kernel, may wrap an [`ExtendedAck`],
+    /// which explains it.
     #[error("I/O error: {0}")]
     Io(io::Error),
 }
 
+impl Error {
+    /// Returns the OS error code, if this error was caused by a failed system call or a request,
+    /// rejected by the kernel.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Self::Io(e) => e.raw_os_error().or_else(|| {
+                let ack = e.get_ref()?.downcast_ref::<ExtendedAck>()?;
+                ack.error.raw_os_error()
+            }),
+            #[cfg(not(target_os = "linux"))]
+            Self::Io(e) => e.raw_os_error(),
+            _ => None,
+        }
+    }
+}
+
+/// Explanation of a rejected request, given by the kernel in its extended acknowledgement
+/// (`NETLINK_EXT_ACK`). Retrieved from [`Error::Io`] with [`io::Error::get_ref`].
+#[cfg(target_os = "linux")]
+#[non_exhaustive]
+#[derive(Debug, ThisError)]
+#[error("{error}{}", ack_details(.message, .attribute))]
+pub struct ExtendedAck {
+    /// Error code of the response.
+    pub error: io::Error,
+    /// Reason, given by the kernel, e.g. "Invalid prefix for given prefix length".
+    pub message: Option<String>,
+    /// Type of the request attribute, the kernel has rejected.
+    pub attribute: Option<u16>,
+}
+
+#[cfg(target_os = "linux")]
+impl From<ExtendedAck> for io::Error {
+    /// Wraps the explanation, keeping the kind of the error.
+    fn from(ack: ExtendedAck) -> Self {
+        io::Error::new(ack.error.kind(), ack)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn ack_details(message: &Option<String>, attribute: &Option<u16>) -> String {
+    match (message, attribute) {
+        (Some(message), Some(attribute)) => format!(": {} (attribute {})", message, attribute),
+        (Some(message), None) => format!(": {}", message),
+        (None, Some(attribute)) => format!(" (attribute {})", attribute),
+        (None, None) => String::new(),
+    }
+}
+
 #[cfg(unix)]
 impl From<nix::Error> for Error {
     fn from(e: nix::Error) -> Self {
@@ -43,3 +97,20 @@ impl From<widestring::error::Utf16Error> for Error {
         Self::UnexpectedMetadata
     }
 }
+
+#[cfg(target_os = "linux")]
+impl From<netlink_packet_route::ErrorMessage> for Error {
+    fn from(e: netlink_packet_route::ErrorMessage) -> Self {
+        match -e.code {
+            libc::ENODEV => Self::InterfaceNotFound,
+            _ => Self::Io(e.to_io()),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<netlink_packet_route::DecodeError> for Error {
+    fn from(e: netlink_packet_route::DecodeError) -> Self {
+        Self::Unknown(Box::new(e))
+    }
+}
@@ -2,6 +2,8 @@ mod error;
 use advmac::MacAddr6;
 use delegate::delegate;
 pub use error::Error;
+#[cfg(target_os = "linux")]
+pub use error::ExtendedAck;
 pub use ipnet;
 use ipnet::IpNet;
 use std::collections::HashSet;
@@ -12,7 +14,7 @@ pub mod sys;
 ///
 /// Index is chosen, because basically all operating systems use index as an identifier.
 /// This struct can be used to manipulate interface parameters, such as IP address and MTU.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Interface(sys::InterfaceHandle);
 
 impl Interface {
@@ -34,7 +36,7 @@ impl Interface {
     }
 
     pub fn from_index_unchecked(index: u32) -> Self {
-        Self(sys::InterfaceHandle { index })
+        Self(sys::InterfaceHandle::new(index))
     }
 
     /// Returns `InterfaceHandle` from given interface index or Error if not found.
@@ -59,15 +61,19 @@ pub fn list_interfaces() -> Result<Vec<Interface>, Error> {
 }
 
 pub fn list_addresses() -> Result<Vec<IpNet>, Error> {
-    let interfaces = list_interfaces()?;
+    Ok(collect_addresses(&list_interfaces()?))
+}
 
+/// Returns unique addresses, assigned to given interfaces. Interfaces, that can't be queried, are
+/// skipped.
+pub(crate) fn collect_addresses(interfaces: &[Interface]) -> Vec<IpNet> {
     let addresses = interfaces
         .iter()
         .flat_map(|iface| iface.addresses())
         .flatten();
 
-    Ok(HashSet::<IpNet>::from_iter(addresses)
+    HashSet::<IpNet>::from_iter(addresses)
         .iter()
         .cloned()
-        .collect())
+        .collect()
 }
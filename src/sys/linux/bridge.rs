@@ -0,0 +1,775 @@
+use super::handle::parse_ip;
+use super::neighbor::NeighborOperation;
+use super::netlink::{self, RawMessage};
+use super::route::ip_to_vec;
+use super::NetnsHandle;
+use crate::sys::InterfaceHandle;
+use crate::{Error, Interface};
+use advmac::MacAddr6;
+use libc::AF_BRIDGE;
+use netlink_packet_route::neighbour::Nla as NeighbourNla;
+use netlink_packet_route::nlas::NlasIterator;
+use netlink_packet_route::{
+    NeighbourMessage, RtnlMessage, NLM_F_CREATE, NLM_F_DUMP, NLM_F_EXCL, NLM_F_REPLACE,
+    NTF_EXT_LEARNED, NTF_MASTER, NTF_SELF, NUD_NOARP, NUD_PERMANENT, NUD_REACHABLE,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+// MDB messages are not supported by netlink-packet-route, see include/uapi/linux/if_bridge.h
+const RTM_NEWMDB: u16 = 84;
+const RTM_DELMDB: u16 = 85;
+const RTM_GETMDB: u16 = 86;
+
+const MDBA_MDB: u16 = 1;
+const MDBA_SET_ENTRY: u16 = 1;
+const MDBA_MDB_ENTRY: u16 = 1;
+const MDBA_MDB_ENTRY_INFO: u16 = 1;
+
+const MDB_TEMPORARY: u8 = 0;
+const MDB_PERMANENT: u8 = 1;
+
+const ETH_P_IP: u16 = 0x0800;
+const ETH_P_IPV6: u16 = 0x86dd;
+
+/// Size of `struct br_port_msg`.
+const PORT_MESSAGE_LEN: usize = 8;
+/// Size of `struct br_mdb_entry`.
+const MDB_ENTRY_LEN: usize = 28;
+
+/// `struct br_port_msg`: family, padding and bridge index, followed by attributes.
+type MdbMessage = RawMessage<PORT_MESSAGE_LEN>;
+
+/// How a bridge FDB entry was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FdbState {
+    /// Learned from traffic, expires when aged out.
+    Dynamic,
+    /// Configured, never aged out (`static`).
+    Static,
+    /// Address of the port itself, packets are delivered locally (`permanent`).
+    Permanent,
+}
+
+/// Entry of a bridge forwarding database, as shown by `bridge fdb`.
+///
+/// The same type is used to describe entries to add, replace or delete.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct FdbEntry {
+    pub mac: MacAddr6,
+    pub vlan: Option<u16>,
+    /// Bridge port, or a device with its own FDB, e.g. a VXLAN device.
+    pub port: Interface,
+    /// Bridge, the port belongs to (`NDA_MASTER`). Ignored when an entry is created.
+    pub bridge: Option<Interface>,
+    /// Remote VTEP of a VXLAN FDB entry.
+    pub remote: Option<IpAddr>,
+    pub state: FdbState,
+    /// Entry is in the FDB of the bridge (`master`).
+    pub master: bool,
+    /// Entry is in the FDB of the port device itself (`self`).
+    pub device: bool,
+    /// Entry was learned by an external control plane, e.g. a switch driver (`extern_learn`).
+    pub extern_learn: bool,
+}
+
+impl FdbEntry {
+    /// Creates a static entry for `mac` on bridge port `port`.
+    pub fn new(mac: MacAddr6, port: &Interface) -> Self {
+        Self {
+            mac,
+            vlan: None,
+            port: port.clone(),
+            bridge: None,
+            remote: None,
+            state: FdbState::Static,
+            master: true,
+            device: false,
+            extern_learn: false,
+        }
+    }
+
+    pub fn vlan(mut self, vlan: u16) -> Self {
+        self.vlan = Some(vlan);
+        self
+    }
+
+    pub fn remote(mut self, remote: IpAddr) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    pub fn state(mut self, state: FdbState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn master(mut self, master: bool) -> Self {
+        self.master = master;
+        self
+    }
+
+    pub fn device(mut self, device: bool) -> Self {
+        self.device = device;
+        self
+    }
+
+    pub fn extern_learn(mut self, extern_learn: bool) -> Self {
+        self.extern_learn = extern_learn;
+        self
+    }
+
+    pub(crate) fn from_message(
+        message: NeighbourMessage,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Option<Self>, Error> {
+        let header = &message.header;
+        if header.family as i32 != AF_BRIDGE {
+            return Ok(None);
+        }
+
+        let mut mac = None;
+        let mut vlan = None;
+        let mut bridge = None;
+        let mut remote = None;
+        for nla in message.nlas {
+            match nla {
+                NeighbourNla::LinkLocalAddress(bytes) => mac = MacAddr6::try_from(&bytes[..]).ok(),
+                NeighbourNla::Vlan(id) => vlan = Some(id),
+                NeighbourNla::Master(bytes) => {
+                    let index = bytes.try_into().map_err(|_| Error::UnexpectedMetadata)?;
+                    bridge = Some(interface(u32::from_ne_bytes(index), netns));
+                }
+                NeighbourNla::Destination(bytes) => remote = parse_ip(&bytes),
+                _ => {}
+            }
+        }
+        let Some(mac) = mac else {
+            return Ok(None);
+        };
+
+        let bridge_set = bridge.is_some();
+        let state = if header.state & NUD_PERMANENT != 0 {
+            FdbState::Permanent
+        } else if header.state & NUD_NOARP != 0 {
+            FdbState::Static
+        } else {
+            FdbState::Dynamic
+        };
+
+        Ok(Some(Self {
+            mac,
+            vlan,
+            port: interface(header.ifindex, netns),
+            bridge,
+            remote,
+            state,
+            // Dumps of the bridge FDB don't set NTF_MASTER, but carry NDA_MASTER
+            master: header.flags & NTF_MASTER != 0 || (bridge_set && header.flags & NTF_SELF == 0),
+            device: header.flags & NTF_SELF != 0,
+            extern_learn: header.flags & NTF_EXT_LEARNED != 0,
+        }))
+    }
+
+    pub(crate) fn to_message(&self) -> NeighbourMessage {
+        let mut message = NeighbourMessage::default();
+        let header = &mut message.header;
+
+        header.family = AF_BRIDGE as _;
+        header.ifindex = self.port.0.index;
+        header.state = match self.state {
+            FdbState::Dynamic => NUD_REACHABLE,
+            FdbState::Static => NUD_NOARP,
+            FdbState::Permanent => NUD_PERMANENT,
+        };
+        if self.master {
+            header.flags |= NTF_MASTER;
+        }
+        if self.device {
+            header.flags |= NTF_SELF;
+        }
+        if self.extern_learn {
+            header.flags |= NTF_EXT_LEARNED;
+        }
+
+        message
+            .nlas
+            .push(NeighbourNla::LinkLocalAddress(self.mac.as_slice().to_vec()));
+        if let Some(vlan) = self.vlan {
+            message.nlas.push(NeighbourNla::Vlan(vlan));
+        }
+        if let Some(remote) = self.remote {
+            message
+                .nlas
+                .push(NeighbourNla::Destination(ip_to_vec(remote)));
+        }
+
+        message
+    }
+}
+
+/// Entry of a bridge multicast database, as shown by `bridge mdb`.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct MdbEntry {
+    pub bridge: Interface,
+    /// Bridge port, multicast traffic is forwarded to.
+    pub port: Interface,
+    /// Multicast group address.
+    pub group: IpAddr,
+    pub vlan: Option<u16>,
+    /// Entry is never aged out. Otherwise it expires, unless refreshed by IGMP or MLD reports.
+    pub permanent: bool,
+}
+
+impl MdbEntry {
+    /// Creates a permanent entry, forwarding traffic for `group` to `port` of `bridge`.
+    pub fn new(bridge: &Interface, port: &Interface, group: IpAddr) -> Self {
+        Self {
+            bridge: bridge.clone(),
+            port: port.clone(),
+            group,
+            vlan: None,
+            permanent: true,
+        }
+    }
+
+    pub fn vlan(mut self, vlan: u16) -> Self {
+        self.vlan = Some(vlan);
+        self
+    }
+
+    pub fn permanent(mut self, permanent: bool) -> Self {
+        self.permanent = permanent;
+        self
+    }
+
+    /// Parses the entries of an MDB dump response or notification, for every group and port.
+    fn from_message(message: &MdbMessage, netns: Option<&NetnsHandle>) -> Result<Vec<Self>, Error> {
+        let bridge = u32::from_ne_bytes(message.header[4..8].try_into().unwrap());
+        let mut entries = vec![];
+
+        // MDBA_MDB contains MDBA_MDB_ENTRY for every group, with MDBA_MDB_ENTRY_INFO for every port
+        for mdb in NlasIterator::new(&message.attributes) {
+            let mdb = mdb?;
+            if mdb.kind() != MDBA_MDB {
+                continue;
+            }
+            for group in NlasIterator::new(mdb.value()) {
+                let group = group?;
+                if group.kind() != MDBA_MDB_ENTRY {
+                    continue;
+                }
+                for info in NlasIterator::new(group.value()) {
+                    let info = info?;
+                    if info.kind() != MDBA_MDB_ENTRY_INFO {
+                        continue;
+                    }
+                    entries.extend(Self::parse(info.value(), bridge, netns)?);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Parses `struct br_mdb_entry`. Entries for non-IP groups are skipped.
+    fn parse(
+        bytes: &[u8],
+        bridge: u32,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Option<Self>, Error> {
+        if bytes.len() < MDB_ENTRY_LEN {
+            return Err(Error::UnexpectedMetadata);
+        }
+
+        let port = u32::from_ne_bytes(bytes[0..4].try_into().unwrap());
+        let state = bytes[4];
+        let vlan = u16::from_ne_bytes(bytes[6..8].try_into().unwrap());
+        let address = &bytes[8..24];
+        let group: IpAddr = match u16::from_be_bytes(bytes[24..26].try_into().unwrap()) {
+            ETH_P_IP => Ipv4Addr::from(<[u8; 4]>::try_from(&address[..4]).unwrap()).into(),
+            ETH_P_IPV6 => Ipv6Addr::from(<[u8; 16]>::try_from(address).unwrap()).into(),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Self {
+            bridge: interface(bridge, netns),
+            port: interface(port, netns),
+            group,
+            vlan: (vlan != 0).then_some(vlan),
+            permanent: state == MDB_PERMANENT,
+        }))
+    }
+
+    fn to_message(&self, message_type: u16) -> MdbMessage {
+        let mut message = MdbMessage::new(message_type);
+        message.header[0] = AF_BRIDGE as _;
+        message.header[4..8].copy_from_slice(&self.bridge.0.index.to_ne_bytes());
+
+        let mut entry = [0; MDB_ENTRY_LEN];
+        entry[0..4].copy_from_slice(&self.port.0.index.to_ne_bytes());
+        entry[4] = if self.permanent {
+            MDB_PERMANENT
+        } else {
+            MDB_TEMPORARY
+        };
+        entry[6..8].copy_from_slice(&self.vlan.unwrap_or(0).to_ne_bytes());
+        let protocol = match self.group {
+            IpAddr::V4(group) => {
+                entry[8..12].copy_from_slice(&group.octets());
+                ETH_P_IP
+            }
+            IpAddr::V6(group) => {
+                entry[8..24].copy_from_slice(&group.octets());
+                ETH_P_IPV6
+            }
+        };
+        entry[24..26].copy_from_slice(&protocol.to_be_bytes());
+
+        netlink::put_attribute(&mut message.attributes, MDBA_SET_ENTRY, &entry);
+        message
+    }
+}
+
+/// Criteria for [`list_fdb`] and [`list_mdb`]. Every unset field matches all entries.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+pub struct BridgeFilter {
+    /// Index of the bridge.
+    pub bridge: Option<u32>,
+    /// Index of the port.
+    pub port: Option<u32>,
+}
+
+impl BridgeFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bridge(mut self, bridge: &Interface) -> Self {
+        self.bridge = Some(bridge.0.index);
+        self
+    }
+
+    pub fn port(mut self, port: &Interface) -> Self {
+        self.port = Some(port.0.index);
+        self
+    }
+
+    fn matches(&self, bridge: Option<&Interface>, port: &Interface) -> bool {
+        let bridge_matches = self.bridge.map_or(true, |index| {
+            bridge.map(|bridge| bridge.0.index) == Some(index)
+        });
+        let port_matches = self.port.map_or(true, |index| port.0.index == index);
+
+        bridge_matches && port_matches
+    }
+}
+
+fn interface(index: u32, netns: Option<&NetnsHandle>) -> Interface {
+    Interface(InterfaceHandle {
+        index,
+        netns: netns.cloned(),
+    })
+}
+
+/// Returns bridge FDB entries, matching given filter.
+pub fn list_fdb(filter: &BridgeFilter) -> Result<Vec<FdbEntry>, Error> {
+    list_fdb_in(None, filter)
+}
+
+/// Adds a bridge FDB entry. Fails, if the same entry already exists.
+pub fn add_fdb(entry: &FdbEntry) -> Result<(), Error> {
+    modify_fdb_in(None, entry, NeighborOperation::Add)
+}
+
+/// Adds a bridge FDB entry or replaces the existing one for the same MAC and VLAN.
+pub fn replace_fdb(entry: &FdbEntry) -> Result<(), Error> {
+    modify_fdb_in(None, entry, NeighborOperation::Replace)
+}
+
+/// Deletes the bridge FDB entry for the MAC, VLAN and port of `entry`.
+pub fn delete_fdb(entry: &FdbEntry) -> Result<(), Error> {
+    modify_fdb_in(None, entry, NeighborOperation::Delete)
+}
+
+/// Returns bridge MDB entries, matching given filter.
+pub fn list_mdb(filter: &BridgeFilter) -> Result<Vec<MdbEntry>, Error> {
+    list_mdb_in(None, filter)
+}
+
+/// Adds a bridge MDB entry, or refreshes the existing one.
+pub fn add_mdb(entry: &MdbEntry) -> Result<(), Error> {
+    modify_mdb_in(None, entry, true)
+}
+
+pub fn delete_mdb(entry: &MdbEntry) -> Result<(), Error> {
+    modify_mdb_in(None, entry, false)
+}
+
+pub(crate) fn list_fdb_in(
+    netns: Option<&NetnsHandle>,
+    filter: &BridgeFilter,
+) -> Result<Vec<FdbEntry>, Error> {
+    let mut message = NeighbourMessage::default();
+    message.header.family = AF_BRIDGE as _;
+
+    let socket = netlink::socket(netns)?;
+    let responses = netlink::request(&socket, RtnlMessage::GetNeighbour(message), NLM_F_DUMP)?;
+
+    let mut entries = vec![];
+    for response in responses {
+        let RtnlMessage::NewNeighbour(message) = response else {
+            continue;
+        };
+        if let Some(entry) = FdbEntry::from_message(message, netns)? {
+            if filter.matches(entry.bridge.as_ref(), &entry.port) {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+pub(crate) fn modify_fdb_in(
+    netns: Option<&NetnsHandle>,
+    entry: &FdbEntry,
+    operation: NeighborOperation,
+) -> Result<(), Error> {
+    let (message, flags) = match operation {
+        NeighborOperation::Add => (
+            RtnlMessage::NewNeighbour(entry.to_message()),
+            NLM_F_CREATE | NLM_F_EXCL,
+        ),
+        NeighborOperation::Replace => (
+            RtnlMessage::NewNeighbour(entry.to_message()),
+            NLM_F_CREATE | NLM_F_REPLACE,
+        ),
+        NeighborOperation::Delete => (RtnlMessage::DelNeighbour(entry.to_message()), 0),
+    };
+
+    let socket = netlink::socket(netns)?;
+    netlink::request(&socket, message, flags)?;
+    Ok(())
+}
+
+pub(crate) fn list_mdb_in(
+    netns: Option<&NetnsHandle>,
+    filter: &BridgeFilter,
+) -> Result<Vec<MdbEntry>, Error> {
+    let mut message = MdbMessage::new(RTM_GETMDB);
+    message.header[0] = AF_BRIDGE as _;
+
+    let socket = netlink::socket(netns)?;
+    let mut entries = vec![];
+
+    for response in netlink::request(&socket, message, NLM_F_DUMP)? {
+        // Dumps are answered with RTM_GETMDB, notifications use RTM_NEWMDB
+        if response.message_type != RTM_GETMDB && response.message_type != RTM_NEWMDB {
+            continue;
+        }
+        for entry in MdbEntry::from_message(&response, netns)? {
+            if filter.matches(Some(&entry.bridge), &entry.port) {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+pub(crate) fn modify_mdb_in(
+    netns: Option<&NetnsHandle>,
+    entry: &MdbEntry,
+    add: bool,
+) -> Result<(), Error> {
+    let (message, flags) = if add {
+        (entry.to_message(RTM_NEWMDB), NLM_F_CREATE | NLM_F_REPLACE)
+    } else {
+        (entry.to_message(RTM_DELMDB), 0)
+    };
+
+    let socket = netlink::socket(netns)?;
+    netlink::request(&socket, message, flags)?;
+    Ok(())
+}
+
+impl NetnsHandle {
+    /// Returns bridge FDB entries inside this namespace, matching given filter.
+    pub fn list_fdb(&self, filter: &BridgeFilter) -> Result<Vec<FdbEntry>, Error> {
+        list_fdb_in(Some(self), filter)
+    }
+
+    pub fn add_fdb(&self, entry: &FdbEntry) -> Result<(), Error> {
+        modify_fdb_in(Some(self), entry, NeighborOperation::Add)
+    }
+
+    pub fn replace_fdb(&self, entry: &FdbEntry) -> Result<(), Error> {
+        modify_fdb_in(Some(self), entry, NeighborOperation::Replace)
+    }
+
+    pub fn delete_fdb(&self, entry: &FdbEntry) -> Result<(), Error> {
+        modify_fdb_in(Some(self), entry, NeighborOperation::Delete)
+    }
+
+    /// Returns bridge MDB entries inside this namespace, matching given filter.
+    pub fn list_mdb(&self, filter: &BridgeFilter) -> Result<Vec<MdbEntry>, Error> {
+        list_mdb_in(Some(self), filter)
+    }
+
+    pub fn add_mdb(&self, entry: &MdbEntry) -> Result<(), Error> {
+        modify_mdb_in(Some(self), entry, true)
+    }
+
+    pub fn delete_mdb(&self, entry: &MdbEntry) -> Result<(), Error> {
+        modify_mdb_in(Some(self), entry, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netlink_packet_route::nlas::NLA_F_NESTED;
+    use netlink_packet_route::NUD_STALE;
+
+    fn mac() -> MacAddr6 {
+        MacAddr6::new([2, 0, 0, 0, 0, 1])
+    }
+
+    /// Returns the `struct br_mdb_entry`, sent to the kernel.
+    fn set_entry(message: &MdbMessage) -> Vec<u8> {
+        let mut nlas = NlasIterator::new(&message.attributes);
+        let nla = nlas.next().unwrap().unwrap();
+        assert_eq!(nla.kind(), MDBA_SET_ENTRY);
+        assert!(nlas.next().is_none());
+        nla.value().to_vec()
+    }
+
+    #[test]
+    fn mdb_entry_layout() {
+        let entry = MdbEntry::new(
+            &interface(2, None),
+            &interface(5, None),
+            "239.1.2.3".parse().unwrap(),
+        )
+        .vlan(10);
+        let message = entry.to_message(RTM_NEWMDB);
+        assert_eq!(message.message_type, RTM_NEWMDB);
+
+        // struct br_port_msg: family, padding, bridge index
+        let mut header = vec![AF_BRIDGE as u8, 0, 0, 0];
+        header.extend_from_slice(&2u32.to_ne_bytes());
+        assert_eq!(message.header[..], header);
+
+        // struct br_mdb_entry: port index, state, flags, vlan, group address, protocol, padding
+        let mut expected = 5u32.to_ne_bytes().to_vec();
+        expected.extend_from_slice(&[MDB_PERMANENT, 0]);
+        expected.extend_from_slice(&10u16.to_ne_bytes());
+        expected.extend_from_slice(&[239, 1, 2, 3]);
+        expected.extend_from_slice(&[0; 12]);
+        expected.extend_from_slice(&[0x08, 0x00]);
+        expected.extend_from_slice(&[0; 2]);
+        assert_eq!(set_entry(&message), expected);
+    }
+
+    #[test]
+    fn mdb_entry_layout_ipv6() {
+        let group: Ipv6Addr = "ff0e::1234".parse().unwrap();
+        let entry =
+            MdbEntry::new(&interface(2, None), &interface(5, None), group.into()).permanent(false);
+        let bytes = set_entry(&entry.to_message(RTM_DELMDB));
+
+        assert_eq!(bytes.len(), MDB_ENTRY_LEN);
+        assert_eq!(bytes[4], MDB_TEMPORARY);
+        assert_eq!(bytes[6..8], [0, 0]);
+        assert_eq!(bytes[8..24], group.octets());
+        assert_eq!(bytes[24..26], [0x86, 0xdd]);
+    }
+
+    #[test]
+    fn mdb_entry_round_trip() {
+        for (group, vlan, permanent) in [("239.1.2.3", Some(10), true), ("ff0e::1234", None, false)]
+        {
+            let mut entry = MdbEntry::new(
+                &interface(2, None),
+                &interface(5, None),
+                group.parse().unwrap(),
+            )
+            .permanent(permanent);
+            entry.vlan = vlan;
+
+            let bytes = set_entry(&entry.to_message(RTM_NEWMDB));
+            let parsed = MdbEntry::parse(&bytes, 2, None).unwrap().unwrap();
+            assert_eq!(parsed.bridge.0.index, 2);
+            assert_eq!(parsed.port.0.index, 5);
+            assert_eq!(parsed.group, entry.group);
+            assert_eq!(parsed.vlan, vlan);
+            assert_eq!(parsed.permanent, permanent);
+        }
+    }
+
+    #[test]
+    fn mdb_entry_other_protocols() {
+        // Layer 2 entries carry a MAC address and no IP protocol
+        let mut bytes = [0; MDB_ENTRY_LEN];
+        bytes[8..14].copy_from_slice(mac().as_slice());
+        assert!(MdbEntry::parse(&bytes, 2, None).unwrap().is_none());
+
+        assert!(MdbEntry::parse(&bytes[..26], 2, None).is_err());
+    }
+
+    #[test]
+    fn mdb_dump() {
+        let entry = |port: u32, group: &str| {
+            let entry = MdbEntry::new(
+                &interface(2, None),
+                &interface(port, None),
+                group.parse().unwrap(),
+            );
+            let bytes = set_entry(&entry.to_message(RTM_NEWMDB));
+            let mut info = vec![];
+            netlink::put_attribute(&mut info, MDBA_MDB_ENTRY_INFO, &bytes);
+            info
+        };
+
+        let mut groups = vec![];
+        let mut group = entry(5, "239.1.1.1");
+        group.extend(entry(6, "239.1.1.1"));
+        netlink::put_attribute(&mut groups, MDBA_MDB_ENTRY | NLA_F_NESTED, &group);
+        netlink::put_attribute(
+            &mut groups,
+            MDBA_MDB_ENTRY | NLA_F_NESTED,
+            &entry(5, "ff0e::1"),
+        );
+
+        let mut message = MdbMessage::new(RTM_GETMDB);
+        message.header[0] = AF_BRIDGE as _;
+        message.header[4..8].copy_from_slice(&2u32.to_ne_bytes());
+        netlink::put_attribute(&mut message.attributes, MDBA_MDB | NLA_F_NESTED, &groups);
+        // Router ports (MDBA_ROUTER) are skipped
+        netlink::put_attribute(&mut message.attributes, 2 | NLA_F_NESTED, &[]);
+
+        let entries: Vec<_> = MdbEntry::from_message(&netlink::reparse(message), None)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.bridge.0.index, entry.port.0.index, entry.group))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (2, 5, "239.1.1.1".parse().unwrap()),
+                (2, 6, "239.1.1.1".parse().unwrap()),
+                (2, 5, "ff0e::1".parse().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn fdb_entry_to_message() {
+        let entry = FdbEntry::new(mac(), &interface(5, None)).vlan(10);
+        let message = entry.to_message();
+        assert_eq!(message.header.family, AF_BRIDGE as u8);
+        assert_eq!(message.header.ifindex, 5);
+        assert_eq!(message.header.state, NUD_NOARP);
+        assert_eq!(message.header.flags, NTF_MASTER);
+        assert!(message
+            .nlas
+            .contains(&NeighbourNla::LinkLocalAddress(mac().as_slice().to_vec())));
+        assert!(message.nlas.contains(&NeighbourNla::Vlan(10)));
+
+        let entry = FdbEntry::new(mac(), &interface(5, None))
+            .state(FdbState::Permanent)
+            .master(false)
+            .device(true)
+            .extern_learn(true)
+            .remote("192.0.2.1".parse().unwrap());
+        let message = entry.to_message();
+        assert_eq!(message.header.state, NUD_PERMANENT);
+        assert_eq!(message.header.flags, NTF_SELF | NTF_EXT_LEARNED);
+        assert!(message
+            .nlas
+            .contains(&NeighbourNla::Destination(vec![192, 0, 2, 1])));
+
+        let entry = FdbEntry::new(mac(), &interface(5, None)).state(FdbState::Dynamic);
+        assert_eq!(entry.to_message().header.state, NUD_REACHABLE);
+    }
+
+    #[test]
+    fn fdb_entry_round_trip() {
+        for state in [FdbState::Dynamic, FdbState::Static, FdbState::Permanent] {
+            let entry = FdbEntry::new(mac(), &interface(5, None))
+                .vlan(10)
+                .state(state)
+                .remote("fe80::1".parse().unwrap());
+            let RtnlMessage::NewNeighbour(message) =
+                netlink::reparse(RtnlMessage::NewNeighbour(entry.to_message()))
+            else {
+                panic!("expected a neighbor");
+            };
+
+            let parsed = FdbEntry::from_message(message, None).unwrap().unwrap();
+            assert_eq!(parsed.mac, mac());
+            assert_eq!(parsed.vlan, Some(10));
+            assert_eq!(parsed.port.0.index, 5);
+            assert_eq!(parsed.remote, entry.remote);
+            assert_eq!(parsed.state, state);
+            assert!(parsed.master);
+            assert!(!parsed.device);
+        }
+    }
+
+    #[test]
+    fn fdb_entry_from_dump() {
+        let message = |state, flags, master: Option<u32>| {
+            let mut message = NeighbourMessage::default();
+            message.header.family = AF_BRIDGE as _;
+            message.header.ifindex = 5;
+            message.header.state = state;
+            message.header.flags = flags;
+            message
+                .nlas
+                .push(NeighbourNla::LinkLocalAddress(mac().as_slice().to_vec()));
+            if let Some(index) = master {
+                message
+                    .nlas
+                    .push(NeighbourNla::Master(index.to_ne_bytes().to_vec()));
+            }
+            message
+        };
+
+        // Bridge FDB dumps carry NDA_MASTER instead of NTF_MASTER
+        let entry = FdbEntry::from_message(message(NUD_STALE, 0, Some(2)), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.state, FdbState::Dynamic);
+        assert_eq!(entry.bridge.map(|bridge| bridge.0.index), Some(2));
+        assert!(entry.master);
+        assert!(!entry.device);
+
+        // Entries of the port device itself, e.g. its own address
+        let entry = FdbEntry::from_message(message(NUD_PERMANENT, NTF_SELF, Some(2)), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.state, FdbState::Permanent);
+        assert!(!entry.master);
+        assert!(entry.device);
+
+        let entry = FdbEntry::from_message(message(NUD_NOARP, NTF_SELF, None), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.state, FdbState::Static);
+        assert!(entry.bridge.is_none());
+        assert!(!entry.master);
+
+        let mut malformed = message(NUD_NOARP, 0, None);
+        malformed.nlas.push(NeighbourNla::Master(vec![2, 0]));
+        assert!(FdbEntry::from_message(malformed, None).is_err());
+
+        let mut no_mac = message(NUD_NOARP, 0, None);
+        no_mac.nlas.clear();
+        assert!(FdbEntry::from_message(no_mac, None).unwrap().is_none());
+
+        let mut neighbor = message(NUD_NOARP, 0, None);
+        neighbor.header.family = libc::AF_INET as _;
+        assert!(FdbEntry::from_message(neighbor, None).unwrap().is_none());
+    }
+}
@@ -0,0 +1,659 @@
+use super::handle::dump_links;
+use super::monitor::{AddressInfo, Event, LinkInfo, Monitor, MonitorGroup};
+use super::route::list_routes_in;
+use super::{netlink, NetnsHandle, Route, RouteFilter, RouteType};
+use crate::Error;
+use ipnet::IpNet;
+use netlink_packet_route::{AddressMessage, RtnlMessage, NLM_F_DUMP};
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+
+const GROUPS: [MonitorGroup; 5] = [
+    MonitorGroup::Link,
+    MonitorGroup::Ipv4Address,
+    MonitorGroup::Ipv6Address,
+    MonitorGroup::Ipv4Route,
+    MonitorGroup::Ipv6Route,
+];
+
+/// Identity of a route, as seen by the kernel.
+///
+/// Besides the lookup key, routes with the same destination and metric are told apart by their
+/// next hops, e.g. IPv6 routes through different gateways.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct RouteKey {
+    table: u32,
+    destination: IpNet,
+    tos: u8,
+    route_type: u8,
+    metric: Option<u32>,
+    interface: Option<u32>,
+    gateway: Option<IpAddr>,
+    nexthop_id: Option<u32>,
+    /// Gateways and interfaces of a multipath route.
+    next_hops: Vec<(Option<IpAddr>, Option<u32>)>,
+}
+
+impl RouteKey {
+    /// Returns `true` if replacing a route with `key` removes this one, i.e. it has the same
+    /// table, destination, type of service and metric.
+    fn replaces(&self, key: &RouteKey) -> bool {
+        self.table == key.table
+            && self.destination == key.destination
+            && self.tos == key.tos
+            && self.metric == key.metric
+    }
+}
+
+impl From<&Route> for RouteKey {
+    fn from(route: &Route) -> Self {
+        Self {
+            table: route.table,
+            destination: route.destination,
+            tos: route.tos,
+            route_type: route.route_type.into(),
+            metric: route.metric,
+            interface: route.interface.as_ref().map(|interface| interface.0.index),
+            gateway: route.gateway,
+            nexthop_id: route.nexthop_id,
+            next_hops: route
+                .next_hops
+                .iter()
+                .map(|hop| {
+                    let interface = hop.interface.as_ref().map(|interface| interface.0.index);
+                    (hop.gateway, interface)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Links, addresses and routes of a namespace, kept in sync with the kernel.
+///
+/// The Cache loads a snapshot once, then applies change notifications on [`update`](Self::update)
+/// or [`wait_for_changes`](Self::wait_for_changes). Lookups don't issue syscalls, and see a
+/// consistent state between updates. If notifications are lost, the snapshot is reloaded
+/// transparently.
+pub struct Cache {
+    monitor: Monitor,
+    netns: Option<NetnsHandle>,
+    links: BTreeMap<u32, LinkInfo>,
+    addresses: BTreeMap<(u32, IpNet), AddressInfo>,
+    routes: BTreeMap<RouteKey, Route>,
+    /// Index of `links` by name.
+    names: BTreeMap<String, u32>,
+    /// Index of `addresses` by address. An address may be assigned to several links.
+    owners: BTreeSet<(IpAddr, u32)>,
+}
+
+impl Cache {
+    /// Loads a Cache for the namespace of the calling thread.
+    pub fn new() -> Result<Self, Error> {
+        Self::new_in(None)
+    }
+
+    pub(crate) fn new_in(netns: Option<&NetnsHandle>) -> Result<Self, Error> {
+        // Subscribed before the snapshot is loaded, so that no change is missed in between
+        let monitor = Monitor::new_in(netns, &GROUPS)?;
+
+        let mut cache = Cache {
+            monitor,
+            netns: netns.cloned(),
+            links: BTreeMap::new(),
+            addresses: BTreeMap::new(),
+            routes: BTreeMap::new(),
+            names: BTreeMap::new(),
+            owners: BTreeSet::new(),
+        };
+        cache.reload(&mut vec![])?;
+        Ok(cache)
+    }
+
+    /// Applies pending notifications without blocking. Returns the changes, made to the Cache.
+    pub fn update(&mut self) -> Result<Vec<Event>, Error> {
+        let mut changes = vec![];
+        while let Some(event) = self.monitor.next_event_timeout(Duration::ZERO)? {
+            self.apply(event, &mut changes)?;
+        }
+        Ok(changes)
+    }
+
+    /// Waits until the Cache changes, then applies all pending notifications. Returns the changes,
+    /// which is empty if nothing has changed within `timeout`.
+    pub fn wait_for_changes(&mut self, timeout: Duration) -> Result<Vec<Event>, Error> {
+        let deadline = Instant::now() + timeout;
+        let mut changes = vec![];
+        while changes.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(event) = self.monitor.next_event_timeout(remaining)? else {
+                break;
+            };
+            self.apply(event, &mut changes)?;
+        }
+        changes.extend(self.update()?);
+        Ok(changes)
+    }
+
+    pub fn links(&self) -> impl Iterator<Item = &LinkInfo> {
+        self.links.values()
+    }
+
+    pub fn link(&self, index: u32) -> Option<&LinkInfo> {
+        self.links.get(&index)
+    }
+
+    pub fn link_by_name(&self, name: &str) -> Option<&LinkInfo> {
+        self.names.get(name).and_then(|index| self.link(*index))
+    }
+
+    /// Returns the link, given address is assigned to. If there are several, returns the one with
+    /// the lowest index.
+    pub fn link_by_address(&self, address: IpAddr) -> Option<&LinkInfo> {
+        self.owners
+            .range((address, 0)..)
+            .next()
+            .filter(|(owned, _)| *owned == address)
+            .and_then(|(_, index)| self.link(*index))
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = &AddressInfo> {
+        self.addresses.values()
+    }
+
+    /// Returns addresses, assigned to the link with given index.
+    pub fn addresses_of(&self, index: u32) -> impl Iterator<Item = &AddressInfo> {
+        self.addresses
+            .range((index, IpNet::default())..)
+            .take_while(move |((address_index, _), _)| *address_index == index)
+            .map(|(_, info)| info)
+    }
+
+    /// Returns routes from all routing tables.
+    pub fn routes(&self) -> impl Iterator<Item = &Route> {
+        self.routes.values()
+    }
+
+    fn apply(&mut self, event: Event, changes: &mut Vec<Event>) -> Result<(), Error> {
+        let change = match event {
+            Event::LinkAdded(info) | Event::LinkChanged(info) => {
+                let index = info.interface.0.index;
+                let old = self.links.insert(index, info.clone());
+                if let Some(old) = &old {
+                    self.unindex_name(&old.name, index);
+                }
+                self.names.insert(info.name.clone(), index);
+                match old {
+                    None => Some(Event::LinkAdded(info)),
+                    Some(old) if !same_link(&old, &info) => {
+                        if old.is_up() && !info.is_up() {
+                            self.forget_routes(index, true, changes);
+                        }
+                        Some(Event::LinkChanged(info))
+                    }
+                    Some(_) => None,
+                }
+            }
+            Event::LinkRemoved(info) => {
+                let index = info.interface.0.index;
+                self.forget_routes(index, false, changes);
+                let addresses: Vec<_> = self.addresses_of(index).cloned().collect();
+                for address in addresses {
+                    self.remove_address(index, address.network);
+                    changes.push(Event::AddressRemoved(address));
+                }
+                self.links.remove(&index).map(|link| {
+                    self.unindex_name(&link.name, index);
+                    Event::LinkRemoved(info)
+                })
+            }
+            Event::AddressAdded(info) => {
+                let index = info.interface.0.index;
+                self.owners.insert((info.network.addr(), index));
+                match self.addresses.insert((index, info.network), info.clone()) {
+                    Some(old) if old.flags == info.flags => None,
+                    _ => Some(Event::AddressAdded(info)),
+                }
+            }
+            Event::AddressRemoved(info) => self
+                .remove_address(info.interface.0.index, info.network)
+                .map(|_| Event::AddressRemoved(info)),
+            Event::RouteAdded { route, replaced } => {
+                let key = RouteKey::from(&route);
+                if replaced {
+                    // The kernel doesn't notify about the route, that was replaced. It's found by
+                    // the lookup key, and may have had other next hops.
+                    self.routes.retain(|other, _| !other.replaces(&key));
+                }
+                self.routes.insert(key, route.clone());
+                Some(Event::RouteAdded { route, replaced })
+            }
+            Event::RouteRemoved(route) => self
+                .routes
+                .remove(&RouteKey::from(&route))
+                .map(|_| Event::RouteRemoved(route)),
+            Event::Resync => return self.reload(changes),
+            _ => None,
+        };
+        changes.extend(change);
+        Ok(())
+    }
+
+    /// Removes the name of a link from the index, unless another link has taken it meanwhile.
+    fn unindex_name(&mut self, name: &str, index: u32) {
+        if self.names.get(name) == Some(&index) {
+            self.names.remove(name);
+        }
+    }
+
+    fn remove_address(&mut self, index: u32, network: IpNet) -> Option<AddressInfo> {
+        let info = self.addresses.remove(&(index, network))?;
+        // The same address may be assigned with another prefix length
+        let address = network.addr();
+        if !self
+            .addresses_of(index)
+            .any(|other| other.network.addr() == address)
+        {
+            self.owners.remove(&(address, index));
+        }
+        Some(info)
+    }
+
+    /// Removes routes through the link with given index. The kernel flushes them without
+    /// notifications, when a link is deleted, and IPv4 routes other than local ones, when it goes
+    /// down.
+    fn forget_routes(&mut self, index: u32, down: bool, changes: &mut Vec<Event>) {
+        let keys: Vec<_> = self
+            .routes
+            .iter()
+            .filter(|(key, route)| {
+                key.interface == Some(index)
+                    && (!down
+                        || matches!(key.destination, IpNet::V4(_))
+                            && route.route_type != RouteType::Local)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            if let Some(route) = self.routes.remove(&key) {
+                changes.push(Event::RouteRemoved(route));
+            }
+        }
+    }
+
+    /// Replaces the contents with a fresh snapshot, reporting the differences as changes.
+    fn reload(&mut self, changes: &mut Vec<Event>) -> Result<(), Error> {
+        let netns = self.netns.as_ref();
+
+        let links = dump_links(netns)?
+            .into_iter()
+            .map(|link| LinkInfo::from_message(link, netns))
+            .collect();
+
+        let socket = netlink::socket(netns)?;
+        let responses = netlink::request(
+            &socket,
+            RtnlMessage::GetAddress(AddressMessage::default()),
+            NLM_F_DUMP,
+        )?;
+        let mut addresses = vec![];
+        for response in responses {
+            let RtnlMessage::NewAddress(message) = response else {
+                continue;
+            };
+            addresses.extend(AddressInfo::from_message(message, netns)?);
+        }
+
+        let routes = list_routes_in(netns, &RouteFilter::new())?;
+        self.load(links, addresses, routes, changes);
+        Ok(())
+    }
+
+    /// Replaces the contents with given snapshot. See [`reload`](Self::reload).
+    fn load(
+        &mut self,
+        links: Vec<LinkInfo>,
+        addresses: Vec<AddressInfo>,
+        routes: Vec<Route>,
+        changes: &mut Vec<Event>,
+    ) {
+        let links: BTreeMap<_, _> = links
+            .into_iter()
+            .map(|info| (info.interface.0.index, info))
+            .collect();
+        let addresses: BTreeMap<_, _> = addresses
+            .into_iter()
+            .map(|info| ((info.interface.0.index, info.network), info))
+            .collect();
+        let routes: BTreeMap<_, _> = routes
+            .into_iter()
+            .map(|route| (RouteKey::from(&route), route))
+            .collect();
+
+        for (index, info) in &self.links {
+            if !links.contains_key(index) {
+                changes.push(Event::LinkRemoved(info.clone()));
+            }
+        }
+        for (index, info) in &links {
+            match self.links.get(index) {
+                None => changes.push(Event::LinkAdded(info.clone())),
+                Some(old) if !same_link(old, info) => {
+                    changes.push(Event::LinkChanged(info.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, info) in &self.addresses {
+            if !addresses.contains_key(key) {
+                changes.push(Event::AddressRemoved(info.clone()));
+            }
+        }
+        for (key, info) in &addresses {
+            match self.addresses.get(key) {
+                Some(old) if old.flags == info.flags => {}
+                _ => changes.push(Event::AddressAdded(info.clone())),
+            }
+        }
+        for (key, route) in &self.routes {
+            if !routes.contains_key(key) {
+                changes.push(Event::RouteRemoved(route.clone()));
+            }
+        }
+        for (key, route) in &routes {
+            if !self.routes.contains_key(key) {
+                changes.push(Event::RouteAdded {
+                    route: route.clone(),
+                    replaced: false,
+                });
+            }
+        }
+
+        self.names = links
+            .iter()
+            .map(|(index, info)| (info.name.clone(), *index))
+            .collect();
+        self.owners = addresses
+            .keys()
+            .map(|(index, network)| (network.addr(), *index))
+            .collect();
+        self.links = links;
+        self.addresses = addresses;
+        self.routes = routes;
+    }
+}
+
+impl AsRawFd for Cache {
+    /// Returns the notification socket, which becomes readable when an update is pending.
+    fn as_raw_fd(&self) -> RawFd {
+        self.monitor.as_raw_fd()
+    }
+}
+
+/// Compares attributes, tracked by [`LinkInfo`]. The kernel also notifies about changes of others.
+fn same_link(a: &LinkInfo, b: &LinkInfo) -> bool {
+    a.name == b.name && a.flags == b.flags && a.mtu == b.mtu && a.hwaddress == b.hwaddress
+}
+
+impl NetnsHandle {
+    /// Loads a Cache for this namespace.
+    pub fn cache(&self) -> Result<Cache, Error> {
+        Cache::new_in(Some(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::linux::{RouteMetrics, RouteProtocol, RouteScope};
+    use crate::Interface;
+    use libc::IFF_UP;
+    use netlink_sys::constants::NETLINK_ROUTE;
+    use netlink_sys::Socket;
+
+    /// Creates an empty Cache. Its Monitor is not subscribed.
+    fn cache() -> Cache {
+        let socket = Socket::new(NETLINK_ROUTE).unwrap();
+        Cache {
+            monitor: Monitor::subscribe(socket, None, &[], &[]).unwrap(),
+            netns: None,
+            links: BTreeMap::new(),
+            addresses: BTreeMap::new(),
+            routes: BTreeMap::new(),
+            names: BTreeMap::new(),
+            owners: BTreeSet::new(),
+        }
+    }
+
+    fn link(index: u32, flags: u32) -> LinkInfo {
+        LinkInfo {
+            interface: Interface::from_index_unchecked(index),
+            name: format!("test{index}"),
+            flags,
+            mtu: Some(1500),
+            hwaddress: None,
+        }
+    }
+
+    fn address(index: u32, network: &str) -> AddressInfo {
+        AddressInfo {
+            interface: Interface::from_index_unchecked(index),
+            network: network.parse().unwrap(),
+            flags: 0,
+        }
+    }
+
+    fn route(destination: &str, gateway: &str, index: u32) -> Route {
+        Route {
+            destination: destination.parse().unwrap(),
+            gateway: Some(gateway.parse().unwrap()),
+            interface: Some(Interface::from_index_unchecked(index)),
+            source: None,
+            metric: Some(100),
+            tos: 0,
+            table: Route::TABLE_MAIN,
+            protocol: RouteProtocol::Static,
+            scope: RouteScope::Universe,
+            route_type: RouteType::Unicast,
+            next_hops: vec![],
+            nexthop_id: None,
+            metrics: RouteMetrics::default(),
+        }
+    }
+
+    fn apply(cache: &mut Cache, event: Event) -> Vec<Event> {
+        let mut changes = vec![];
+        cache.apply(event, &mut changes).unwrap();
+        changes
+    }
+
+    fn gateways(cache: &Cache) -> Vec<IpAddr> {
+        cache.routes().filter_map(|route| route.gateway).collect()
+    }
+
+    #[test]
+    fn links() {
+        let mut cache = cache();
+        let changes = apply(&mut cache, Event::LinkChanged(link(1, 0)));
+        assert!(matches!(changes[..], [Event::LinkAdded(_)]));
+        assert_eq!(cache.link_by_name("test1").unwrap().interface.0.index, 1);
+
+        // Notifications about untracked attributes aren't reported
+        assert!(apply(&mut cache, Event::LinkChanged(link(1, 0))).is_empty());
+
+        let mut renamed = link(1, 0);
+        renamed.name = "renamed".to_string();
+        let changes = apply(&mut cache, Event::LinkChanged(renamed));
+        assert!(matches!(changes[..], [Event::LinkChanged(_)]));
+        assert!(cache.link_by_name("test1").is_none());
+        assert!(cache.link_by_name("renamed").is_some());
+    }
+
+    #[test]
+    fn addresses() {
+        let mut cache = cache();
+        apply(&mut cache, Event::LinkAdded(link(1, 0)));
+        let changes = apply(&mut cache, Event::AddressAdded(address(1, "10.0.0.1/24")));
+        assert!(matches!(changes[..], [Event::AddressAdded(_)]));
+        apply(&mut cache, Event::AddressAdded(address(1, "10.0.0.1/32")));
+        let ip = "10.0.0.1".parse().unwrap();
+        assert_eq!(cache.link_by_address(ip).unwrap().interface.0.index, 1);
+
+        // The address is still assigned with the other prefix length
+        apply(&mut cache, Event::AddressRemoved(address(1, "10.0.0.1/24")));
+        assert!(cache.link_by_address(ip).is_some());
+        let changes = apply(&mut cache, Event::AddressRemoved(address(1, "10.0.0.1/32")));
+        assert!(matches!(changes[..], [Event::AddressRemoved(_)]));
+        assert!(cache.link_by_address(ip).is_none());
+        assert!(apply(&mut cache, Event::AddressRemoved(address(1, "10.0.0.1/32"))).is_empty());
+    }
+
+    #[test]
+    fn route_added_and_removed() {
+        let mut cache = cache();
+        for gateway in ["fe80::1", "fe80::2"] {
+            let route = route("2001:db8::/64", gateway, 1);
+            let event = Event::RouteAdded {
+                route,
+                replaced: false,
+            };
+            let changes = apply(&mut cache, event);
+            assert!(matches!(changes[..], [Event::RouteAdded { .. }]));
+        }
+        // Routes, that differ only in their gateways, are distinct
+        assert_eq!(cache.routes().count(), 2);
+
+        let removed = Event::RouteRemoved(route("2001:db8::/64", "fe80::1", 1));
+        let changes = apply(&mut cache, removed.clone());
+        assert!(matches!(changes[..], [Event::RouteRemoved(_)]));
+        assert_eq!(gateways(&cache), ["fe80::2".parse::<IpAddr>().unwrap()]);
+        assert!(apply(&mut cache, removed).is_empty());
+    }
+
+    #[test]
+    fn route_replaced() {
+        let mut cache = cache();
+        let event = Event::RouteAdded {
+            route: route("10.1.0.0/16", "10.0.0.1", 1),
+            replaced: false,
+        };
+        apply(&mut cache, event);
+        let mut other_metric = route("10.1.0.0/16", "10.0.0.3", 1);
+        other_metric.metric = Some(200);
+        let event = Event::RouteAdded {
+            route: other_metric,
+            replaced: false,
+        };
+        apply(&mut cache, event);
+
+        let event = Event::RouteAdded {
+            route: route("10.1.0.0/16", "10.0.0.2", 2),
+            replaced: true,
+        };
+        let changes = apply(&mut cache, event);
+        assert!(matches!(
+            changes[..],
+            [Event::RouteAdded { replaced: true, .. }]
+        ));
+
+        let mut gateways = gateways(&cache);
+        gateways.sort();
+        let expected: Vec<IpAddr> = vec!["10.0.0.2".parse().unwrap(), "10.0.0.3".parse().unwrap()];
+        assert_eq!(gateways, expected);
+    }
+
+    #[test]
+    fn link_removed() {
+        let mut cache = cache();
+        apply(&mut cache, Event::LinkAdded(link(1, IFF_UP as _)));
+        apply(&mut cache, Event::LinkAdded(link(2, IFF_UP as _)));
+        apply(&mut cache, Event::AddressAdded(address(1, "10.0.0.10/24")));
+        for (gateway, index) in [("10.0.0.1", 1), ("10.0.1.1", 2)] {
+            let event = Event::RouteAdded {
+                route: route("10.1.0.0/16", gateway, index),
+                replaced: false,
+            };
+            apply(&mut cache, event);
+        }
+
+        let changes = apply(&mut cache, Event::LinkRemoved(link(1, IFF_UP as _)));
+        assert!(matches!(
+            changes[..],
+            [
+                Event::RouteRemoved(_),
+                Event::AddressRemoved(_),
+                Event::LinkRemoved(_)
+            ]
+        ));
+        assert!(cache.link(1).is_none());
+        assert!(cache.link_by_name("test1").is_none());
+        assert_eq!(cache.addresses().count(), 0);
+        assert_eq!(gateways(&cache), ["10.0.1.1".parse::<IpAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn link_down() {
+        let mut cache = cache();
+        apply(&mut cache, Event::LinkAdded(link(1, IFF_UP as _)));
+        let mut local = route("10.0.0.10/32", "10.0.0.10", 1);
+        local.route_type = RouteType::Local;
+        for route in [
+            route("10.1.0.0/16", "10.0.0.1", 1),
+            route("2001:db8::/64", "fe80::1", 1),
+            local,
+        ] {
+            let event = Event::RouteAdded {
+                route,
+                replaced: false,
+            };
+            apply(&mut cache, event);
+        }
+
+        // The kernel flushes IPv4 routes, other than local ones, without notifications
+        let changes = apply(&mut cache, Event::LinkChanged(link(1, 0)));
+        assert!(matches!(
+            changes[..],
+            [Event::RouteRemoved(_), Event::LinkChanged(_)]
+        ));
+        assert_eq!(cache.routes().count(), 2);
+    }
+
+    #[test]
+    fn resync() {
+        let mut cache = cache();
+        let mut changes = vec![];
+        cache.load(
+            vec![link(1, 0), link(2, 0)],
+            vec![address(1, "10.0.0.10/24")],
+            vec![route("10.1.0.0/16", "10.0.0.1", 1)],
+            &mut changes,
+        );
+        assert_eq!(changes.len(), 4);
+
+        // Differences to the previous snapshot are reported
+        let mut changes = vec![];
+        cache.load(
+            vec![link(1, IFF_UP as _), link(3, 0)],
+            vec![address(1, "10.0.0.10/24")],
+            vec![route("10.1.0.0/16", "10.0.0.2", 1)],
+            &mut changes,
+        );
+        assert!(matches!(
+            changes[..],
+            [
+                Event::LinkRemoved(_),
+                Event::LinkChanged(_),
+                Event::LinkAdded(_),
+                Event::RouteRemoved(_),
+                Event::RouteAdded {
+                    replaced: false,
+                    ..
+                },
+            ]
+        ));
+        assert!(cache.link_by_name("test2").is_none());
+        assert!(cache.link_by_name("test3").is_some());
+        assert_eq!(gateways(&cache), ["10.0.0.2".parse::<IpAddr>().unwrap()]);
+    }
+}
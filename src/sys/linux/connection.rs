@@ -0,0 +1,491 @@
+use super::handle::{make_address_message, make_link_message, LinkChange};
+use super::monitor::{AddressInfo, LinkInfo};
+use super::neighbor::NeighborOperation;
+use super::route::RouteOperation;
+use super::{netlink, Neighbor, NeighborFilter, NetnsHandle, Route, RouteFilter, RouteSpec};
+use crate::{Error, Interface};
+use advmac::MacAddr6;
+use ipnet::IpNet;
+use libc::{IFF_UP, MSG_PEEK, MSG_TRUNC};
+use log::debug;
+use netlink_packet_core::{NetlinkDeserializable, NetlinkSerializable};
+use netlink_packet_route::{
+    AddressMessage, LinkMessage, NetlinkMessage, NetlinkPayload, RtnlMessage, NLM_F_CREATE,
+    NLM_F_DUMP, NLM_F_REPLACE,
+};
+use netlink_sys::constants::NETLINK_ROUTE;
+use netlink_sys::Socket;
+use std::fmt::Debug;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// Initial size of the receive buffer. Dumps are sent in datagrams of up to this size, unless a
+/// single entry doesn't fit.
+const BUFFER_SIZE: usize = 32 * 1024;
+
+/// Maximum number of requests, sent before waiting for their acknowledgements. Every
+/// acknowledgement is a separate datagram, and the kernel drops them once the receive buffer of
+/// the socket is full.
+const WINDOW: usize = 64;
+
+/// Rtnetlink socket, shared by many operations.
+///
+/// Unlike the free functions, which open a socket per call, a Connection keeps its socket and
+/// receive buffer. Requests are numbered per Connection, and responses to other requests, e.g.
+/// late ones after a failed call, are discarded. Changes, queued in a [`Batch`], are pipelined,
+/// so that provisioning many objects takes a few round trips.
+pub struct Connection {
+    socket: Socket,
+    netns: Option<NetnsHandle>,
+    sequence_number: u32,
+    buffer: Vec<u8>,
+}
+
+impl Connection {
+    /// Connects to the namespace of the calling thread.
+    pub fn new() -> Result<Self, Error> {
+        Self::new_in(None)
+    }
+
+    pub(crate) fn new_in(netns: Option<&NetnsHandle>) -> Result<Self, Error> {
+        Self::with_protocol(netns, NETLINK_ROUTE)
+    }
+
+    /// Connects to another netlink protocol, e.g. generic netlink. Only [`request`](Self::request)
+    /// is meaningful for such a Connection.
+    pub(crate) fn with_protocol(
+        netns: Option<&NetnsHandle>,
+        protocol: isize,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            socket: netlink::open(netns, protocol)?,
+            netns: netns.cloned(),
+            sequence_number: 0,
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+        })
+    }
+
+    /// Returns the namespace, this Connection is bound to, or `None` for the namespace of the
+    /// thread, that created it.
+    pub fn netns(&self) -> Option<&NetnsHandle> {
+        self.netns.as_ref()
+    }
+
+    pub fn list_interfaces(&mut self) -> Result<Vec<LinkInfo>, Error> {
+        let responses = self.request(RtnlMessage::GetLink(LinkMessage::default()), NLM_F_DUMP)?;
+        Ok(responses
+            .into_iter()
+            .filter_map(|response| match response {
+                RtnlMessage::NewLink(link) => Some(LinkInfo::from_message(link, self.netns())),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Returns addresses of all interfaces.
+    pub fn list_addresses(&mut self) -> Result<Vec<AddressInfo>, Error> {
+        let responses = self.request(
+            RtnlMessage::GetAddress(AddressMessage::default()),
+            NLM_F_DUMP,
+        )?;
+
+        let mut addresses = vec![];
+        for response in responses {
+            let RtnlMessage::NewAddress(message) = response else {
+                continue;
+            };
+            addresses.extend(AddressInfo::from_message(message, self.netns())?);
+        }
+        Ok(addresses)
+    }
+
+    /// Returns routes from all routing tables, matching given filter.
+    pub fn list_routes(&mut self, filter: &RouteFilter) -> Result<Vec<Route>, Error> {
+        let responses = self.request(filter.to_message(), NLM_F_DUMP)?;
+        filter.collect(responses, self.netns())
+    }
+
+    /// Returns neighbor cache entries, matching given filter.
+    pub fn list_neighbors(&mut self, filter: &NeighborFilter) -> Result<Vec<Neighbor>, Error> {
+        let responses = self.request(filter.to_message(), NLM_F_DUMP)?;
+        filter.collect(responses, self.netns())
+    }
+
+    /// Sends the requests, queued in `batch`, and waits until all of them are acknowledged.
+    ///
+    /// Returns the outcome of every request in the order they were queued. A failed request
+    /// doesn't stop the others. The outer error is returned, if the exchange itself fails, in
+    /// which case the requests may have been partially applied.
+    pub fn execute(&mut self, batch: Batch) -> Result<Vec<Result<(), Error>>, Error> {
+        let mut results = Vec::with_capacity(batch.requests.len());
+        let mut buf = vec![];
+        for window in batch.requests.chunks(WINDOW) {
+            buf.clear();
+            let mut acks = Acks::new(self.sequence_number.wrapping_add(1));
+            for request in window {
+                // Invalid requests keep their sequence numbers, so that positions stay aligned
+                let sequence_number = self.next_sequence_number();
+                match request {
+                    Some((message, flags)) => {
+                        netlink::encode_message(&mut buf, message.clone(), *flags, sequence_number);
+                        acks.expect();
+                    }
+                    None => acks.reject(Error::InvalidParameter),
+                }
+            }
+            if acks.pending > 0 {
+                self.socket.send(&buf, 0)?;
+            }
+            while acks.pending > 0 {
+                self.receive()?;
+                acks.decode(&self.buffer)?;
+            }
+            results.extend(acks.into_results());
+        }
+        Ok(results)
+    }
+
+    /// Sends a request and collects all messages, sent in response, until the request is
+    /// acknowledged or the dump is done.
+    pub(crate) fn request<T>(&mut self, message: T, flags: u16) -> Result<Vec<T>, Error>
+    where
+        T: NetlinkSerializable + NetlinkDeserializable + Debug,
+    {
+        let sequence_number = self.next_sequence_number();
+        let mut buf = vec![];
+        netlink::encode_message(&mut buf, message, flags, sequence_number);
+        self.socket.send(&buf, 0)?;
+
+        let mut responses = vec![];
+        loop {
+            self.receive()?;
+            if netlink::decode_responses(&self.buffer, sequence_number, &mut responses)? {
+                return Ok(responses);
+            }
+        }
+    }
+
+    /// Joins a multicast group of the protocol.
+    pub(crate) fn add_membership(&mut self, group: u32) -> Result<(), Error> {
+        self.socket.add_membership(group)?;
+        Ok(())
+    }
+
+    /// Waits for a datagram and returns notifications in it, i.e. messages, which don't answer a
+    /// request.
+    pub(crate) fn notifications<T>(&mut self) -> Result<Vec<T>, Error>
+    where
+        T: NetlinkDeserializable + Debug,
+    {
+        self.receive()?;
+        let mut notifications = vec![];
+        netlink::decode_notifications(&self.buffer, &mut notifications)?;
+        Ok(notifications)
+    }
+
+    fn next_sequence_number(&mut self) -> u32 {
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+        self.sequence_number
+    }
+
+    /// Receives a datagram into the buffer, growing it, if the datagram doesn't fit.
+    fn receive(&mut self) -> Result<(), Error> {
+        self.buffer.clear();
+        let length = self.socket.recv(&mut self.buffer, MSG_PEEK | MSG_TRUNC)?;
+        self.buffer.clear();
+        self.buffer.reserve(length);
+        self.socket.recv(&mut self.buffer, 0)?;
+        Ok(())
+    }
+}
+
+/// Outcomes of consecutively numbered requests, filled in as their acknowledgements arrive.
+struct Acks {
+    /// Sequence number of the first request.
+    first: u32,
+    acks: Vec<Option<Result<(), Error>>>,
+    /// Number of requests, that aren't acknowledged yet.
+    pending: usize,
+}
+
+impl Acks {
+    fn new(first: u32) -> Self {
+        Self {
+            first,
+            acks: Vec::with_capacity(WINDOW),
+            pending: 0,
+        }
+    }
+
+    /// Adds a request, that awaits an acknowledgement.
+    fn expect(&mut self) {
+        self.acks.push(None);
+        self.pending += 1;
+    }
+
+    /// Adds a request, that wasn't sent.
+    fn reject(&mut self, error: Error) {
+        self.acks.push(Some(Err(error)));
+    }
+
+    /// Records acknowledgements from a received datagram. Responses to other requests, and
+    /// repeated acknowledgements, are skipped.
+    fn decode(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let mut offset = 0;
+        while offset < buf.len() {
+            let response = NetlinkMessage::<RtnlMessage>::deserialize(&buf[offset..])?;
+            debug!("<<< {:?}", response);
+
+            if response.header.length == 0 {
+                break;
+            }
+            offset += netlink::align(response.header.length as _);
+
+            let position = response.header.sequence_number.wrapping_sub(self.first) as usize;
+            let Some(ack @ None) = self.acks.get_mut(position) else {
+                continue;
+            };
+            match response.payload {
+                NetlinkPayload::Ack(_) => *ack = Some(Ok(())),
+                NetlinkPayload::Error(e) => *ack = Some(Err(netlink::error(e))),
+                _ => continue,
+            }
+            self.pending -= 1;
+        }
+        Ok(())
+    }
+
+    /// Returns the outcomes in the order of the requests. Requests, that aren't acknowledged, are
+    /// left out.
+    fn into_results(self) -> impl Iterator<Item = Result<(), Error>> {
+        self.acks.into_iter().flatten()
+    }
+}
+
+impl AsRawFd for Connection {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+/// Changes, applied together by [`Connection::execute`].
+///
+/// Interfaces and routes must belong to the namespace of the Connection, that executes the
+/// Batch.
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    /// `None` for requests, that can't be encoded. They fail with `InvalidParameter`.
+    requests: Vec<Option<(RtnlMessage, u16)>>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    pub fn add_address(&mut self, interface: &Interface, network: IpNet) -> &mut Self {
+        let message = make_address_message(interface.0.index, network);
+        self.push(Some((
+            RtnlMessage::NewAddress(message),
+            NLM_F_CREATE | NLM_F_REPLACE,
+        )))
+    }
+
+    pub fn remove_address(&mut self, interface: &Interface, network: IpNet) -> &mut Self {
+        let message = make_address_message(interface.0.index, network);
+        self.push(Some((RtnlMessage::DelAddress(message), 0)))
+    }
+
+    pub fn set_up(&mut self, interface: &Interface, v: bool) -> &mut Self {
+        self.change_link(interface, LinkChange::Flag(IFF_UP as _, v))
+    }
+
+    pub fn set_mtu(&mut self, interface: &Interface, mtu: u32) -> &mut Self {
+        self.change_link(interface, LinkChange::Mtu(mtu))
+    }
+
+    pub fn set_hwaddress(&mut self, interface: &Interface, hwaddress: MacAddr6) -> &mut Self {
+        self.change_link(interface, LinkChange::HwAddress(hwaddress))
+    }
+
+    pub fn add_route(&mut self, spec: &RouteSpec) -> &mut Self {
+        self.push(RouteOperation::Add.request(spec).ok())
+    }
+
+    pub fn replace_route(&mut self, spec: &RouteSpec) -> &mut Self {
+        self.push(RouteOperation::Replace.request(spec).ok())
+    }
+
+    pub fn delete_route(&mut self, spec: &RouteSpec) -> &mut Self {
+        self.push(RouteOperation::Delete.request(spec).ok())
+    }
+
+    pub fn add_neighbor(&mut self, neighbor: &Neighbor) -> &mut Self {
+        self.push(Some(NeighborOperation::Add.request(neighbor)))
+    }
+
+    pub fn replace_neighbor(&mut self, neighbor: &Neighbor) -> &mut Self {
+        self.push(Some(NeighborOperation::Replace.request(neighbor)))
+    }
+
+    pub fn delete_neighbor(&mut self, neighbor: &Neighbor) -> &mut Self {
+        self.push(Some(NeighborOperation::Delete.request(neighbor)))
+    }
+
+    fn change_link(&mut self, interface: &Interface, change: LinkChange) -> &mut Self {
+        let message = make_link_message(interface.0.index, change);
+        self.push(Some((RtnlMessage::SetLink(message), 0)))
+    }
+
+    fn push(&mut self, request: Option<(RtnlMessage, u16)>) -> &mut Self {
+        self.requests.push(request);
+        self
+    }
+}
+
+impl NetnsHandle {
+    /// Opens a Connection to this namespace.
+    pub fn connection(&self) -> Result<Connection, Error> {
+        Connection::new_in(Some(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netlink_packet_core::{ErrorMessage, NetlinkHeader};
+
+    /// Appends a response to the request with given sequence number to `buf`.
+    fn respond(buf: &mut Vec<u8>, sequence_number: u32, payload: NetlinkPayload<RtnlMessage>) {
+        let mut response = NetlinkMessage {
+            header: NetlinkHeader {
+                sequence_number,
+                ..Default::default()
+            },
+            payload,
+        };
+        response.finalize();
+
+        let offset = buf.len();
+        let length = response.header.length as usize;
+        buf.resize(offset + netlink::align(length), 0);
+        response.serialize(&mut buf[offset..offset + length]);
+    }
+
+    /// Appends an acknowledgement, or an error unless `code` is 0.
+    fn ack(buf: &mut Vec<u8>, sequence_number: u32, code: i32) {
+        let message = ErrorMessage {
+            code: -code,
+            header: vec![0; 16],
+        };
+        let payload = match code {
+            0 => NetlinkPayload::Ack(message),
+            _ => NetlinkPayload::Error(message),
+        };
+        respond(buf, sequence_number, payload);
+    }
+
+    fn acks(first: u32, count: usize) -> Acks {
+        let mut acks = Acks::new(first);
+        for _ in 0..count {
+            acks.expect();
+        }
+        acks
+    }
+
+    fn codes(acks: Acks) -> Vec<Option<i32>> {
+        acks.into_results()
+            .map(|result| result.err().map(|e| e.raw_os_error().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn out_of_order() {
+        let mut acks = acks(1, 3);
+        let mut buf = vec![];
+        ack(&mut buf, 3, 0);
+        ack(&mut buf, 1, libc::EEXIST);
+        acks.decode(&buf).unwrap();
+        assert_eq!(acks.pending, 1);
+
+        buf.clear();
+        ack(&mut buf, 2, 0);
+        acks.decode(&buf).unwrap();
+        assert_eq!(acks.pending, 0);
+        assert_eq!(codes(acks), [Some(libc::EEXIST), None, None]);
+    }
+
+    #[test]
+    fn stray_responses() {
+        let mut acks = acks(10, 2);
+        acks.reject(Error::InvalidParameter);
+        let mut buf = vec![];
+        // Late acknowledgement of an earlier request, and one past the window
+        ack(&mut buf, 9, 0);
+        ack(&mut buf, 13, 0);
+        // Data isn't an acknowledgement
+        let link = RtnlMessage::NewLink(LinkMessage::default());
+        respond(&mut buf, 10, NetlinkPayload::InnerMessage(link));
+        acks.decode(&buf).unwrap();
+        assert_eq!(acks.pending, 2);
+
+        // Outcomes aren't overwritten by repeated acknowledgements
+        buf.clear();
+        ack(&mut buf, 10, 0);
+        ack(&mut buf, 10, libc::EINVAL);
+        ack(&mut buf, 12, 0);
+        acks.decode(&buf).unwrap();
+        assert_eq!(acks.pending, 1);
+
+        buf.clear();
+        ack(&mut buf, 11, libc::ENOENT);
+        acks.decode(&buf).unwrap();
+        assert_eq!(acks.pending, 0);
+        let results: Vec<_> = acks.into_results().collect();
+        assert!(matches!(
+            results[..],
+            [Ok(()), Err(Error::Io(_)), Err(Error::InvalidParameter)]
+        ));
+    }
+
+    #[test]
+    fn wraparound() {
+        let mut acks = acks(u32::MAX - 1, 4);
+        let mut buf = vec![];
+        for sequence_number in [1, u32::MAX, 0, u32::MAX - 1] {
+            ack(&mut buf, sequence_number, 0);
+        }
+        // Sequence numbers before the first one don't alias positions after the wrap
+        ack(&mut buf, u32::MAX - 2, libc::EINVAL);
+        acks.decode(&buf).unwrap();
+        assert_eq!(acks.pending, 0);
+        assert_eq!(codes(acks), [None; 4]);
+    }
+
+    #[test]
+    fn batch_larger_than_window() {
+        let mut connection = Connection::new().unwrap();
+        // The numbering wraps around within the batch
+        connection.sequence_number = u32::MAX - 100;
+
+        // Deleting an address from a missing interface fails without changing anything
+        let interface = Interface::from_index_unchecked(u32::MAX);
+        let mut batch = Batch::new();
+        for i in 0..WINDOW as u8 * 3 {
+            let network = IpNet::new([10, 0, i, 1].into(), 24).unwrap();
+            batch.remove_address(&interface, network);
+        }
+
+        let results = connection.execute(batch).unwrap();
+        assert_eq!(results.len(), WINDOW * 3);
+        assert!(results.iter().all(Result::is_err));
+        assert_eq!(connection.sequence_number, WINDOW as u32 * 3 - 101);
+    }
+}
@@ -0,0 +1,896 @@
+use super::genl::{GenericConnection, GenericFamily, GenericMessage, GenericRequest};
+use super::netlink::{parse_string, parse_u32, put_attribute};
+use super::NetnsHandle;
+use crate::sys::posix::{ifreq::ifreq, ioctls};
+use crate::sys::InterfaceHandle;
+use crate::Error;
+use netlink_packet_route::nlas::{NlasIterator, NLA_F_NESTED};
+use nix::sys::socket::{socket, AddressFamily, SockFlag, SockType};
+use std::io;
+use std::mem;
+use std::ops::Range;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::Mutex;
+
+const ETHTOOL: &str = "ethtool";
+
+const ETHTOOL_MSG_LINKINFO_GET: u8 = 2;
+const ETHTOOL_MSG_LINKMODES_GET: u8 = 4;
+const ETHTOOL_MSG_RINGS_GET: u8 = 15;
+const ETHTOOL_MSG_RINGS_SET: u8 = 16;
+const ETHTOOL_MSG_CHANNELS_GET: u8 = 17;
+const ETHTOOL_MSG_CHANNELS_SET: u8 = 18;
+const ETHTOOL_MSG_COALESCE_GET: u8 = 19;
+const ETHTOOL_MSG_COALESCE_SET: u8 = 20;
+
+/// Header, nested in every request under the same attribute kind.
+const ETHTOOL_A_HEADER: u16 = 1;
+const ETHTOOL_A_HEADER_DEV_INDEX: u16 = 1;
+
+const ETHTOOL_A_LINKINFO_PORT: u16 = 2;
+
+const ETHTOOL_A_LINKMODES_AUTONEG: u16 = 2;
+const ETHTOOL_A_LINKMODES_OURS: u16 = 3;
+const ETHTOOL_A_LINKMODES_SPEED: u16 = 5;
+const ETHTOOL_A_LINKMODES_DUPLEX: u16 = 6;
+
+const ETHTOOL_A_BITSET_NOMASK: u16 = 1;
+const ETHTOOL_A_BITSET_BITS: u16 = 3;
+const ETHTOOL_A_BITSET_BITS_BIT: u16 = 1;
+const ETHTOOL_A_BITSET_BIT_INDEX: u16 = 1;
+const ETHTOOL_A_BITSET_BIT_NAME: u16 = 2;
+const ETHTOOL_A_BITSET_BIT_VALUE: u16 = 3;
+
+const ETHTOOL_GDRVINFO: u32 = 0x03;
+const ETHTOOL_GCOALESCE: u32 = 0x0e;
+const ETHTOOL_SCOALESCE: u32 = 0x0f;
+const ETHTOOL_GRINGPARAM: u32 = 0x10;
+const ETHTOOL_SRINGPARAM: u32 = 0x11;
+const ETHTOOL_GCHANNELS: u32 = 0x3c;
+const ETHTOOL_SCHANNELS: u32 = 0x3d;
+const ETHTOOL_GLINKSETTINGS: u32 = 0x4c;
+
+const SPEED_UNKNOWN: u32 = u32::MAX;
+const DUPLEX_UNKNOWN: u8 = 0xff;
+const PORT_NONE: u8 = 0xef;
+const AUTONEG_ENABLE: u8 = 1;
+
+/// Names of link modes by bit index (`enum ethtool_link_mode_bit_indices`), as the kernel reports
+/// them over netlink.
+const LINK_MODE_NAMES: &[&str] = &[
+    "10baseT/Half",
+    "10baseT/Full",
+    "100baseT/Half",
+    "100baseT/Full",
+    "1000baseT/Half",
+    "1000baseT/Full",
+    "Autoneg",
+    "TP",
+    "AUI",
+    "MII",
+    "FIBRE",
+    "BNC",
+    "10000baseT/Full",
+    "Pause",
+    "Asym_Pause",
+    "2500baseX/Full",
+    "Backplane",
+    "1000baseKX/Full",
+    "10000baseKX4/Full",
+    "10000baseKR/Full",
+    "10000baseR_FEC",
+    "20000baseMLD2/Full",
+    "20000baseKR2/Full",
+    "40000baseKR4/Full",
+    "40000baseCR4/Full",
+    "40000baseSR4/Full",
+    "40000baseLR4/Full",
+    "56000baseKR4/Full",
+    "56000baseCR4/Full",
+    "56000baseSR4/Full",
+    "56000baseLR4/Full",
+    "25000baseCR/Full",
+    "25000baseKR/Full",
+    "25000baseSR/Full",
+    "50000baseCR2/Full",
+    "50000baseKR2/Full",
+    "100000baseKR4/Full",
+    "100000baseSR4/Full",
+    "100000baseCR4/Full",
+    "100000baseLR4_ER4/Full",
+    "50000baseSR2/Full",
+    "1000baseX/Full",
+    "10000baseCR/Full",
+    "10000baseSR/Full",
+    "10000baseLR/Full",
+    "10000baseLRM/Full",
+    "10000baseER/Full",
+    "2500baseT/Full",
+    "5000baseT/Full",
+    "None",
+    "RS",
+    "BASER",
+    "50000baseKR/Full",
+    "50000baseSR/Full",
+    "50000baseCR/Full",
+    "50000baseLR_ER_FR/Full",
+    "50000baseDR/Full",
+    "100000baseKR2/Full",
+    "100000baseSR2/Full",
+    "100000baseCR2/Full",
+    "100000baseLR2_ER2_FR2/Full",
+    "100000baseDR2/Full",
+    "200000baseKR4/Full",
+    "200000baseSR4/Full",
+    "200000baseLR4_ER4_FR4/Full",
+    "200000baseDR4/Full",
+    "200000baseCR4/Full",
+    "100baseT1/Full",
+    "1000baseT1/Full",
+    "400000baseKR8/Full",
+    "400000baseSR8/Full",
+    "400000baseLR8_ER8_FR8/Full",
+    "400000baseDR8/Full",
+    "400000baseCR8/Full",
+    "LLRS",
+    "100000baseKR/Full",
+    "100000baseSR/Full",
+    "100000baseLR_ER_FR/Full",
+    "100000baseCR/Full",
+    "100000baseDR/Full",
+    "200000baseKR2/Full",
+    "200000baseSR2/Full",
+    "200000baseLR2_ER2_FR2/Full",
+    "200000baseDR2/Full",
+    "200000baseCR2/Full",
+    "400000baseKR4/Full",
+    "400000baseSR4/Full",
+    "400000baseLR4_ER4_FR4/Full",
+    "400000baseDR4/Full",
+    "400000baseCR4/Full",
+    "100baseFX/Half",
+    "100baseFX/Full",
+    "10baseT1L/Full",
+];
+
+/// Driver of a network device, as shown by `ethtool -i`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverInfo {
+    pub driver: String,
+    pub version: String,
+    pub firmware_version: String,
+    /// Location of the device, e.g. PCI address. Empty for virtual devices.
+    pub bus_info: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Duplex {
+    Half,
+    Full,
+}
+
+u8_enum! {
+    /// Physical connector of a network device (`PORT_*`).
+    pub enum PortType {
+        TwistedPair = 0x00,
+        Aui = 0x01,
+        Bnc = 0x02,
+        Mii = 0x03,
+        Fibre = 0x04,
+        DirectAttach = 0x05,
+    }
+}
+
+/// Speed, duplex and link modes of a network device, as shown by `ethtool`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkSettings {
+    /// Speed in Mb/s, `None` if unknown, e.g. without carrier.
+    pub speed: Option<u32>,
+    pub duplex: Option<Duplex>,
+    pub autoneg: bool,
+    pub port: Option<PortType>,
+    /// Link modes, supported by the device, named like `1000baseT/Full`. Besides speeds, they
+    /// include ports, e.g. `TP`, pause frames and FEC modes.
+    pub supported_modes: Vec<String>,
+    /// Link modes, advertised during autonegotiation.
+    pub advertised_modes: Vec<String>,
+}
+
+impl LinkSettings {
+    fn new(speed: u32, duplex: u8, autoneg: u8, port: u8) -> Self {
+        Self {
+            speed: Some(speed).filter(|speed| *speed != SPEED_UNKNOWN),
+            duplex: match duplex {
+                0 => Some(Duplex::Half),
+                1 => Some(Duplex::Full),
+                _ => None,
+            },
+            autoneg: autoneg == AUTONEG_ENABLE,
+            port: Some(port)
+                .filter(|port| *port != PORT_NONE)
+                .map(PortType::from),
+            supported_modes: vec![],
+            advertised_modes: vec![],
+        }
+    }
+}
+
+/// Settings, that the kernel exchanges as a flat list of values: attributes of an ethtool netlink
+/// message, numbered from 2 after the header, or `u32` fields of an ioctl struct after the
+/// command. Both follow the same order.
+trait EthtoolParams {
+    const GET: u8;
+    const SET: u8;
+    const IOCTL_GET: u32;
+    const IOCTL_SET: u32;
+    /// Indices of values, that can be changed. The others are limits, reported by the driver.
+    const SETTABLE: Range<usize>;
+    /// Whether a value is a flag, which netlink carries as `u8`, by index.
+    const FLAGS: &'static [bool];
+
+    fn from_values(values: &[u32]) -> Self;
+    fn values(&self) -> Vec<u32>;
+}
+
+trait ParamValue: Copy {
+    const FLAG: bool;
+
+    fn from_u32(value: u32) -> Self;
+    fn to_u32(self) -> u32;
+}
+
+impl ParamValue for u32 {
+    const FLAG: bool = false;
+
+    fn from_u32(value: u32) -> Self {
+        value
+    }
+
+    fn to_u32(self) -> u32 {
+        self
+    }
+}
+
+impl ParamValue for bool {
+    const FLAG: bool = true;
+
+    fn from_u32(value: u32) -> Self {
+        value != 0
+    }
+
+    fn to_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Defines a struct of settings along with its [`EthtoolParams`] implementation. Fields must be
+/// listed in the order of the kernel.
+macro_rules! ethtool_params {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $($(#[$fmeta:meta])* pub $field:ident: $type:ty,)+
+        }
+        netlink: ($get:expr, $set:expr),
+        ioctl: ($ioctl_get:expr, $ioctl_set:expr),
+        settable: $settable:expr,
+    ) => {
+        $(#[$meta])*
+        #[non_exhaustive]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        pub struct $name {
+            $($(#[$fmeta])* pub $field: $type,)+
+        }
+
+        impl EthtoolParams for $name {
+            const GET: u8 = $get;
+            const SET: u8 = $set;
+            const IOCTL_GET: u32 = $ioctl_get;
+            const IOCTL_SET: u32 = $ioctl_set;
+            const SETTABLE: Range<usize> = $settable;
+            const FLAGS: &'static [bool] = &[$(<$type as ParamValue>::FLAG,)+];
+
+            fn from_values(values: &[u32]) -> Self {
+                let mut values = values.iter().copied();
+                Self {
+                    $($field: ParamValue::from_u32(values.next().unwrap_or_default()),)+
+                }
+            }
+
+            fn values(&self) -> Vec<u32> {
+                vec![$(self.$field.to_u32(),)+]
+            }
+        }
+    };
+}
+
+ethtool_params! {
+    /// Sizes of ring buffers of a network device in descriptors, as shown by `ethtool -g`.
+    ///
+    /// Maximums are reported by the driver and ignored when the rings are set.
+    pub struct Rings {
+        pub rx_max: u32,
+        pub rx_mini_max: u32,
+        pub rx_jumbo_max: u32,
+        pub tx_max: u32,
+        pub rx: u32,
+        pub rx_mini: u32,
+        pub rx_jumbo: u32,
+        pub tx: u32,
+    }
+    netlink: (ETHTOOL_MSG_RINGS_GET, ETHTOOL_MSG_RINGS_SET),
+    ioctl: (ETHTOOL_GRINGPARAM, ETHTOOL_SRINGPARAM),
+    settable: 4..8,
+}
+
+ethtool_params! {
+    /// Numbers of queues, and interrupts serving them, of a network device, as shown by
+    /// `ethtool -l`. Combined channels serve both directions.
+    ///
+    /// Maximums are reported by the driver and ignored when the channels are set.
+    pub struct Channels {
+        pub rx_max: u32,
+        pub tx_max: u32,
+        pub other_max: u32,
+        pub combined_max: u32,
+        pub rx: u32,
+        pub tx: u32,
+        pub other: u32,
+        pub combined: u32,
+    }
+    netlink: (ETHTOOL_MSG_CHANNELS_GET, ETHTOOL_MSG_CHANNELS_SET),
+    ioctl: (ETHTOOL_GCHANNELS, ETHTOOL_SCHANNELS),
+    settable: 4..8,
+}
+
+ethtool_params! {
+    /// Interrupt coalescing of a network device, as shown by `ethtool -c`.
+    ///
+    /// An interrupt is delayed by up to `*_usecs` microseconds, or until `*_max_frames` frames
+    /// arrive. Parameters, unsupported by the driver, read as zero and must stay zero. `*_low`
+    /// and `*_high` parameters apply with adaptive coalescing, when the packet rate falls below
+    /// `pkt_rate_low` or exceeds `pkt_rate_high`, sampled every `rate_sample_interval` seconds.
+    pub struct Coalesce {
+        pub rx_usecs: u32,
+        pub rx_max_frames: u32,
+        /// Applies while an interrupt is being serviced.
+        pub rx_usecs_irq: u32,
+        pub rx_max_frames_irq: u32,
+        pub tx_usecs: u32,
+        pub tx_max_frames: u32,
+        pub tx_usecs_irq: u32,
+        pub tx_max_frames_irq: u32,
+        /// Delay of statistics updates in microseconds.
+        pub stats_block_usecs: u32,
+        pub adaptive_rx: bool,
+        pub adaptive_tx: bool,
+        pub pkt_rate_low: u32,
+        pub rx_usecs_low: u32,
+        pub rx_max_frames_low: u32,
+        pub tx_usecs_low: u32,
+        pub tx_max_frames_low: u32,
+        pub pkt_rate_high: u32,
+        pub rx_usecs_high: u32,
+        pub rx_max_frames_high: u32,
+        pub tx_usecs_high: u32,
+        pub tx_max_frames_high: u32,
+        pub rate_sample_interval: u32,
+    }
+    netlink: (ETHTOOL_MSG_COALESCE_GET, ETHTOOL_MSG_COALESCE_SET),
+    ioctl: (ETHTOOL_GCOALESCE, ETHTOOL_SCOALESCE),
+    settable: 0..22,
+}
+
+/// Ethtool request for one device. Replies are returned as their raw attributes.
+pub(crate) struct EthtoolRequest {
+    command: u8,
+    index: u32,
+    attributes: Vec<u8>,
+}
+
+impl EthtoolRequest {
+    pub(crate) fn new(command: u8, index: u32) -> Self {
+        Self::with_attributes(command, index, vec![])
+    }
+
+    pub(crate) fn with_attributes(command: u8, index: u32, attributes: Vec<u8>) -> Self {
+        Self {
+            command,
+            index,
+            attributes,
+        }
+    }
+}
+
+impl GenericRequest for EthtoolRequest {
+    type Response = Vec<u8>;
+
+    const FAMILY: &'static str = ETHTOOL;
+
+    fn command(&self) -> u8 {
+        self.command
+    }
+
+    fn attributes(&self, buffer: &mut Vec<u8>) {
+        let mut header = vec![];
+        put_attribute(
+            &mut header,
+            ETHTOOL_A_HEADER_DEV_INDEX,
+            &self.index.to_ne_bytes(),
+        );
+        put_attribute(buffer, ETHTOOL_A_HEADER | NLA_F_NESTED, &header);
+        buffer.extend_from_slice(&self.attributes);
+    }
+
+    fn parse(&self, response: &GenericMessage) -> Result<Option<Self::Response>, Error> {
+        Ok(Some(response.attributes.clone()))
+    }
+}
+
+/// The ethtool family, once resolved, or `None` inside, if the kernel lacks it. Generic netlink
+/// families are registered for all namespaces at once, so it serves every namespace.
+static FAMILY: Mutex<Option<Option<GenericFamily>>> = Mutex::new(None);
+
+/// Opens a connection for ethtool requests. Returns `None`, if the kernel doesn't support ethtool
+/// netlink (before 5.6), so that the caller falls back to the ioctl.
+///
+/// The family is resolved on first use only, but every call opens a socket of its own.
+pub(crate) fn connection(netns: Option<&NetnsHandle>) -> Result<Option<GenericConnection>, Error> {
+    let cached = FAMILY.lock().unwrap().clone();
+    if let Some(family) = cached {
+        let Some(family) = family else {
+            return Ok(None);
+        };
+        let mut connection = GenericConnection::new_in(netns)?;
+        connection.insert_family(family);
+        return Ok(Some(connection));
+    }
+
+    let mut connection = GenericConnection::new_in(netns)?;
+    let family = match connection.family(ETHTOOL) {
+        Err(e) if e.raw_os_error() == Some(libc::ENOENT) => None,
+        Err(e) => return Err(e),
+        Ok(family) => Some(family.clone()),
+    };
+    let supported = family.is_some();
+    *FAMILY.lock().unwrap() = Some(family);
+    Ok(Some(connection).filter(|_| supported))
+}
+
+/// Sends a request without attributes, other than the header, and returns attributes of the reply.
+pub(crate) fn get(
+    connection: &mut GenericConnection,
+    command: u8,
+    index: u32,
+) -> Result<Vec<u8>, Error> {
+    let mut responses = connection.request(&EthtoolRequest::new(command, index))?;
+    Ok(responses.pop().unwrap_or_default())
+}
+
+/// Sends a request with given attributes, which changes settings of a device.
+pub(crate) fn set(
+    connection: &mut GenericConnection,
+    command: u8,
+    index: u32,
+    attributes: Vec<u8>,
+) -> Result<(), Error> {
+    connection.request(&EthtoolRequest::with_attributes(command, index, attributes))?;
+    Ok(())
+}
+
+/// Parses a verbose bitset into its bits: index, name and whether it's set in the value. Without a
+/// mask, only set bits are listed. Bits may be identified by name alone, like in requests, but the
+/// kernel always reports their indices.
+pub(crate) fn parse_bitset(bytes: &[u8]) -> Result<Vec<(Option<u32>, String, bool)>, Error> {
+    let mut nomask = false;
+    let mut bits = vec![];
+    for nla in NlasIterator::new(bytes) {
+        let nla = nla?;
+        match nla.kind() {
+            ETHTOOL_A_BITSET_NOMASK => nomask = true,
+            ETHTOOL_A_BITSET_BITS => {
+                for bit in NlasIterator::new(nla.value()) {
+                    let bit = bit?;
+                    if bit.kind() == ETHTOOL_A_BITSET_BITS_BIT {
+                        bits.extend(parse_bit(bit.value())?);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if nomask {
+        bits.iter_mut().for_each(|(_, _, value)| *value = true);
+    }
+    Ok(bits)
+}
+
+/// Appends a verbose bitset, which sets or clears bits by name. Listed bits form the mask, so
+/// the others are left unchanged.
+pub(crate) fn put_bitset(buffer: &mut Vec<u8>, kind: u16, bits: &[(&str, bool)]) {
+    let mut list = vec![];
+    for (name, value) in bits {
+        let mut name = name.as_bytes().to_vec();
+        name.push(0);
+        let mut bit = vec![];
+        put_attribute(&mut bit, ETHTOOL_A_BITSET_BIT_NAME, &name);
+        if *value {
+            put_attribute(&mut bit, ETHTOOL_A_BITSET_BIT_VALUE, &[]);
+        }
+        put_attribute(&mut list, ETHTOOL_A_BITSET_BITS_BIT | NLA_F_NESTED, &bit);
+    }
+    let mut bitset = vec![];
+    put_attribute(&mut bitset, ETHTOOL_A_BITSET_BITS | NLA_F_NESTED, &list);
+    put_attribute(buffer, kind | NLA_F_NESTED, &bitset);
+}
+
+fn parse_bit(bytes: &[u8]) -> Result<Option<(Option<u32>, String, bool)>, Error> {
+    let (mut index, mut name, mut value) = (None, None, false);
+    for nla in NlasIterator::new(bytes) {
+        let nla = nla?;
+        match nla.kind() {
+            ETHTOOL_A_BITSET_BIT_INDEX => index = Some(parse_u32(nla.value())?),
+            ETHTOOL_A_BITSET_BIT_NAME => name = Some(parse_string(nla.value())?),
+            ETHTOOL_A_BITSET_BIT_VALUE => value = true,
+            _ => {}
+        }
+    }
+    Ok(name.map(|name| (index, name, value)))
+}
+
+/// Opens a socket for ethtool ioctls, which address devices in the namespace of the socket.
+///
+/// The socket is left unbound, as the namespace may lack addresses, even on loopback.
+fn ioctl_socket(netns: Option<&NetnsHandle>) -> Result<OwnedFd, Error> {
+    let open = || -> io::Result<OwnedFd> {
+        let fd = socket(
+            AddressFamily::Inet,
+            SockType::Datagram,
+            SockFlag::SOCK_CLOEXEC,
+            None,
+        )?;
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    };
+    match netns {
+        Some(netns) => netns.run(open),
+        None => Ok(open()?),
+    }
+}
+
+/// Issues `SIOCETHTOOL` with `data`, a struct starting with the ethtool command.
+///
+/// # Safety
+///
+/// `data` must be large enough for what the kernel writes back for the command.
+pub(crate) unsafe fn ioctl<T: ?Sized>(handle: &InterfaceHandle, data: &mut T) -> Result<(), Error> {
+    let socket = ioctl_socket(handle.netns.as_ref())?;
+    let mut req = ifreq::new(handle.name()?);
+    req.ifr_ifru.ifru_data = data as *mut T as *mut libc::c_char;
+    ioctls::siocethtool(socket.as_raw_fd(), &mut req).map_err(io::Error::from)?;
+    Ok(())
+}
+
+/// `struct ethtool_drvinfo`
+#[repr(C)]
+#[allow(dead_code)]
+struct EthtoolDrvinfo {
+    cmd: u32,
+    driver: [u8; 32],
+    version: [u8; 32],
+    fw_version: [u8; 32],
+    bus_info: [u8; 32],
+    erom_version: [u8; 32],
+    reserved2: [u8; 12],
+    n_priv_flags: u32,
+    n_stats: u32,
+    testinfo_len: u32,
+    eedump_len: u32,
+    regdump_len: u32,
+}
+
+/// `struct ethtool_link_settings`, followed by room for the largest link mode masks.
+#[repr(C)]
+#[allow(dead_code)]
+struct EthtoolLinkSettings {
+    cmd: u32,
+    speed: u32,
+    duplex: u8,
+    port: u8,
+    phy_address: u8,
+    autoneg: u8,
+    mdio_support: u8,
+    eth_tp_mdix: u8,
+    eth_tp_mdix_ctrl: u8,
+    link_mode_masks_nwords: i8,
+    transceiver: u8,
+    master_slave_cfg: u8,
+    master_slave_state: u8,
+    rate_matching: u8,
+    reserved: [u32; 7],
+    /// Supported, advertised and link partner modes, `link_mode_masks_nwords` each.
+    link_mode_masks: [u32; 3 * i8::MAX as usize],
+}
+
+impl InterfaceHandle {
+    pub fn driver_info(&self) -> Result<DriverInfo, Error> {
+        // Ethtool netlink has no counterpart of ETHTOOL_GDRVINFO
+        let mut info: EthtoolDrvinfo = unsafe { mem::zeroed() };
+        info.cmd = ETHTOOL_GDRVINFO;
+        unsafe { ioctl(self, &mut info)? };
+
+        Ok(DriverInfo {
+            driver: parse_string(&info.driver)?,
+            version: parse_string(&info.version)?,
+            firmware_version: parse_string(&info.fw_version)?,
+            bus_info: parse_string(&info.bus_info)?,
+        })
+    }
+
+    pub fn link_settings(&self) -> Result<LinkSettings, Error> {
+        let Some(mut connection) = connection(self.netns.as_ref())? else {
+            return self.link_settings_from_ioctl();
+        };
+        let modes = get(&mut connection, ETHTOOL_MSG_LINKMODES_GET, self.index)?;
+        let info = get(&mut connection, ETHTOOL_MSG_LINKINFO_GET, self.index)?;
+
+        let (mut speed, mut duplex, mut autoneg) = (SPEED_UNKNOWN, DUPLEX_UNKNOWN, 0);
+        let mut ours = vec![];
+        for nla in NlasIterator::new(&modes) {
+            let nla = nla?;
+            match (nla.kind(), nla.value()) {
+                (ETHTOOL_A_LINKMODES_SPEED, value) => speed = parse_u32(value)?,
+                (ETHTOOL_A_LINKMODES_DUPLEX, [value]) => duplex = *value,
+                (ETHTOOL_A_LINKMODES_AUTONEG, [value]) => autoneg = *value,
+                (ETHTOOL_A_LINKMODES_OURS, value) => ours = parse_bitset(value)?,
+                _ => {}
+            }
+        }
+        let mut port = PORT_NONE;
+        for nla in NlasIterator::new(&info) {
+            let nla = nla?;
+            if let (ETHTOOL_A_LINKINFO_PORT, [value]) = (nla.kind(), nla.value()) {
+                port = *value;
+            }
+        }
+
+        // Value of the bitset holds advertised modes, its mask supported ones
+        let mut settings = LinkSettings::new(speed, duplex, autoneg, port);
+        for (_, name, advertised) in ours {
+            if advertised {
+                settings.advertised_modes.push(name.clone());
+            }
+            settings.supported_modes.push(name);
+        }
+        Ok(settings)
+    }
+
+    fn link_settings_from_ioctl(&self) -> Result<LinkSettings, Error> {
+        // The kernel reports the size of link mode masks in response to a request without them
+        let mut request: EthtoolLinkSettings = unsafe { mem::zeroed() };
+        request.cmd = ETHTOOL_GLINKSETTINGS;
+        unsafe { ioctl(self, &mut request)? };
+        let words = request.link_mode_masks_nwords.unsigned_abs() as usize;
+        request.link_mode_masks_nwords = words as i8;
+        unsafe { ioctl(self, &mut request)? };
+
+        let mut settings =
+            LinkSettings::new(request.speed, request.duplex, request.autoneg, request.port);
+        let masks = &request.link_mode_masks;
+        settings.supported_modes = link_modes(&masks[..words]);
+        settings.advertised_modes = link_modes(&masks[words..2 * words]);
+        Ok(settings)
+    }
+
+    pub fn rings(&self) -> Result<Rings, Error> {
+        self.params()
+    }
+
+    pub fn set_rings(&self, rings: Rings) -> Result<(), Error> {
+        self.set_params(&rings)
+    }
+
+    pub fn channels(&self) -> Result<Channels, Error> {
+        self.params()
+    }
+
+    pub fn set_channels(&self, channels: Channels) -> Result<(), Error> {
+        self.set_params(&channels)
+    }
+
+    pub fn coalesce(&self) -> Result<Coalesce, Error> {
+        self.params()
+    }
+
+    pub fn set_coalesce(&self, coalesce: Coalesce) -> Result<(), Error> {
+        self.set_params(&coalesce)
+    }
+
+    fn params<P: EthtoolParams>(&self) -> Result<P, Error> {
+        let Some(mut connection) = connection(self.netns.as_ref())? else {
+            let mut data = vec![0; P::FLAGS.len() + 1];
+            data[0] = P::IOCTL_GET;
+            unsafe { ioctl(self, data.as_mut_slice())? };
+            return Ok(P::from_values(&data[1..]));
+        };
+        let reply = get(&mut connection, P::GET, self.index)?;
+        Ok(P::from_values(&parse_params(&reply, P::FLAGS)?))
+    }
+
+    fn set_params<P: EthtoolParams>(&self, params: &P) -> Result<(), Error> {
+        let values = params.values();
+        let Some(mut connection) = connection(self.netns.as_ref())? else {
+            let mut data = vec![P::IOCTL_SET];
+            data.extend(values);
+            unsafe { ioctl(self, data.as_mut_slice())? };
+            return Ok(());
+        };
+
+        // Only changed values are sent, as the kernel rejects attributes of parameters, that the
+        // driver doesn't support, even if they are zero
+        let reply = get(&mut connection, P::GET, self.index)?;
+        let current = parse_params(&reply, P::FLAGS)?;
+        let mut attributes = vec![];
+        for index in P::SETTABLE {
+            let value = values[index];
+            if value == current[index] {
+                continue;
+            }
+            let kind = index as u16 + 2;
+            if P::FLAGS[index] {
+                put_attribute(&mut attributes, kind, &[value as u8]);
+            } else {
+                put_attribute(&mut attributes, kind, &value.to_ne_bytes());
+            }
+        }
+        if attributes.is_empty() {
+            return Ok(());
+        }
+        set(&mut connection, P::SET, self.index, attributes)
+    }
+}
+
+/// Names the bits, set in a link mode mask.
+fn link_modes(mask: &[u32]) -> Vec<String> {
+    let mut modes = vec![];
+    for (word_index, word) in mask.iter().enumerate() {
+        for bit in 0..32 {
+            if word & (1 << bit) == 0 {
+                continue;
+            }
+            let index = word_index * 32 + bit;
+            modes.push(match LINK_MODE_NAMES.get(index) {
+                Some(name) => name.to_string(),
+                None => index.to_string(),
+            });
+        }
+    }
+    modes
+}
+
+/// Parses attributes of a reply into values of [`EthtoolParams`]. Missing values, e.g. of
+/// parameters unsupported by the driver, are zero.
+fn parse_params(bytes: &[u8], flags: &[bool]) -> Result<Vec<u32>, Error> {
+    let mut values = vec![0; flags.len()];
+    for nla in NlasIterator::new(bytes) {
+        let nla = nla?;
+        let index = (nla.kind() as usize).wrapping_sub(2);
+        if index >= values.len() {
+            continue;
+        }
+        values[index] = match (flags[index], nla.value()) {
+            (true, [value]) => *value as u32,
+            (true, _) => return Err(Error::UnexpectedMetadata),
+            (false, value) => parse_u32(value)?,
+        };
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a verbose bitset, like the kernel reports it.
+    fn bitset(bits: &[(u32, &str, bool)], nomask: bool) -> Vec<u8> {
+        let mut list = vec![];
+        for (index, name, value) in bits {
+            let mut bit = vec![];
+            put_attribute(&mut bit, ETHTOOL_A_BITSET_BIT_INDEX, &index.to_ne_bytes());
+            put_attribute(
+                &mut bit,
+                ETHTOOL_A_BITSET_BIT_NAME,
+                format!("{name}\0").as_bytes(),
+            );
+            if *value {
+                put_attribute(&mut bit, ETHTOOL_A_BITSET_BIT_VALUE, &[]);
+            }
+            put_attribute(&mut list, ETHTOOL_A_BITSET_BITS_BIT | NLA_F_NESTED, &bit);
+        }
+        let mut bytes = vec![];
+        if nomask {
+            put_attribute(&mut bytes, ETHTOOL_A_BITSET_NOMASK, &[]);
+        }
+        put_attribute(&mut bytes, ETHTOOL_A_BITSET_BITS | NLA_F_NESTED, &list);
+        bytes
+    }
+
+    #[test]
+    fn bitset_with_mask() {
+        let bytes = bitset(
+            &[(0, "10baseT/Half", false), (5, "1000baseT/Full", true)],
+            false,
+        );
+        assert_eq!(
+            parse_bitset(&bytes).unwrap(),
+            vec![
+                (Some(0), "10baseT/Half".to_string(), false),
+                (Some(5), "1000baseT/Full".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn bitset_without_mask() {
+        // Only set bits are listed, and they carry no value attribute
+        let bytes = bitset(&[(3, "rx-gro", false)], true);
+        assert_eq!(
+            parse_bitset(&bytes).unwrap(),
+            vec![(Some(3), "rx-gro".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn link_modes_by_bit() {
+        assert_eq!(link_modes(&[0b101, 0]), ["10baseT/Half", "100baseT/Half"]);
+        assert_eq!(link_modes(&[1 << 5]), ["1000baseT/Full"]);
+        assert!(link_modes(&[0, 0, 0]).is_empty());
+
+        // Bits, this crate has no name for, are reported by index
+        let mut mask = vec![0; 8];
+        mask[7] = 1 << 31;
+        assert_eq!(link_modes(&mask), ["255"]);
+    }
+
+    #[test]
+    fn link_settings_unknown_values() {
+        let settings = LinkSettings::new(SPEED_UNKNOWN, DUPLEX_UNKNOWN, 0, PORT_NONE);
+        assert_eq!(settings.speed, None);
+        assert_eq!(settings.duplex, None);
+        assert!(!settings.autoneg);
+        assert_eq!(settings.port, None);
+
+        let settings = LinkSettings::new(1000, 1, AUTONEG_ENABLE, 0);
+        assert_eq!(settings.speed, Some(1000));
+        assert_eq!(settings.duplex, Some(Duplex::Full));
+        assert!(settings.autoneg);
+        assert_eq!(settings.port, Some(PortType::TwistedPair));
+    }
+
+    #[test]
+    fn params_by_attribute_kind() {
+        // Attributes are indexed by their kind, not by their order in the reply
+        let mut bytes = vec![];
+        put_attribute(&mut bytes, 3, &7u32.to_ne_bytes());
+        put_attribute(&mut bytes, 2, &[1]);
+        put_attribute(&mut bytes, 1, &[0; 8]);
+        put_attribute(&mut bytes, 10, &9u32.to_ne_bytes());
+
+        assert_eq!(
+            parse_params(&bytes, &[true, false, false]).unwrap(),
+            [1, 7, 0]
+        );
+    }
+
+    #[test]
+    fn params_with_wrong_size() {
+        let mut bytes = vec![];
+        put_attribute(&mut bytes, 2, &1u32.to_ne_bytes());
+        assert!(parse_params(&bytes, &[true]).is_err());
+
+        let mut bytes = vec![];
+        put_attribute(&mut bytes, 2, &[1]);
+        assert!(parse_params(&bytes, &[false]).is_err());
+    }
+}
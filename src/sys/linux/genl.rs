@@ -0,0 +1,351 @@
+use super::connection::Connection;
+use super::netlink::{parse_string, parse_u16, parse_u32, put_attribute, RawMessage};
+use super::NetnsHandle;
+use crate::Error;
+use netlink_packet_route::nlas::NlasIterator;
+use netlink_sys::constants::NETLINK_GENERIC;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+const CTRL_ATTR_VERSION: u16 = 3;
+const CTRL_ATTR_MCAST_GROUPS: u16 = 7;
+
+const CTRL_ATTR_MCAST_GRP_NAME: u16 = 1;
+const CTRL_ATTR_MCAST_GRP_ID: u16 = 2;
+
+/// Message of a generic netlink family: request, response or notification.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenericMessage {
+    /// Id of the family, the message belongs to.
+    pub family: u16,
+    pub command: u8,
+    pub version: u8,
+    /// Attributes in wire format, e.g. to be walked with `NlasIterator` of netlink-packet-utils.
+    pub attributes: Vec<u8>,
+}
+
+impl From<RawMessage<4>> for GenericMessage {
+    /// Splits `struct genlmsghdr`: command, version and padding. The family id is the message
+    /// type.
+    fn from(message: RawMessage<4>) -> Self {
+        Self {
+            family: message.message_type,
+            command: message.header[0],
+            version: message.header[1],
+            attributes: message.attributes,
+        }
+    }
+}
+
+/// Generic netlink family, registered with the kernel, e.g. `ethtool` or `wireguard`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenericFamily {
+    pub name: String,
+    /// Message type, assigned by the kernel at registration. It differs between boots.
+    pub id: u16,
+    pub version: u32,
+    pub multicast_groups: Vec<MulticastGroup>,
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MulticastGroup {
+    pub name: String,
+    pub id: u32,
+}
+
+impl MulticastGroup {
+    fn parse(bytes: &[u8]) -> Result<Option<Self>, Error> {
+        let (mut name, mut id) = (None, None);
+        for nla in NlasIterator::new(bytes) {
+            let nla = nla?;
+            match nla.kind() {
+                CTRL_ATTR_MCAST_GRP_NAME => name = Some(parse_string(nla.value())?),
+                CTRL_ATTR_MCAST_GRP_ID => id = Some(parse_u32(nla.value())?),
+                _ => {}
+            }
+        }
+        Ok(name.zip(id).map(|(name, id)| MulticastGroup { name, id }))
+    }
+}
+
+impl GenericFamily {
+    /// Returns id of the multicast group with given name.
+    pub fn multicast_group(&self, name: &str) -> Option<u32> {
+        self.multicast_groups
+            .iter()
+            .find(|group| group.name == name)
+            .map(|group| group.id)
+    }
+
+    fn parse(message: &GenericMessage) -> Result<Self, Error> {
+        let mut family = GenericFamily {
+            name: String::new(),
+            id: 0,
+            version: 0,
+            multicast_groups: vec![],
+        };
+        for nla in NlasIterator::new(&message.attributes) {
+            let nla = nla?;
+            match nla.kind() {
+                CTRL_ATTR_FAMILY_ID => family.id = parse_u16(nla.value())?,
+                CTRL_ATTR_FAMILY_NAME => family.name = parse_string(nla.value())?,
+                CTRL_ATTR_VERSION => family.version = parse_u32(nla.value())?,
+                CTRL_ATTR_MCAST_GROUPS => {
+                    // Array of nested groups, indexed by their attribute kinds
+                    for group in NlasIterator::new(nla.value()) {
+                        family
+                            .multicast_groups
+                            .extend(MulticastGroup::parse(group?.value())?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if family.id == 0 {
+            return Err(Error::UnexpectedMetadata);
+        }
+        Ok(family)
+    }
+}
+
+/// Request of a generic netlink family, along with the type its responses are parsed into. Sent
+/// with [`GenericConnection::request`].
+pub trait GenericRequest {
+    type Response;
+
+    /// Name of the family, resolved to its id on first use. `nlctrl` has a fixed id.
+    const FAMILY: &'static str;
+    const VERSION: u8 = 1;
+    /// Netlink flags, e.g. `NLM_F_DUMP`.
+    const FLAGS: u16 = 0;
+
+    fn command(&self) -> u8;
+
+    /// Appends attributes of the request.
+    fn attributes(&self, buffer: &mut Vec<u8>);
+
+    /// Parses a response. Returns `None` for responses, that should be skipped.
+    fn parse(&self, response: &GenericMessage) -> Result<Option<Self::Response>, Error>;
+}
+
+struct GetFamily<'a>(&'a str);
+
+impl GenericRequest for GetFamily<'_> {
+    type Response = GenericFamily;
+
+    const FAMILY: &'static str = "nlctrl";
+    const VERSION: u8 = 2;
+
+    fn command(&self) -> u8 {
+        CTRL_CMD_GETFAMILY
+    }
+
+    fn attributes(&self, buffer: &mut Vec<u8>) {
+        let mut name = self.0.as_bytes().to_vec();
+        name.push(0);
+        put_attribute(buffer, CTRL_ATTR_FAMILY_NAME, &name);
+    }
+
+    fn parse(&self, response: &GenericMessage) -> Result<Option<Self::Response>, Error> {
+        GenericFamily::parse(response).map(Some)
+    }
+}
+
+/// Generic netlink socket, which resolves and remembers families, it sends requests to.
+///
+/// It also receives notifications of multicast groups, it [subscribed](Self::subscribe) to.
+/// Notifications, that arrive while a request is pending, are discarded, so a subscribed
+/// GenericConnection is better left for notifications only.
+pub struct GenericConnection {
+    connection: Connection,
+    families: HashMap<String, GenericFamily>,
+    notifications: VecDeque<GenericMessage>,
+}
+
+impl GenericConnection {
+    /// Connects to the namespace of the calling thread.
+    pub fn new() -> Result<Self, Error> {
+        Self::new_in(None)
+    }
+
+    pub(crate) fn new_in(netns: Option<&NetnsHandle>) -> Result<Self, Error> {
+        Ok(Self {
+            connection: Connection::with_protocol(netns, NETLINK_GENERIC)?,
+            families: HashMap::new(),
+            notifications: VecDeque::new(),
+        })
+    }
+
+    /// Resolves a family by name. Fails with `ENOENT`, if the family isn't registered, e.g.
+    /// because its module isn't loaded.
+    pub fn family(&mut self, name: &str) -> Result<&GenericFamily, Error> {
+        if !self.families.contains_key(name) {
+            let family = self
+                .request(&GetFamily(name))?
+                .pop()
+                .ok_or(Error::UnexpectedMetadata)?;
+            self.families.insert(name.to_string(), family);
+        }
+        Ok(&self.families[name])
+    }
+
+    /// Remembers a family, resolved earlier, so that requests to it don't resolve it again.
+    pub(crate) fn insert_family(&mut self, family: GenericFamily) {
+        self.families.insert(family.name.clone(), family);
+    }
+
+    /// Sends a request to its family and parses the responses.
+    pub fn request<R: GenericRequest>(&mut self, request: &R) -> Result<Vec<R::Response>, Error> {
+        let id = match R::FAMILY {
+            "nlctrl" => GENL_ID_CTRL,
+            name => self.family(name)?.id,
+        };
+
+        let mut message = RawMessage::<4>::new(id);
+        message.header[0] = request.command();
+        message.header[1] = R::VERSION;
+        request.attributes(&mut message.attributes);
+
+        let mut responses = vec![];
+        for response in self.connection.request(message, R::FLAGS)? {
+            if response.message_type != id {
+                continue;
+            }
+            responses.extend(request.parse(&GenericMessage::from(response))?);
+        }
+        Ok(responses)
+    }
+
+    /// Joins a multicast group of a family, e.g. `monitor` of `ethtool`. Fails with `ENOENT`, if
+    /// the family has no such group.
+    pub fn subscribe(&mut self, family: &str, group: &str) -> Result<(), Error> {
+        let id = self
+            .family(family)?
+            .multicast_group(group)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENOENT))?;
+        self.connection.add_membership(id)
+    }
+
+    /// Waits for the next notification of the subscribed groups. Fails with `ENOBUFS`, if
+    /// notifications were lost, as the receive buffer overflowed.
+    pub fn next_notification(&mut self) -> Result<GenericMessage, Error> {
+        loop {
+            if let Some(notification) = self.notifications.pop_front() {
+                return Ok(notification);
+            }
+            let notifications = self.connection.notifications::<RawMessage<4>>()?;
+            self.notifications
+                .extend(notifications.into_iter().map(GenericMessage::from));
+        }
+    }
+}
+
+impl AsRawFd for GenericConnection {
+    /// Returns the socket, which becomes readable when a notification is pending.
+    fn as_raw_fd(&self) -> RawFd {
+        self.connection.as_raw_fd()
+    }
+}
+
+/// Resolves a generic netlink family by name, along with its multicast groups.
+pub fn resolve_family(name: &str) -> Result<GenericFamily, Error> {
+    resolve_family_in(None, name)
+}
+
+pub(crate) fn resolve_family_in(
+    netns: Option<&NetnsHandle>,
+    name: &str,
+) -> Result<GenericFamily, Error> {
+    GenericConnection::new_in(netns)?.family(name).cloned()
+}
+
+impl NetnsHandle {
+    /// Resolves a generic netlink family by name inside this namespace.
+    pub fn resolve_family(&self, name: &str) -> Result<GenericFamily, Error> {
+        resolve_family_in(Some(self), name)
+    }
+
+    /// Opens a GenericConnection to this namespace.
+    pub fn generic_connection(&self) -> Result<GenericConnection, Error> {
+        GenericConnection::new_in(Some(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(name: &str, id: u32) -> Vec<u8> {
+        let mut bytes = vec![];
+        put_attribute(&mut bytes, CTRL_ATTR_MCAST_GRP_ID, &id.to_ne_bytes());
+        put_attribute(
+            &mut bytes,
+            CTRL_ATTR_MCAST_GRP_NAME,
+            format!("{name}\0").as_bytes(),
+        );
+        bytes
+    }
+
+    #[test]
+    fn multicast_group() {
+        assert_eq!(
+            MulticastGroup::parse(&group("monitor", 7)).unwrap(),
+            Some(MulticastGroup {
+                name: "monitor".to_string(),
+                id: 7,
+            })
+        );
+
+        let mut bytes = vec![];
+        put_attribute(&mut bytes, CTRL_ATTR_MCAST_GRP_NAME, b"monitor\0");
+        assert_eq!(MulticastGroup::parse(&bytes).unwrap(), None);
+    }
+
+    #[test]
+    fn family() {
+        let mut groups = vec![];
+        put_attribute(&mut groups, 1, &group("monitor", 7));
+        put_attribute(&mut groups, 2, &group("config", 8));
+
+        let mut message = GenericMessage {
+            family: GENL_ID_CTRL,
+            command: 1,
+            version: 2,
+            attributes: vec![],
+        };
+        let attributes = &mut message.attributes;
+        put_attribute(attributes, CTRL_ATTR_FAMILY_NAME, b"ethtool\0");
+        put_attribute(attributes, CTRL_ATTR_FAMILY_ID, &20u16.to_ne_bytes());
+        put_attribute(attributes, CTRL_ATTR_VERSION, &1u32.to_ne_bytes());
+        put_attribute(attributes, CTRL_ATTR_MCAST_GROUPS, &groups);
+
+        let family = GenericFamily::parse(&message).unwrap();
+        assert_eq!(family.name, "ethtool");
+        assert_eq!(family.id, 20);
+        assert_eq!(family.version, 1);
+        assert_eq!(family.multicast_groups.len(), 2);
+        assert_eq!(family.multicast_group("config"), Some(8));
+        assert_eq!(family.multicast_group("other"), None);
+    }
+
+    #[test]
+    fn family_without_id() {
+        let mut message = GenericMessage {
+            family: GENL_ID_CTRL,
+            command: 1,
+            version: 2,
+            attributes: vec![],
+        };
+        put_attribute(&mut message.attributes, CTRL_ATTR_FAMILY_NAME, b"ethtool\0");
+        assert!(GenericFamily::parse(&message).is_err());
+    }
+}
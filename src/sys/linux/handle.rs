@@ -1,18 +1,19 @@
-use crate::sys::ifreq::ifreq;
-use crate::sys::{dummy_socket, ioctls, InterfaceHandle};
+use super::{
+    netlink, Channels, Coalesce, DriverInfo, Feature, InterfaceKind, InterfaceStats, LinkSettings,
+    NetnsHandle, NetnsTarget, Offload, Rings,
+};
+use crate::sys::posix::{dummy_socket, ifreq::ifreq, ioctls};
+use crate::sys::InterfaceHandle;
 use crate::{Error, Interface};
 use advmac::MacAddr6;
 use delegate::delegate;
 use ipnet::IpNet;
-use libc::{AF_INET, AF_INET6, ARPHRD_ETHER};
-use log::debug;
+use libc::{AF_INET, AF_INET6, ARPHRD_ETHER, IFF_RUNNING, IFF_UP};
 use netlink_packet_route::{
-    address::Nla as AddressNla, AddressMessage, NetlinkHeader, NetlinkMessage, NetlinkPayload,
-    RtnlMessage, NLM_F_DUMP, NLM_F_REQUEST,
+    address::Nla as AddressNla, link::nlas::Nla as LinkNla, AddressMessage, LinkMessage,
+    RtnlMessage, NLM_F_CREATE, NLM_F_DUMP, NLM_F_REPLACE,
 };
-use netlink_sys::constants::NETLINK_ROUTE;
-use netlink_sys::{Socket, SocketAddr};
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::os::unix::io::AsRawFd;
 
 // Public interface (platform extension)
@@ -20,70 +21,284 @@ pub trait InterfaceExt {
     fn set_up(&self, v: bool) -> Result<(), Error>;
     fn set_running(&self, v: bool) -> Result<(), Error>;
     fn set_hwaddress(&self, hwaddress: MacAddr6) -> Result<(), Error>;
+    /// Returns network namespace, this Interface belongs to, or `None` if it was obtained from the
+    /// namespace of the calling thread.
+    fn netns(&self) -> Option<NetnsHandle>;
+    /// Moves this Interface to another network namespace, optionally renaming it.
+    ///
+    /// Returns Interface, valid in the target namespace. Its index may differ from the original one,
+    /// if the index is already taken in the target namespace.
+    fn move_to_netns<T: Into<NetnsTarget>>(
+        &self,
+        target: T,
+        name: Option<&str>,
+    ) -> Result<Interface, Error>;
+    /// Returns traffic counters of this Interface.
+    ///
+    /// Counters are read from `IFLA_STATS64`, falling back to `/proc/net/dev` if netlink is
+    /// unavailable.
+    fn stats(&self) -> Result<InterfaceStats, Error>;
+    /// Returns type of this Interface, derived from `IFLA_LINKINFO`, link layer type and sysfs.
+    fn kind(&self) -> Result<InterfaceKind, Error>;
+    /// Returns `true` if this Interface answers ARP requests on behalf of other hosts
+    /// (`net.ipv4.conf.<name>.proxy_arp`).
+    fn proxy_arp(&self) -> Result<bool, Error>;
+    fn set_proxy_arp(&self, v: bool) -> Result<(), Error>;
+    /// Returns `true` if this Interface answers NDP requests for proxy neighbor entries
+    /// (`net.ipv6.conf.<name>.proxy_ndp`).
+    fn proxy_ndp(&self) -> Result<bool, Error>;
+    fn set_proxy_ndp(&self, v: bool) -> Result<(), Error>;
+    /// Returns driver, version, firmware and bus info of the device, like `ethtool -i` does.
+    fn driver_info(&self) -> Result<DriverInfo, Error>;
+    /// Returns speed, duplex, autonegotiation and link modes of the device, like `ethtool` does.
+    ///
+    /// Read through the ethtool netlink family, falling back to the `SIOCETHTOOL` ioctl on
+    /// kernels before 5.6.
+    fn link_settings(&self) -> Result<LinkSettings, Error>;
+    /// Returns sizes of ring buffers, like `ethtool -g` does.
+    fn rings(&self) -> Result<Rings, Error>;
+    /// Resizes ring buffers, like `ethtool -G` does. Only `rx`, `rx_mini`, `rx_jumbo` and `tx`
+    /// are applied.
+    fn set_rings(&self, rings: Rings) -> Result<(), Error>;
+    /// Returns numbers of queues, like `ethtool -l` does.
+    fn channels(&self) -> Result<Channels, Error>;
+    /// Changes numbers of queues, like `ethtool -L` does. Only `rx`, `tx`, `other` and
+    /// `combined` are applied.
+    fn set_channels(&self, channels: Channels) -> Result<(), Error>;
+    /// Returns interrupt coalescing parameters, like `ethtool -c` does.
+    fn coalesce(&self) -> Result<Coalesce, Error>;
+    /// Changes interrupt coalescing parameters, like `ethtool -C` does.
+    ///
+    /// Obtain the current parameters with [`coalesce`](Self::coalesce) and modify them, as
+    /// parameters, that the driver doesn't support, must remain zero.
+    fn set_coalesce(&self, coalesce: Coalesce) -> Result<(), Error>;
+    /// Returns features of the device, like `ethtool -k` does.
+    fn features(&self) -> Result<Vec<Feature>, Error>;
+    /// Requests features to be turned on or off by their names, like `ethtool -K` does. A
+    /// requested feature may stay inactive, e.g. if it depends on another one.
+    fn set_features(&self, features: &[(&str, bool)]) -> Result<(), Error>;
+    /// Returns `true` if any feature of the offload is active.
+    fn offload(&self, offload: Offload) -> Result<bool, Error>;
+    /// Turns all changeable features of the offload on or off. Fails with `EOPNOTSUPP`, if none
+    /// of them can be changed.
+    fn set_offload(&self, offload: Offload, v: bool) -> Result<(), Error>;
 }
 
 // Private interface
 impl InterfaceHandle {
     pub fn add_address(&self, network: IpNet) -> Result<(), Error> {
-        let mut socket = Socket::new(NETLINK_ROUTE)?;
-        socket.bind_auto()?;
-        socket.connect(&SocketAddr::new(0, 0))?;
+        let message = make_address_message(self.index, network);
+        // Adding an already assigned address succeeds, like it always did
+        self.request(
+            RtnlMessage::NewAddress(message),
+            NLM_F_CREATE | NLM_F_REPLACE,
+        )?;
+        Ok(())
+    }
 
+    pub fn remove_address(&self, network: IpNet) -> Result<(), Error> {
         let message = make_address_message(self.index, network);
+        self.request(RtnlMessage::DelAddress(message), 0)?;
+        Ok(())
+    }
 
-        let mut req = NetlinkMessage {
-            header: NetlinkHeader {
-                flags: NLM_F_DUMP | NLM_F_REQUEST,
-                ..Default::default()
-            },
-            payload: NetlinkPayload::from(RtnlMessage::NewAddress(message)),
-        };
+    pub fn addresses(&self) -> Result<Vec<IpNet>, Error> {
+        let responses = self.request(
+            RtnlMessage::GetAddress(AddressMessage::default()),
+            NLM_F_DUMP,
+        )?;
 
-        req.finalize();
+        let mut result = vec![];
+        for response in responses {
+            let RtnlMessage::NewAddress(message) = response else {
+                continue;
+            };
+            if message.header.index != self.index {
+                continue;
+            }
 
-        let mut buf = vec![0; req.header.length as _];
-        req.serialize(&mut buf);
+            if let Some(network) = address_network(&message)? {
+                result.push(network);
+            }
+        }
+        Ok(result)
+    }
 
-        debug!(">>> {:?}", req);
-        socket.send(&buf, 0)?;
+    pub fn mtu(&self) -> Result<u32, Error> {
+        self.link()?
+            .nlas
+            .into_iter()
+            .find_map(|nla| match nla {
+                LinkNla::Mtu(mtu) => Some(mtu),
+                _ => None,
+            })
+            .ok_or(Error::UnexpectedMetadata)
+    }
 
-        Ok(())
+    pub fn set_mtu(&self, mtu: u32) -> Result<(), Error> {
+        self.change_link(LinkChange::Mtu(mtu))
     }
 
-    pub fn remove_address(&self, network: IpNet) -> Result<(), Error> {
-        let mut socket = Socket::new(NETLINK_ROUTE)?;
-        socket.bind_auto()?;
-        socket.connect(&SocketAddr::new(0, 0))?;
+    pub fn name(&self) -> Result<String, Error> {
+        link_name(&self.link()?)
+            .map(str::to_string)
+            .ok_or(Error::UnexpectedMetadata)
+    }
 
-        let message = make_address_message(self.index, network);
+    pub fn index(&self) -> Result<u32, Error> {
+        Ok(self.index)
+    }
+
+    pub fn hwaddress(&self) -> Result<MacAddr6, Error> {
+        if self.netns.is_none() {
+            let mut req = ifreq::new(self.name()?);
+            let socket = dummy_socket()?;
 
-        let mut req = NetlinkMessage {
-            header: NetlinkHeader {
-                flags: NLM_F_REQUEST,
-                ..Default::default()
-            },
-            payload: NetlinkPayload::from(RtnlMessage::DelAddress(message)),
+            unsafe { ioctls::siocgifhwaddr(socket.as_raw_fd(), &mut req) }?;
+            return Ok(unsafe { &req.ifr_ifru.ifru_hwaddr.sa_data[0..6] }
+                .try_into()
+                .unwrap());
+        }
+
+        // ioctls are bound to the namespace of the calling thread, ask netlink instead
+        self.link()?
+            .nlas
+            .into_iter()
+            .find_map(|nla| match nla {
+                LinkNla::Address(address) => Some(address),
+                _ => None,
+            })
+            .and_then(|address| MacAddr6::try_from(&address[..]).ok())
+            .ok_or(Error::UnexpectedMetadata)
+    }
+
+    pub fn set_hwaddress(&self, hwaddress: MacAddr6) -> Result<(), Error> {
+        if self.netns.is_none() {
+            let mut req = ifreq::new(self.name()?);
+            req.ifr_ifru.ifru_hwaddr = libc::sockaddr {
+                sa_family: ARPHRD_ETHER,
+                sa_data: unsafe { std::mem::zeroed() },
+            };
+
+            unsafe {
+                req.ifr_ifru.ifru_hwaddr.sa_data[0..6].copy_from_slice(hwaddress.as_c_slice());
+            }
+
+            let socket = dummy_socket()?;
+
+            unsafe { ioctls::siocsifhwaddr(socket.as_raw_fd(), &req) }?;
+            return Ok(());
+        }
+
+        self.change_link(LinkChange::HwAddress(hwaddress))
+    }
+
+    pub fn set_up(&self, v: bool) -> Result<(), Error> {
+        self.change_link(LinkChange::Flag(IFF_UP as _, v))
+    }
+
+    pub fn set_running(&self, v: bool) -> Result<(), Error> {
+        self.change_link(LinkChange::Flag(IFF_RUNNING as _, v))
+    }
+
+    pub fn move_to_netns(
+        &self,
+        target: NetnsTarget,
+        name: Option<&str>,
+    ) -> Result<Interface, Error> {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => self.name()?,
         };
 
-        req.finalize();
+        let mut message = LinkMessage::default();
+        message.header.index = self.index;
+        message.nlas.push(LinkNla::IfName(name.clone()));
 
-        let mut buf = vec![0; req.header.length as _];
-        req.serialize(&mut buf);
+        // Target namespace is opened beforehand to look up the moved interface afterwards
+        let netns = match target {
+            NetnsTarget::Handle(netns) => {
+                message.nlas.push(LinkNla::NetNsFd(netns.as_raw_fd()));
+                netns
+            }
+            NetnsTarget::Pid(pid) => {
+                message.nlas.push(LinkNla::NetNsPid(pid));
+                NetnsHandle::from_pid(pid)?
+            }
+            NetnsTarget::Name(netns_name) => {
+                let netns = NetnsHandle::from_name(&netns_name)?;
+                message.nlas.push(LinkNla::NetNsFd(netns.as_raw_fd()));
+                netns
+            }
+        };
 
-        debug!(">>> {:?}", req);
-        socket.send(&buf, 0).unwrap();
+        self.request(RtnlMessage::SetLink(message), 0)?;
+        Self::try_from_name_in(&name, Some(&netns))
+    }
 
-        Ok(())
+    pub fn try_from_name(name: &str) -> Result<Interface, Error> {
+        Self::try_from_name_in(name, None)
     }
 
-    pub fn hwaddress(&self) -> Result<MacAddr6, Error> {
-        let mut req = ifreq::new(self.name()?);
-        let socket = dummy_socket()?;
+    pub fn try_from_index(index: u32) -> Result<Interface, Error> {
+        let interface = Interface::from_index_unchecked(index);
+        interface.0.link()?;
+        Ok(interface)
+    }
+}
+
+impl InterfaceHandle {
+    pub(crate) fn try_from_name_in(
+        name: &str,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Interface, Error> {
+        let mut message = LinkMessage::default();
+        message.nlas.push(LinkNla::IfName(name.to_string()));
+
+        let socket = netlink::socket(netns)?;
+        let link = netlink::request(&socket, RtnlMessage::GetLink(message), 0)?
+            .into_iter()
+            .find_map(|response| match response {
+                RtnlMessage::NewLink(link) => Some(link),
+                _ => None,
+            })
+            .ok_or(Error::InterfaceNotFound)?;
+
+        Ok(Interface(InterfaceHandle {
+            index: link.header.index,
+            netns: netns.cloned(),
+        }))
+    }
+
+    /// Sends a request to the namespace, this interface belongs to. Every request opens a socket
+    /// of its own, a [`Connection`](super::Connection) keeps one for many requests.
+    pub(crate) fn request(
+        &self,
+        message: RtnlMessage,
+        flags: u16,
+    ) -> Result<Vec<RtnlMessage>, Error> {
+        let socket = netlink::socket(self.netns.as_ref())?;
+        netlink::request(&socket, message, flags)
+    }
 
-        unsafe { ioctls::siocgifhwaddr(socket.as_raw_fd(), &mut req) }?;
-        Ok(unsafe { &req.ifr_ifru.ifru_hwaddr.sa_data[0..6] }
-            .try_into()
-            .unwrap())
+    /// Queries link information (`RTM_GETLINK`) for this interface.
+    pub(crate) fn link(&self) -> Result<LinkMessage, Error> {
+        let mut message = LinkMessage::default();
+        message.header.index = self.index;
+
+        self.request(RtnlMessage::GetLink(message), 0)?
+            .into_iter()
+            .find_map(|response| match response {
+                RtnlMessage::NewLink(link) => Some(link),
+                _ => None,
+            })
+            .ok_or(Error::InterfaceNotFound)
+    }
+
+    fn change_link(&self, change: LinkChange) -> Result<(), Error> {
+        let message = make_link_message(self.index, change);
+        self.request(RtnlMessage::SetLink(message), 0)?;
+        Ok(())
     }
 }
 
@@ -92,28 +307,131 @@ impl InterfaceExt for Interface {
         to self.0 {
             fn set_up(&self, v: bool) -> Result<(), Error>;
             fn set_running(&self, v: bool) -> Result<(), Error>;
+            fn set_hwaddress(&self, hwaddress: MacAddr6) -> Result<(), Error>;
+            fn stats(&self) -> Result<InterfaceStats, Error>;
+            fn kind(&self) -> Result<InterfaceKind, Error>;
+            fn proxy_arp(&self) -> Result<bool, Error>;
+            fn set_proxy_arp(&self, v: bool) -> Result<(), Error>;
+            fn proxy_ndp(&self) -> Result<bool, Error>;
+            fn set_proxy_ndp(&self, v: bool) -> Result<(), Error>;
+            fn driver_info(&self) -> Result<DriverInfo, Error>;
+            fn link_settings(&self) -> Result<LinkSettings, Error>;
+            fn rings(&self) -> Result<Rings, Error>;
+            fn set_rings(&self, rings: Rings) -> Result<(), Error>;
+            fn channels(&self) -> Result<Channels, Error>;
+            fn set_channels(&self, channels: Channels) -> Result<(), Error>;
+            fn coalesce(&self) -> Result<Coalesce, Error>;
+            fn set_coalesce(&self, coalesce: Coalesce) -> Result<(), Error>;
+            fn features(&self) -> Result<Vec<Feature>, Error>;
+            fn set_features(&self, features: &[(&str, bool)]) -> Result<(), Error>;
+            fn offload(&self, offload: Offload) -> Result<bool, Error>;
+            fn set_offload(&self, offload: Offload, v: bool) -> Result<(), Error>;
         }
     }
 
-    fn set_hwaddress(&self, hwaddress: MacAddr6) -> Result<(), Error> {
-        let mut req = ifreq::new(self.name()?);
-        req.ifr_ifru.ifru_hwaddr = libc::sockaddr {
-            sa_family: ARPHRD_ETHER,
-            sa_data: unsafe { std::mem::zeroed() },
-        };
+    fn netns(&self) -> Option<NetnsHandle> {
+        self.0.netns.clone()
+    }
+
+    fn move_to_netns<T: Into<NetnsTarget>>(
+        &self,
+        target: T,
+        name: Option<&str>,
+    ) -> Result<Interface, Error> {
+        self.0.move_to_netns(target.into(), name)
+    }
+}
 
-        unsafe {
-            req.ifr_ifru.ifru_hwaddr.sa_data[0..6].copy_from_slice(hwaddress.as_c_slice());
+pub(crate) fn list_interfaces_in(netns: Option<&NetnsHandle>) -> Result<Vec<Interface>, Error> {
+    Ok(dump_links(netns)?
+        .into_iter()
+        .map(|link| {
+            Interface(InterfaceHandle {
+                index: link.header.index,
+                netns: netns.cloned(),
+            })
+        })
+        .collect())
+}
+
+/// Dumps link information (`RTM_GETLINK`) for all interfaces in the namespace.
+pub(crate) fn dump_links(netns: Option<&NetnsHandle>) -> Result<Vec<LinkMessage>, Error> {
+    let socket = netlink::socket(netns)?;
+    let responses = netlink::request(
+        &socket,
+        RtnlMessage::GetLink(LinkMessage::default()),
+        NLM_F_DUMP,
+    )?;
+
+    Ok(responses
+        .into_iter()
+        .filter_map(|response| match response {
+            RtnlMessage::NewLink(link) => Some(link),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Returns interface name (`IFLA_IFNAME`) from link information.
+pub(crate) fn link_name(link: &LinkMessage) -> Option<&str> {
+    link.nlas.iter().find_map(|nla| match nla {
+        LinkNla::IfName(name) => Some(name.as_str()),
+        _ => None,
+    })
+}
+
+/// Returns address and prefix length from address information.
+pub(crate) fn address_network(message: &AddressMessage) -> Result<Option<IpNet>, Error> {
+    let mut address = None;
+    for nla in &message.nlas {
+        match nla {
+            // IFA_LOCAL is the local address on point-to-point links, prefer it
+            AddressNla::Local(bytes) => address = parse_ip(bytes),
+            AddressNla::Address(bytes) if address.is_none() => address = parse_ip(bytes),
+            _ => {}
         }
+    }
+
+    address
+        .map(|address| {
+            IpNet::new(address, message.header.prefix_len).map_err(|_| Error::UnexpectedMetadata)
+        })
+        .transpose()
+}
 
-        let socket = dummy_socket()?;
+pub(crate) fn parse_ip(bytes: &[u8]) -> Option<IpAddr> {
+    match bytes.len() {
+        4 => Some(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?).into()),
+        16 => Some(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?).into()),
+        _ => None,
+    }
+}
 
-        unsafe { ioctls::siocsifhwaddr(socket.as_raw_fd(), &req) }?;
-        Ok(())
+/// Change of a link attribute, requested by `RTM_SETLINK`.
+pub(crate) enum LinkChange {
+    /// Sets or clears an `IFF_*` flag, leaving other flags unchanged.
+    Flag(u32, bool),
+    Mtu(u32),
+    HwAddress(MacAddr6),
+}
+
+pub(crate) fn make_link_message(index: u32, change: LinkChange) -> LinkMessage {
+    let mut message = LinkMessage::default();
+    message.header.index = index;
+    match change {
+        LinkChange::Flag(flag, v) => {
+            message.header.flags = if v { flag } else { 0 };
+            message.header.change_mask = flag;
+        }
+        LinkChange::Mtu(mtu) => message.nlas.push(LinkNla::Mtu(mtu)),
+        LinkChange::HwAddress(hwaddress) => message
+            .nlas
+            .push(LinkNla::Address(hwaddress.as_slice().to_vec())),
     }
+    message
 }
 
-fn make_address_message(index: u32, network: IpNet) -> AddressMessage {
+pub(crate) fn make_address_message(index: u32, network: IpNet) -> AddressMessage {
     let mut message = AddressMessage::default();
     message.header.prefix_len = network.prefix_len();
     message.header.index = index;
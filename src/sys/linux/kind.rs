@@ -0,0 +1,248 @@
+use super::handle::link_name;
+use crate::sys::InterfaceHandle;
+use crate::Error;
+use libc::{
+    ARPHRD_ETHER, ARPHRD_IEEE80211, ARPHRD_IEEE80211_PRISM, ARPHRD_IEEE80211_RADIOTAP,
+    ARPHRD_INFINIBAND, ARPHRD_LOOPBACK, ARPHRD_NONE, ARPHRD_PPP,
+};
+use netlink_packet_route::link::nlas::{Info, InfoKind, Nla as LinkNla};
+use netlink_packet_route::LinkMessage;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Type of a network interface.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InterfaceKind {
+    Loopback,
+    /// Ethernet device without a virtual link kind, usually a physical NIC.
+    Ethernet,
+    Wireless,
+    Infiniband,
+    Ppp,
+    Bridge,
+    Bond,
+    Team,
+    Vlan,
+    Veth,
+    Tun,
+    Tap,
+    Vxlan,
+    Geneve,
+    Wireguard,
+    Dummy,
+    Ifb,
+    Gre,
+    Gretap,
+    Ip6Gre,
+    Ip6Gretap,
+    Ipip,
+    Sit,
+    Ip6Tunnel,
+    Vti,
+    Vti6,
+    Macvlan,
+    Macvtap,
+    Ipvlan,
+    Vrf,
+    /// Netlink monitor, captures netlink traffic.
+    Nlmon,
+    /// GPRS tunnel.
+    Gtp,
+    /// Link kind (`IFLA_INFO_KIND`), unknown to this crate.
+    Other(String),
+}
+
+impl InterfaceKind {
+    /// Returns `true` for software interfaces, that are not backed by a hardware device.
+    ///
+    /// Returns `false` for [`Other`](Self::Other) kinds too, as they may stand for either, e.g.
+    /// `can` devices.
+    pub fn is_virtual(&self) -> bool {
+        !matches!(
+            self,
+            Self::Ethernet | Self::Wireless | Self::Infiniband | Self::Ppp | Self::Other(_)
+        )
+    }
+
+    pub(crate) fn from_link(link: &LinkMessage) -> Self {
+        let link_layer_type = link.header.link_layer_type;
+
+        let info_kind = link.nlas.iter().find_map(|nla| match nla {
+            LinkNla::Info(infos) => infos.iter().find_map(|info| match info {
+                Info::Kind(kind) => Some(kind),
+                _ => None,
+            }),
+            _ => None,
+        });
+
+        match info_kind {
+            Some(kind) => Self::from_info_kind(kind, link_layer_type),
+            None => Self::from_link_layer_type(link_layer_type),
+        }
+    }
+
+    fn from_info_kind(kind: &InfoKind, link_layer_type: u16) -> Self {
+        match kind {
+            InfoKind::Dummy => Self::Dummy,
+            InfoKind::Ifb => Self::Ifb,
+            InfoKind::Bridge => Self::Bridge,
+            // tun and tap share the same link kind, tap devices carry Ethernet frames
+            InfoKind::Tun if link_layer_type == ARPHRD_ETHER => Self::Tap,
+            InfoKind::Tun => Self::Tun,
+            InfoKind::Vlan => Self::Vlan,
+            InfoKind::Veth => Self::Veth,
+            InfoKind::Vxlan => Self::Vxlan,
+            InfoKind::Bond => Self::Bond,
+            InfoKind::IpVlan => Self::Ipvlan,
+            InfoKind::MacVlan => Self::Macvlan,
+            InfoKind::MacVtap => Self::Macvtap,
+            InfoKind::GreTap => Self::Gretap,
+            InfoKind::GreTap6 => Self::Ip6Gretap,
+            InfoKind::IpTun => Self::Ipip,
+            InfoKind::SitTun => Self::Sit,
+            InfoKind::GreTun => Self::Gre,
+            InfoKind::GreTun6 => Self::Ip6Gre,
+            InfoKind::Vti => Self::Vti,
+            InfoKind::Vrf => Self::Vrf,
+            InfoKind::Ipoib => Self::Infiniband,
+            InfoKind::Wireguard => Self::Wireguard,
+            InfoKind::Nlmon => Self::Nlmon,
+            InfoKind::Gtp => Self::Gtp,
+            InfoKind::Other(kind) => match kind.as_str() {
+                "team" => Self::Team,
+                "geneve" => Self::Geneve,
+                "ip6tnl" => Self::Ip6Tunnel,
+                "vti6" => Self::Vti6,
+                _ => Self::Other(kind.clone()),
+            },
+        }
+    }
+
+    fn from_link_layer_type(link_layer_type: u16) -> Self {
+        match link_layer_type {
+            ARPHRD_LOOPBACK => Self::Loopback,
+            ARPHRD_ETHER => Self::Ethernet,
+            ARPHRD_IEEE80211 | ARPHRD_IEEE80211_PRISM | ARPHRD_IEEE80211_RADIOTAP => Self::Wireless,
+            ARPHRD_INFINIBAND => Self::Infiniband,
+            ARPHRD_PPP => Self::Ppp,
+            // tun devices, created by older kernels or userspace, may lack IFLA_LINKINFO
+            ARPHRD_NONE => Self::Tun,
+            _ => Self::Other(format!("arphrd-{link_layer_type}")),
+        }
+    }
+
+    /// Refines the kind, using `DEVTYPE` from sysfs `uevent`.
+    ///
+    /// Wireless devices are plain Ethernet devices from the netlink point of view.
+    fn refine_from_sysfs(self, name: &str) -> Self {
+        if self != Self::Ethernet {
+            return self;
+        }
+
+        let uevent = Path::new("/sys/class/net").join(name).join("uevent");
+        let Ok(uevent) = fs::read_to_string(uevent) else {
+            return self;
+        };
+
+        match uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("DEVTYPE="))
+        {
+            Some("wlan") => Self::Wireless,
+            Some("bridge") => Self::Bridge,
+            Some("bond") => Self::Bond,
+            Some("vlan") => Self::Vlan,
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for InterfaceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Loopback => "loopback",
+            Self::Ethernet => "ethernet",
+            Self::Wireless => "wireless",
+            Self::Infiniband => "infiniband",
+            Self::Ppp => "ppp",
+            Self::Bridge => "bridge",
+            Self::Bond => "bond",
+            Self::Team => "team",
+            Self::Vlan => "vlan",
+            Self::Veth => "veth",
+            Self::Tun => "tun",
+            Self::Tap => "tap",
+            Self::Vxlan => "vxlan",
+            Self::Geneve => "geneve",
+            Self::Wireguard => "wireguard",
+            Self::Dummy => "dummy",
+            Self::Ifb => "ifb",
+            Self::Gre => "gre",
+            Self::Gretap => "gretap",
+            Self::Ip6Gre => "ip6gre",
+            Self::Ip6Gretap => "ip6gretap",
+            Self::Ipip => "ipip",
+            Self::Sit => "sit",
+            Self::Ip6Tunnel => "ip6tnl",
+            Self::Vti => "vti",
+            Self::Vti6 => "vti6",
+            Self::Macvlan => "macvlan",
+            Self::Macvtap => "macvtap",
+            Self::Ipvlan => "ipvlan",
+            Self::Vrf => "vrf",
+            Self::Nlmon => "nlmon",
+            Self::Gtp => "gtp",
+            Self::Other(kind) => kind,
+        };
+        f.write_str(name)
+    }
+}
+
+impl InterfaceHandle {
+    pub fn kind(&self) -> Result<InterfaceKind, Error> {
+        let link = self.link()?;
+        let kind = InterfaceKind::from_link(&link);
+
+        // sysfs reflects the namespace of the calling thread only
+        match (&self.netns, link_name(&link)) {
+            (None, Some(name)) => Ok(kind.refine_from_sysfs(name)),
+            _ => Ok(kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vti6() {
+        let kind = InterfaceKind::from_info_kind(&InfoKind::Other("vti6".to_string()), 0);
+        assert_eq!(kind, InterfaceKind::Vti6);
+        assert_eq!(kind.to_string(), "vti6");
+        assert!(kind.is_virtual());
+    }
+
+    #[test]
+    fn unknown_kinds_are_not_virtual() {
+        let kind = InterfaceKind::from_info_kind(&InfoKind::Other("can".to_string()), 0);
+        assert!(!kind.is_virtual());
+
+        let kind = InterfaceKind::from_link_layer_type(804);
+        assert_eq!(kind, InterfaceKind::Other("arphrd-804".to_string()));
+        assert!(!kind.is_virtual());
+
+        // Software kinds, known to netlink-packet-route, don't fall back to Other
+        for (info_kind, name) in [(InfoKind::Nlmon, "nlmon"), (InfoKind::Gtp, "gtp")] {
+            let kind = InterfaceKind::from_info_kind(&info_kind, 0);
+            assert!(!matches!(kind, InterfaceKind::Other(_)));
+            assert_eq!(kind.to_string(), name);
+            assert!(kind.is_virtual());
+        }
+
+        assert!(InterfaceKind::from_link_layer_type(ARPHRD_LOOPBACK).is_virtual());
+        assert!(!InterfaceKind::from_link_layer_type(ARPHRD_ETHER).is_virtual());
+    }
+}
@@ -1,3 +1,92 @@
+/// Defines a `u8`-backed enum with an `Other` variant for values, unknown to this crate.
+macro_rules! u8_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident = $value:expr,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)+
+            Other(u8),
+        }
+
+        impl From<u8> for $name {
+            fn from(value: u8) -> Self {
+                match value {
+                    $($value => Self::$variant,)+
+                    other => Self::Other(other),
+                }
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value,)+
+                    $name::Other(other) => other,
+                }
+            }
+        }
+    };
+}
+
+pub use bridge::{
+    add_fdb, add_mdb, delete_fdb, delete_mdb, list_fdb, list_mdb, replace_fdb, BridgeFilter,
+    FdbEntry, FdbState, MdbEntry,
+};
+pub use cache::Cache;
+pub use connection::{Batch, Connection};
+pub use ethtool::{Channels, Coalesce, DriverInfo, Duplex, LinkSettings, PortType, Rings};
+pub use genl::{
+    resolve_family, GenericConnection, GenericFamily, GenericMessage, GenericRequest,
+    MulticastGroup,
+};
 pub use handle::InterfaceExt;
+pub use kind::InterfaceKind;
+pub use monitor::{AddressInfo, Event, LinkInfo, Monitor, MonitorGroup};
+pub use neighbor::{
+    add_neighbor, delete_neighbor, flush_neighbors, list_neighbors, replace_neighbor, Neighbor,
+    NeighborFilter, NeighborState,
+};
+pub use netns::{NetnsHandle, NetnsTarget};
+pub use nexthop::{
+    add_nexthop, delete_nexthop, list_nexthops, replace_nexthop, Nexthop, NexthopGroup,
+    NexthopGroupMember, NexthopKind, ResilientGroup,
+};
+pub use offload::{Feature, Offload};
+pub use rate::{RateEvent, RateSample, RateSampler};
+pub use route::{
+    add_route, default_routes, delete_route, list_routes, replace_route, route_get,
+    set_default_gateway, IpFamily, NextHop, Route, RouteFilter, RouteGetOptions, RouteMetrics,
+    RouteProtocol, RouteScope, RouteSpec, RouteType,
+};
+pub use rule::{add_rule, delete_rule, list_rules, Rule, RuleAction};
+pub use stats::InterfaceStats;
+pub use wait::{
+    wait_for_address, wait_for_carrier, wait_for_interface, wait_until_removed, wait_until_up,
+};
 
+mod bridge;
+mod cache;
+mod connection;
+mod ethtool;
+mod genl;
 mod handle;
+mod kind;
+mod monitor;
+mod neighbor;
+mod netlink;
+mod netns;
+mod nexthop;
+#[cfg(feature = "tokio")]
+pub mod nonblocking;
+mod offload;
+mod rate;
+mod route;
+mod rule;
+mod stats;
+mod sysctl;
+mod wait;
@@ -0,0 +1,655 @@
+use super::handle::{address_network, dump_links, link_name};
+use super::{netlink, Neighbor, NetnsHandle, Route};
+use crate::sys::InterfaceHandle;
+use crate::{Error, Interface};
+use advmac::MacAddr6;
+use ipnet::IpNet;
+use libc::{IFF_LOWER_UP, IFF_RUNNING, IFF_UP};
+use log::warn;
+use netlink_packet_core::{NetlinkMessage, NetlinkPayload};
+use netlink_packet_route::address::Nla as AddressNla;
+use netlink_packet_route::link::nlas::Nla as LinkNla;
+use netlink_packet_route::{
+    AddressMessage, LinkMessage, RtnlMessage, IFA_F_DADFAILED, IFA_F_DEPRECATED, IFA_F_TENTATIVE,
+    NLM_F_REPLACE, RTNLGRP_IPV4_IFADDR, RTNLGRP_IPV4_ROUTE, RTNLGRP_IPV6_IFADDR,
+    RTNLGRP_IPV6_ROUTE, RTNLGRP_LINK, RTNLGRP_NEIGH,
+};
+use netlink_sys::{Socket, SocketAddr};
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags};
+use std::collections::{HashSet, VecDeque};
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+
+/// Notification group, a [`Monitor`] can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MonitorGroup {
+    Link,
+    Ipv4Address,
+    Ipv6Address,
+    Ipv4Route,
+    Ipv6Route,
+    Neighbor,
+}
+
+impl MonitorGroup {
+    pub const ALL: [MonitorGroup; 6] = [
+        MonitorGroup::Link,
+        MonitorGroup::Ipv4Address,
+        MonitorGroup::Ipv6Address,
+        MonitorGroup::Ipv4Route,
+        MonitorGroup::Ipv6Route,
+        MonitorGroup::Neighbor,
+    ];
+
+    fn rtnlgrp(self) -> u32 {
+        match self {
+            MonitorGroup::Link => RTNLGRP_LINK,
+            MonitorGroup::Ipv4Address => RTNLGRP_IPV4_IFADDR,
+            MonitorGroup::Ipv6Address => RTNLGRP_IPV6_IFADDR,
+            MonitorGroup::Ipv4Route => RTNLGRP_IPV4_ROUTE,
+            MonitorGroup::Ipv6Route => RTNLGRP_IPV6_ROUTE,
+            MonitorGroup::Neighbor => RTNLGRP_NEIGH,
+        }
+    }
+}
+
+/// State of a link, carried by link events.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct LinkInfo {
+    pub interface: Interface,
+    pub name: String,
+    /// Combination of `IFF_*` flags.
+    pub flags: u32,
+    pub mtu: Option<u32>,
+    pub hwaddress: Option<MacAddr6>,
+}
+
+impl LinkInfo {
+    /// Returns `true` if the link is administratively up (`IFF_UP`).
+    pub fn is_up(&self) -> bool {
+        self.flags & IFF_UP as u32 != 0
+    }
+
+    /// Returns `true` if the link is operational (`IFF_RUNNING`).
+    pub fn is_running(&self) -> bool {
+        self.flags & IFF_RUNNING as u32 != 0
+    }
+
+    /// Returns `true` if the link has carrier (`IFF_LOWER_UP`).
+    pub fn has_carrier(&self) -> bool {
+        self.flags & IFF_LOWER_UP as u32 != 0
+    }
+
+    pub(crate) fn from_message(message: LinkMessage, netns: Option<&NetnsHandle>) -> Self {
+        let mut info = LinkInfo {
+            interface: Interface(InterfaceHandle {
+                index: message.header.index,
+                netns: netns.cloned(),
+            }),
+            name: link_name(&message).unwrap_or_default().to_string(),
+            flags: message.header.flags,
+            mtu: None,
+            hwaddress: None,
+        };
+
+        for nla in message.nlas {
+            match nla {
+                LinkNla::Mtu(mtu) => info.mtu = Some(mtu),
+                LinkNla::Address(address) => info.hwaddress = MacAddr6::try_from(&address[..]).ok(),
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+/// IP address, carried by address events.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct AddressInfo {
+    pub interface: Interface,
+    pub network: IpNet,
+    /// Combination of `IFA_F_*` flags.
+    pub flags: u32,
+}
+
+impl AddressInfo {
+    /// Returns `true` while IPv6 duplicate address detection is in progress. Such address can't be
+    /// used yet.
+    pub fn is_tentative(&self) -> bool {
+        self.flags & IFA_F_TENTATIVE != 0
+    }
+
+    /// Returns `true` if duplicate address detection has found a conflict.
+    pub fn is_dad_failed(&self) -> bool {
+        self.flags & IFA_F_DADFAILED != 0
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.flags & IFA_F_DEPRECATED != 0
+    }
+
+    pub(crate) fn from_message(
+        message: AddressMessage,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Option<Self>, Error> {
+        let Some(network) = address_network(&message)? else {
+            return Ok(None);
+        };
+
+        // IFA_FLAGS carries all flags, the header field only the lower 8 bits
+        let flags = message
+            .nlas
+            .iter()
+            .find_map(|nla| match nla {
+                AddressNla::Flags(flags) => Some(*flags),
+                _ => None,
+            })
+            .unwrap_or(message.header.flags as u32);
+
+        Ok(Some(Self {
+            interface: Interface(InterfaceHandle {
+                index: message.header.index,
+                netns: netns.cloned(),
+            }),
+            network,
+            flags,
+        }))
+    }
+}
+
+/// Change of network configuration, reported by a [`Monitor`].
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub enum Event {
+    LinkAdded(LinkInfo),
+    /// Flags, MTU, name or another attribute of an existing link has changed.
+    LinkChanged(LinkInfo),
+    /// Link was deleted or moved to another namespace.
+    LinkRemoved(LinkInfo),
+    /// Address was added, or its flags or lifetimes were updated.
+    AddressAdded(AddressInfo),
+    AddressRemoved(AddressInfo),
+    /// Route was added, or replaced an existing one.
+    ///
+    /// A replaced route is not reported as removed. It had the same table, destination, type of
+    /// service and metric, but may have differed in other attributes, e.g. the gateway.
+    RouteAdded {
+        route: Route,
+        replaced: bool,
+    },
+    RouteRemoved(Route),
+    /// Neighbor entry was added, or its state has changed.
+    NeighborChanged(Neighbor),
+    NeighborRemoved(Neighbor),
+    /// The kernel has dropped notifications, because they were not read fast enough (`ENOBUFS`).
+    ///
+    /// Events may have been lost, state obtained from earlier events must be re-read.
+    Resync,
+}
+
+/// Subscription to network configuration changes, delivered by the kernel over netlink.
+///
+/// Events are read with [`next_event`](Self::next_event), or by iterating the Monitor, which blocks
+/// until the next event arrives.
+pub struct Monitor {
+    socket: Socket,
+    netns: Option<NetnsHandle>,
+    /// Indexes of known links, to tell added links from changed ones.
+    links: HashSet<u32>,
+    pending: VecDeque<Event>,
+}
+
+impl Monitor {
+    /// Subscribes to all groups in the namespace of the calling thread.
+    pub fn new() -> Result<Self, Error> {
+        Self::new_in(None, &MonitorGroup::ALL)
+    }
+
+    /// Subscribes to given groups in the namespace of the calling thread.
+    pub fn with_groups(groups: &[MonitorGroup]) -> Result<Self, Error> {
+        Self::new_in(None, groups)
+    }
+
+    pub(crate) fn new_in(
+        netns: Option<&NetnsHandle>,
+        groups: &[MonitorGroup],
+    ) -> Result<Self, Error> {
+        // Links are listed before subscribing, so that notifications about links, created in
+        // between, aren't mistaken for changes of known links. Such links are reported as added
+        // on their next change instead.
+        let links = dump_links(netns)?;
+        Self::subscribe(netlink::socket(netns)?, netns, groups, &links)
+    }
+
+    /// Subscribes `socket` to given groups. `links` must be listed before subscribing.
+    pub(crate) fn subscribe(
+        mut socket: Socket,
+        netns: Option<&NetnsHandle>,
+        groups: &[MonitorGroup],
+        links: &[LinkMessage],
+    ) -> Result<Self, Error> {
+        add_memberships(&mut socket, groups)?;
+
+        let mut monitor = Monitor {
+            socket,
+            netns: netns.cloned(),
+            links: HashSet::new(),
+            pending: VecDeque::new(),
+        };
+        monitor.set_links(links);
+        Ok(monitor)
+    }
+
+    /// Returns the next event, blocking until it arrives.
+    pub fn next_event(&mut self) -> Result<Event, Error> {
+        loop {
+            if let Some(event) = self.pending_event() {
+                return Ok(event);
+            }
+
+            if let Some(event) = self.receive_blocking()? {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Returns the next event, or `None` if none arrives within `timeout`.
+    pub fn next_event_timeout(&mut self, timeout: Duration) -> Result<Option<Event>, Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(event) = self.pending_event() {
+                return Ok(Some(event));
+            }
+
+            // Rounded up, so that poll doesn't return just before the deadline
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let millis = ((remaining.as_micros() + 999) / 1000).min(i32::MAX as u128) as i32;
+            let mut fds = [PollFd::new(self.socket.as_raw_fd(), PollFlags::POLLIN)];
+            match poll(&mut fds, millis) {
+                Ok(0) => return Ok(None),
+                Ok(_) => {}
+                Err(Errno::EINTR) => continue,
+                Err(e) => return Err(e.into()),
+            }
+
+            if let Some(event) = self.receive_blocking()? {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    /// Returns a pending event, parsed from an earlier datagram, if any.
+    pub(crate) fn pending_event(&mut self) -> Option<Event> {
+        self.pending.pop_front()
+    }
+
+    /// Receives a datagram, blocking until it arrives, and re-reads links on overrun.
+    fn receive_blocking(&mut self) -> Result<Option<Event>, Error> {
+        let result = self.socket.recv_from_full();
+        let event = self.receive(result)?;
+        if let Some(Event::Resync) = event {
+            self.sync_links()?;
+        }
+        Ok(event)
+    }
+
+    /// Handles the result of receiving from the socket. Returns [`Event::Resync`] on overrun, other
+    /// events are queued. On overrun, the caller has to re-read links with
+    /// [`set_links`](Self::set_links).
+    pub(crate) fn receive(
+        &mut self,
+        result: io::Result<(Vec<u8>, SocketAddr)>,
+    ) -> Result<Option<Event>, Error> {
+        match result {
+            Ok((buffer, _)) => {
+                self.process(&buffer);
+                Ok(None)
+            }
+            Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                self.pending.clear();
+                Ok(Some(Event::Resync))
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn socket(&self) -> &Socket {
+        &self.socket
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn netns(&self) -> Option<&NetnsHandle> {
+        self.netns.as_ref()
+    }
+
+    /// Parses all notifications from a datagram, received by the socket, into pending events.
+    ///
+    /// Malformed notifications are logged and skipped, so that they don't take the other events
+    /// of the datagram with them.
+    fn process(&mut self, buffer: &[u8]) {
+        let mut offset = 0;
+        // Every message starts with its length (`nlmsg_len`)
+        while let Some(length) = buffer.get(offset..offset + 4) {
+            let length = u32::from_ne_bytes(length.try_into().unwrap()) as usize;
+            if length == 0 {
+                break;
+            }
+            let end = buffer.len().min(offset + length);
+            let message = &buffer[offset..end];
+            offset += netlink::align(length);
+
+            let event = NetlinkMessage::<RtnlMessage>::deserialize(message)
+                .map_err(Error::from)
+                .and_then(|message| match message.payload {
+                    NetlinkPayload::InnerMessage(inner) => self.event(inner, message.header.flags),
+                    _ => Ok(None),
+                });
+            match event {
+                Ok(Some(event)) => self.pending.push_back(event),
+                Ok(None) => {}
+                Err(e) => warn!("Skipping malformed notification: {}", e),
+            }
+        }
+    }
+
+    /// Converts a notification, `flags` are those of its netlink header.
+    fn event(&mut self, message: RtnlMessage, flags: u16) -> Result<Option<Event>, Error> {
+        let netns = self.netns.as_ref();
+        let event = match message {
+            RtnlMessage::NewLink(link) => {
+                let info = LinkInfo::from_message(link, netns);
+                if self.links.insert(info.interface.0.index) {
+                    Event::LinkAdded(info)
+                } else {
+                    Event::LinkChanged(info)
+                }
+            }
+            RtnlMessage::DelLink(link) => {
+                let info = LinkInfo::from_message(link, netns);
+                self.links.remove(&info.interface.0.index);
+                Event::LinkRemoved(info)
+            }
+            RtnlMessage::NewAddress(address) => match AddressInfo::from_message(address, netns)? {
+                Some(info) => Event::AddressAdded(info),
+                None => return Ok(None),
+            },
+            RtnlMessage::DelAddress(address) => match AddressInfo::from_message(address, netns)? {
+                Some(info) => Event::AddressRemoved(info),
+                None => return Ok(None),
+            },
+            RtnlMessage::NewRoute(route) => match Route::from_message(route, netns)? {
+                Some(route) => Event::RouteAdded {
+                    route,
+                    replaced: flags & NLM_F_REPLACE != 0,
+                },
+                None => return Ok(None),
+            },
+            RtnlMessage::DelRoute(route) => match Route::from_message(route, netns)? {
+                Some(route) => Event::RouteRemoved(route),
+                None => return Ok(None),
+            },
+            RtnlMessage::NewNeighbour(neighbor) => match Neighbor::from_message(neighbor, netns)? {
+                Some(neighbor) => Event::NeighborChanged(neighbor),
+                None => return Ok(None),
+            },
+            RtnlMessage::DelNeighbour(neighbor) => match Neighbor::from_message(neighbor, netns)? {
+                Some(neighbor) => Event::NeighborRemoved(neighbor),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+
+    /// Re-reads the set of known links.
+    fn sync_links(&mut self) -> Result<(), Error> {
+        let links = dump_links(self.netns.as_ref())?;
+        self.set_links(&links);
+        Ok(())
+    }
+
+    /// Replaces the set of known links with a fresh dump.
+    pub(crate) fn set_links(&mut self, links: &[LinkMessage]) {
+        self.links = links.iter().map(|link| link.header.index).collect();
+    }
+}
+
+/// Joins given groups. `add_membership` takes `&mut self` in older versions of netlink-sys and
+/// `&self` in newer ones, the reference works with both.
+fn add_memberships(socket: &mut Socket, groups: &[MonitorGroup]) -> Result<(), Error> {
+    for group in groups {
+        socket.add_membership(group.rtnlgrp())?;
+    }
+    Ok(())
+}
+
+impl Iterator for Monitor {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_event())
+    }
+}
+
+impl AsRawFd for Monitor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl NetnsHandle {
+    /// Subscribes to given groups inside this namespace.
+    pub fn monitor(&self, groups: &[MonitorGroup]) -> Result<Monitor, Error> {
+        Monitor::new_in(Some(self), groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netlink_packet_core::NetlinkHeader;
+    use netlink_packet_route::route::Nla as RouteNla;
+    use netlink_packet_route::{RouteMessage, IFA_F_PERMANENT, NLM_F_CREATE, RTM_NEWLINK};
+    use netlink_sys::constants::NETLINK_ROUTE;
+    use std::net::Ipv6Addr;
+
+    /// Creates a Monitor, that knows links with given indexes. Its socket is not subscribed.
+    fn monitor(links: &[u32]) -> Monitor {
+        Monitor {
+            socket: Socket::new(NETLINK_ROUTE).unwrap(),
+            netns: None,
+            links: links.iter().copied().collect(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Appends a notification, like the kernel sends it, to `buffer`.
+    fn notify(buffer: &mut Vec<u8>, message: RtnlMessage, flags: u16) {
+        let mut notification = NetlinkMessage {
+            header: NetlinkHeader {
+                flags,
+                ..Default::default()
+            },
+            payload: NetlinkPayload::InnerMessage(message),
+        };
+        notification.finalize();
+
+        let offset = buffer.len();
+        let length = notification.header.length as usize;
+        buffer.resize(offset + netlink::align(length), 0);
+        notification.serialize(&mut buffer[offset..offset + length]);
+    }
+
+    fn link(index: u32) -> LinkMessage {
+        let mut message = LinkMessage::default();
+        message.header.index = index;
+        message.nlas.push(LinkNla::IfName(format!("test{index}")));
+        message
+    }
+
+    fn route(destination: [u8; 4]) -> RouteMessage {
+        let mut message = RouteMessage::default();
+        message.header.address_family = libc::AF_INET as u8;
+        message.header.destination_prefix_length = 24;
+        message.header.table = Route::TABLE_MAIN as u8;
+        message
+            .nlas
+            .push(RouteNla::Destination(destination.to_vec()));
+        message
+    }
+
+    fn events(monitor: &mut Monitor) -> Vec<Event> {
+        std::iter::from_fn(|| monitor.pending_event()).collect()
+    }
+
+    #[test]
+    fn link_added_then_changed() {
+        let mut monitor = monitor(&[1]);
+        let mut buffer = vec![];
+        notify(&mut buffer, RtnlMessage::NewLink(link(5)), 0);
+        notify(&mut buffer, RtnlMessage::NewLink(link(5)), 0);
+        notify(&mut buffer, RtnlMessage::NewLink(link(1)), 0);
+        monitor.process(&buffer);
+
+        let events = events(&mut monitor);
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[0], Event::LinkAdded(info) if info.name == "test5"));
+        assert!(matches!(&events[1], Event::LinkChanged(info) if info.interface.0.index == 5));
+        assert!(matches!(&events[2], Event::LinkChanged(info) if info.interface.0.index == 1));
+    }
+
+    #[test]
+    fn link_removed() {
+        let mut monitor = monitor(&[1, 5]);
+        let mut buffer = vec![];
+        notify(&mut buffer, RtnlMessage::DelLink(link(5)), 0);
+        // A link with the same index is new again
+        notify(&mut buffer, RtnlMessage::NewLink(link(5)), 0);
+        monitor.process(&buffer);
+
+        let events = events(&mut monitor);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], Event::LinkRemoved(info) if info.name == "test5"));
+        assert!(matches!(&events[1], Event::LinkAdded(info) if info.interface.0.index == 5));
+        assert_eq!(monitor.links, [1, 5].into_iter().collect());
+    }
+
+    #[test]
+    fn malformed_notifications_are_skipped() {
+        let mut monitor = monitor(&[]);
+        let mut buffer = vec![];
+        notify(&mut buffer, RtnlMessage::NewLink(link(2)), 0);
+
+        // RTM_NEWLINK, too short for struct ifinfomsg
+        buffer.extend_from_slice(&20u32.to_ne_bytes());
+        buffer.extend_from_slice(&RTM_NEWLINK.to_ne_bytes());
+        buffer.extend_from_slice(&[0; 14]);
+
+        // Address with a prefix, too long for IPv4
+        let mut address = AddressMessage::default();
+        address.header.family = libc::AF_INET as u8;
+        address.header.prefix_len = 40;
+        address.nlas.push(AddressNla::Address(vec![10, 0, 0, 1]));
+        notify(&mut buffer, RtnlMessage::NewAddress(address), 0);
+
+        notify(&mut buffer, RtnlMessage::DelLink(link(2)), 0);
+
+        // Truncated notification at the end of the datagram
+        let mut truncated = vec![];
+        notify(&mut truncated, RtnlMessage::NewLink(link(3)), 0);
+        buffer.extend_from_slice(&truncated[..truncated.len() - 8]);
+
+        monitor.process(&buffer);
+        let events = events(&mut monitor);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], Event::LinkAdded(_)));
+        assert!(matches!(&events[1], Event::LinkRemoved(_)));
+    }
+
+    #[test]
+    fn overrun_resyncs() {
+        let mut monitor = monitor(&[]);
+        let mut buffer = vec![];
+        notify(&mut buffer, RtnlMessage::NewLink(link(2)), 0);
+        let result = monitor.receive(Ok((buffer, SocketAddr::new(0, 0))));
+        assert!(matches!(result, Ok(None)));
+
+        // Queued events are stale after an overrun
+        let result = monitor.receive(Err(io::Error::from_raw_os_error(libc::ENOBUFS)));
+        assert!(matches!(result, Ok(Some(Event::Resync))));
+        assert!(monitor.pending_event().is_none());
+
+        let result = monitor.receive(Err(io::Error::from_raw_os_error(libc::EINTR)));
+        assert!(matches!(result, Ok(None)));
+        let result = monitor.receive(Err(io::Error::from_raw_os_error(libc::EBADF)));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn replaced_routes() {
+        let mut monitor = monitor(&[]);
+        let mut buffer = vec![];
+        notify(
+            &mut buffer,
+            RtnlMessage::NewRoute(route([10, 0, 1, 0])),
+            NLM_F_CREATE,
+        );
+        notify(
+            &mut buffer,
+            RtnlMessage::NewRoute(route([10, 0, 2, 0])),
+            NLM_F_CREATE | NLM_F_REPLACE,
+        );
+        notify(&mut buffer, RtnlMessage::DelRoute(route([10, 0, 1, 0])), 0);
+        monitor.process(&buffer);
+
+        let events = events(&mut monitor);
+        assert_eq!(events.len(), 3);
+        let Event::RouteAdded { route, replaced } = &events[0] else {
+            panic!("expected an added route");
+        };
+        assert_eq!(route.destination, "10.0.1.0/24".parse().unwrap());
+        assert!(!replaced);
+        assert!(matches!(
+            &events[1],
+            Event::RouteAdded { replaced: true, .. }
+        ));
+        assert!(matches!(&events[2], Event::RouteRemoved(_)));
+    }
+
+    #[test]
+    fn address_flags() {
+        let mut monitor = monitor(&[]);
+        let mut address = AddressMessage::default();
+        address.header.family = libc::AF_INET6 as u8;
+        address.header.prefix_len = 64;
+        address.header.index = 2;
+        address.header.flags = IFA_F_PERMANENT as u8;
+        address.nlas.push(AddressNla::Address(
+            "fd00::1".parse::<Ipv6Addr>().unwrap().octets().to_vec(),
+        ));
+        // IFA_FLAGS takes precedence over the header
+        address.nlas.push(AddressNla::Flags(IFA_F_TENTATIVE));
+
+        let mut buffer = vec![];
+        notify(&mut buffer, RtnlMessage::NewAddress(address.clone()), 0);
+        notify(&mut buffer, RtnlMessage::DelAddress(address), 0);
+        monitor.process(&buffer);
+
+        let events = events(&mut monitor);
+        assert_eq!(events.len(), 2);
+        let Event::AddressAdded(info) = &events[0] else {
+            panic!("expected an added address");
+        };
+        assert_eq!(info.network, "fd00::1/64".parse().unwrap());
+        assert_eq!(info.interface.0.index, 2);
+        assert!(info.is_tentative());
+        assert!(!info.is_dad_failed());
+        assert!(matches!(&events[1], Event::AddressRemoved(_)));
+    }
+}
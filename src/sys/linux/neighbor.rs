@@ -0,0 +1,603 @@
+use super::handle::parse_ip;
+use super::route::ip_to_vec;
+use super::{netlink, IpFamily, NetnsHandle};
+use crate::sys::InterfaceHandle;
+use crate::{Error, Interface};
+use advmac::MacAddr6;
+use netlink_packet_route::neighbour::Nla as NeighbourNla;
+use netlink_packet_route::{
+    NeighbourMessage, RtnlMessage, NLM_F_CREATE, NLM_F_DUMP, NLM_F_EXCL, NLM_F_REPLACE, NTF_PROXY,
+    NTF_ROUTER, NUD_DELAY, NUD_FAILED, NUD_INCOMPLETE, NUD_NOARP, NUD_NONE, NUD_PERMANENT,
+    NUD_PROBE, NUD_REACHABLE, NUD_STALE,
+};
+use netlink_sys::Socket;
+use std::net::IpAddr;
+
+/// State of a neighbor cache entry (`NUD_*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NeighborState {
+    None,
+    /// Address resolution is in progress.
+    Incomplete,
+    /// Neighbor was confirmed reachable recently.
+    Reachable,
+    /// Neighbor is not confirmed reachable, it will be probed before the next use.
+    Stale,
+    Delay,
+    Probe,
+    /// Address resolution failed.
+    Failed,
+    /// Entry is valid, but no address resolution is done, e.g. for point-to-point links.
+    NoArp,
+    /// Static entry, never expired by the kernel.
+    Permanent,
+    /// State, unknown to this crate.
+    Other(u16),
+}
+
+impl NeighborState {
+    /// Returns `true` for entries, managed by the kernel, that can be flushed.
+    pub fn is_dynamic(self) -> bool {
+        !matches!(self, Self::Permanent | Self::NoArp | Self::None)
+    }
+}
+
+impl From<u16> for NeighborState {
+    fn from(state: u16) -> Self {
+        match state {
+            NUD_NONE => Self::None,
+            NUD_INCOMPLETE => Self::Incomplete,
+            NUD_REACHABLE => Self::Reachable,
+            NUD_STALE => Self::Stale,
+            NUD_DELAY => Self::Delay,
+            NUD_PROBE => Self::Probe,
+            NUD_FAILED => Self::Failed,
+            NUD_NOARP => Self::NoArp,
+            NUD_PERMANENT => Self::Permanent,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl From<NeighborState> for u16 {
+    fn from(state: NeighborState) -> Self {
+        match state {
+            NeighborState::None => NUD_NONE,
+            NeighborState::Incomplete => NUD_INCOMPLETE,
+            NeighborState::Reachable => NUD_REACHABLE,
+            NeighborState::Stale => NUD_STALE,
+            NeighborState::Delay => NUD_DELAY,
+            NeighborState::Probe => NUD_PROBE,
+            NeighborState::Failed => NUD_FAILED,
+            NeighborState::NoArp => NUD_NOARP,
+            NeighborState::Permanent => NUD_PERMANENT,
+            NeighborState::Other(other) => other,
+        }
+    }
+}
+
+/// Entry of the ARP or NDP neighbor cache, as shown by `ip neigh`.
+///
+/// The same type is used to describe entries to add, replace or delete.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct Neighbor {
+    pub address: IpAddr,
+    /// Link-layer address. `None` for incomplete entries and for link layers with addresses, other
+    /// than 6 bytes long.
+    pub lladdr: Option<MacAddr6>,
+    pub interface: Interface,
+    pub state: NeighborState,
+    /// Neighbor is an IPv6 router.
+    pub router: bool,
+    /// Entry is a proxy entry: the host answers ARP or NDP requests for `address`, received on
+    /// `interface`. Proxy NDP also requires `proxy_ndp` to be enabled on the interface.
+    pub proxy: bool,
+}
+
+impl Neighbor {
+    /// Creates a permanent entry for `address` on `interface`.
+    pub fn new(address: IpAddr, interface: &Interface) -> Self {
+        Self {
+            address,
+            lladdr: None,
+            interface: interface.clone(),
+            state: NeighborState::Permanent,
+            router: false,
+            proxy: false,
+        }
+    }
+
+    pub fn lladdr(mut self, lladdr: MacAddr6) -> Self {
+        self.lladdr = Some(lladdr);
+        self
+    }
+
+    pub fn state(mut self, state: NeighborState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn router(mut self, router: bool) -> Self {
+        self.router = router;
+        self
+    }
+
+    pub fn proxy(mut self, proxy: bool) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub(crate) fn from_message(
+        message: NeighbourMessage,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Option<Self>, Error> {
+        let header = &message.header;
+        if IpFamily::from_af(header.family).is_none() {
+            return Ok(None);
+        }
+
+        let mut address = None;
+        let mut lladdr = None;
+        for nla in message.nlas {
+            match nla {
+                NeighbourNla::Destination(bytes) => address = parse_ip(&bytes),
+                NeighbourNla::LinkLocalAddress(bytes) => {
+                    lladdr = MacAddr6::try_from(&bytes[..]).ok()
+                }
+                _ => {}
+            }
+        }
+        // Entries without a destination have no address to report and are skipped
+        let Some(address) = address else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            address,
+            lladdr,
+            interface: Interface(InterfaceHandle {
+                index: header.ifindex,
+                netns: netns.cloned(),
+            }),
+            state: header.state.into(),
+            router: header.flags & NTF_ROUTER != 0,
+            proxy: header.flags & NTF_PROXY != 0,
+        }))
+    }
+
+    pub(crate) fn to_message(&self) -> NeighbourMessage {
+        let mut message = NeighbourMessage::default();
+        let header = &mut message.header;
+
+        header.family = IpFamily::from(self.address).af();
+        header.ifindex = self.interface.0.index;
+        header.state = self.state.into();
+        if self.router {
+            header.flags |= NTF_ROUTER;
+        }
+        if self.proxy {
+            header.flags |= NTF_PROXY;
+        }
+
+        message
+            .nlas
+            .push(NeighbourNla::Destination(ip_to_vec(self.address)));
+        if let Some(lladdr) = self.lladdr {
+            message
+                .nlas
+                .push(NeighbourNla::LinkLocalAddress(lladdr.as_slice().to_vec()));
+        }
+
+        message
+    }
+}
+
+/// Criteria for [`list_neighbors`] and [`flush_neighbors`]. Every unset field matches all entries.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+pub struct NeighborFilter {
+    pub family: Option<IpFamily>,
+    /// Index of the interface.
+    pub interface: Option<u32>,
+    /// Proxy entries are listed instead of neighbor cache entries.
+    pub proxy: bool,
+}
+
+impl NeighborFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn family(mut self, family: IpFamily) -> Self {
+        self.family = Some(family);
+        self
+    }
+
+    pub fn interface(mut self, interface: &Interface) -> Self {
+        self.interface = Some(interface.0.index);
+        self
+    }
+
+    pub fn proxy(mut self, proxy: bool) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Returns the dump request for this filter.
+    pub(crate) fn to_message(&self) -> RtnlMessage {
+        let mut message = NeighbourMessage::default();
+        if let Some(family) = self.family {
+            message.header.family = family.af();
+        }
+        // Proxy entries are kept in a separate table, dumped only on request
+        if self.proxy {
+            message.header.flags = NTF_PROXY;
+        }
+        RtnlMessage::GetNeighbour(message)
+    }
+
+    /// Returns entries from the dump, matching this filter.
+    pub(crate) fn collect(
+        &self,
+        responses: Vec<RtnlMessage>,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Vec<Neighbor>, Error> {
+        let mut neighbors = vec![];
+        for response in responses {
+            let RtnlMessage::NewNeighbour(message) = response else {
+                continue;
+            };
+            if let Some(neighbor) = Neighbor::from_message(message, netns)? {
+                if self.matches(&neighbor) {
+                    neighbors.push(neighbor);
+                }
+            }
+        }
+        Ok(neighbors)
+    }
+
+    fn matches(&self, neighbor: &Neighbor) -> bool {
+        let family_matches = self
+            .family
+            .map_or(true, |family| IpFamily::from(neighbor.address) == family);
+        let interface_matches = self
+            .interface
+            .map_or(true, |index| neighbor.interface.0.index == index);
+
+        family_matches && interface_matches
+    }
+
+    /// Returns `true`, if a matching entry is deleted by a flush. Proxy entries have no dynamic
+    /// state and are always deleted.
+    fn flushes(&self, neighbor: &Neighbor) -> bool {
+        self.proxy || neighbor.state.is_dynamic()
+    }
+}
+
+/// Returns ARP and NDP neighbor cache entries, matching given filter.
+pub fn list_neighbors(filter: &NeighborFilter) -> Result<Vec<Neighbor>, Error> {
+    list_neighbors_in(None, filter)
+}
+
+/// Adds a neighbor entry. Fails, if an entry for the same address and interface already exists.
+pub fn add_neighbor(neighbor: &Neighbor) -> Result<(), Error> {
+    modify_neighbor_in(None, neighbor, NeighborOperation::Add)
+}
+
+/// Adds a neighbor entry or replaces the existing one for the same address and interface.
+pub fn replace_neighbor(neighbor: &Neighbor) -> Result<(), Error> {
+    modify_neighbor_in(None, neighbor, NeighborOperation::Replace)
+}
+
+/// Deletes the entry for the address and interface of `neighbor`.
+pub fn delete_neighbor(neighbor: &Neighbor) -> Result<(), Error> {
+    modify_neighbor_in(None, neighbor, NeighborOperation::Delete)
+}
+
+/// Deletes dynamic entries, matching given filter, like `ip neigh flush` does. Permanent entries
+/// are kept. Proxy entries have no dynamic state, so with [`NeighborFilter::proxy`] all matching
+/// ones are deleted.
+///
+/// Returns the number of deleted entries.
+pub fn flush_neighbors(filter: &NeighborFilter) -> Result<usize, Error> {
+    flush_neighbors_in(None, filter)
+}
+
+pub(crate) fn list_neighbors_in(
+    netns: Option<&NetnsHandle>,
+    filter: &NeighborFilter,
+) -> Result<Vec<Neighbor>, Error> {
+    let socket = netlink::socket(netns)?;
+    let responses = netlink::request(&socket, filter.to_message(), NLM_F_DUMP)?;
+    filter.collect(responses, netns)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NeighborOperation {
+    Add,
+    Replace,
+    Delete,
+}
+
+impl NeighborOperation {
+    /// Returns the request and its flags, performing this operation.
+    pub(crate) fn request(self, neighbor: &Neighbor) -> (RtnlMessage, u16) {
+        match self {
+            NeighborOperation::Add => (
+                RtnlMessage::NewNeighbour(neighbor.to_message()),
+                NLM_F_CREATE | NLM_F_EXCL,
+            ),
+            NeighborOperation::Replace => (
+                RtnlMessage::NewNeighbour(neighbor.to_message()),
+                NLM_F_CREATE | NLM_F_REPLACE,
+            ),
+            NeighborOperation::Delete => (RtnlMessage::DelNeighbour(neighbor.to_message()), 0),
+        }
+    }
+}
+
+pub(crate) fn modify_neighbor_in(
+    netns: Option<&NetnsHandle>,
+    neighbor: &Neighbor,
+    operation: NeighborOperation,
+) -> Result<(), Error> {
+    let socket = netlink::socket(netns)?;
+    modify_neighbor(&socket, neighbor, operation)
+}
+
+fn modify_neighbor(
+    socket: &Socket,
+    neighbor: &Neighbor,
+    operation: NeighborOperation,
+) -> Result<(), Error> {
+    let (message, flags) = operation.request(neighbor);
+    netlink::request(socket, message, flags)?;
+    Ok(())
+}
+
+pub(crate) fn flush_neighbors_in(
+    netns: Option<&NetnsHandle>,
+    filter: &NeighborFilter,
+) -> Result<usize, Error> {
+    let socket = netlink::socket(netns)?;
+    let mut flushed = 0;
+
+    for neighbor in list_neighbors_in(netns, filter)? {
+        if !filter.flushes(&neighbor) {
+            continue;
+        }
+        match modify_neighbor(&socket, &neighbor, NeighborOperation::Delete) {
+            Ok(()) => flushed += 1,
+            // Entry may have been garbage collected since the dump
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(flushed)
+}
+
+impl NetnsHandle {
+    /// Returns neighbor cache entries inside this namespace, matching given filter.
+    pub fn list_neighbors(&self, filter: &NeighborFilter) -> Result<Vec<Neighbor>, Error> {
+        list_neighbors_in(Some(self), filter)
+    }
+
+    pub fn add_neighbor(&self, neighbor: &Neighbor) -> Result<(), Error> {
+        modify_neighbor_in(Some(self), neighbor, NeighborOperation::Add)
+    }
+
+    pub fn replace_neighbor(&self, neighbor: &Neighbor) -> Result<(), Error> {
+        modify_neighbor_in(Some(self), neighbor, NeighborOperation::Replace)
+    }
+
+    pub fn delete_neighbor(&self, neighbor: &Neighbor) -> Result<(), Error> {
+        modify_neighbor_in(Some(self), neighbor, NeighborOperation::Delete)
+    }
+
+    /// Deletes dynamic entries inside this namespace, matching given filter.
+    pub fn flush_neighbors(&self, filter: &NeighborFilter) -> Result<usize, Error> {
+        flush_neighbors_in(Some(self), filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netlink_packet_route::LinkMessage;
+
+    fn link(index: u32) -> Interface {
+        Interface(InterfaceHandle { index, netns: None })
+    }
+
+    fn lladdr() -> MacAddr6 {
+        MacAddr6::new([2, 0, 0, 0, 0, 1])
+    }
+
+    /// Parses an entry, as if it was sent by the kernel.
+    fn parse(message: NeighbourMessage) -> Option<Neighbor> {
+        let RtnlMessage::NewNeighbour(message) =
+            netlink::reparse(RtnlMessage::NewNeighbour(message))
+        else {
+            panic!("expected a neighbor");
+        };
+        Neighbor::from_message(message, None).unwrap()
+    }
+
+    #[test]
+    fn neighbor_round_trip() {
+        let neighbor = Neighbor::new("fe80::1".parse().unwrap(), &link(3))
+            .lladdr(lladdr())
+            .state(NeighborState::Reachable)
+            .router(true);
+        let message = neighbor.to_message();
+        assert_eq!(message.header.family, libc::AF_INET6 as u8);
+        assert_eq!(message.header.ifindex, 3);
+        assert_eq!(message.header.state, NUD_REACHABLE);
+        assert_eq!(message.header.flags, NTF_ROUTER);
+
+        let parsed = parse(message).unwrap();
+        assert_eq!(parsed.address, neighbor.address);
+        assert_eq!(parsed.lladdr, Some(lladdr()));
+        assert_eq!(parsed.interface.0.index, 3);
+        assert_eq!(parsed.state, NeighborState::Reachable);
+        assert!(parsed.router);
+        assert!(!parsed.proxy);
+
+        // Entries without a link-layer address, e.g. incomplete ones
+        let neighbor =
+            Neighbor::new("10.0.0.1".parse().unwrap(), &link(2)).state(NeighborState::Incomplete);
+        let parsed = parse(neighbor.to_message()).unwrap();
+        assert_eq!(parsed.lladdr, None);
+        assert_eq!(parsed.state, NeighborState::Incomplete);
+    }
+
+    #[test]
+    fn neighbor_states() {
+        for state in [
+            NeighborState::None,
+            NeighborState::Incomplete,
+            NeighborState::Reachable,
+            NeighborState::Stale,
+            NeighborState::Delay,
+            NeighborState::Probe,
+            NeighborState::Failed,
+            NeighborState::NoArp,
+            NeighborState::Permanent,
+            NeighborState::Other(0x100),
+        ] {
+            assert_eq!(NeighborState::from(u16::from(state)), state);
+        }
+    }
+
+    #[test]
+    fn unusual_entries() {
+        // Link-layer addresses, other than 6 bytes long, are not reported
+        let mut message = Neighbor::new("10.0.0.1".parse().unwrap(), &link(2)).to_message();
+        message
+            .nlas
+            .push(NeighbourNla::LinkLocalAddress(vec![10, 0, 0, 2]));
+        assert_eq!(parse(message).unwrap().lladdr, None);
+
+        // Entries without a destination are skipped
+        let mut message = Neighbor::new("10.0.0.1".parse().unwrap(), &link(2)).to_message();
+        message.nlas.clear();
+        assert!(parse(message).is_none());
+
+        // Bridge FDB entries share the message type
+        let mut message = Neighbor::new("10.0.0.1".parse().unwrap(), &link(2)).to_message();
+        message.header.family = libc::AF_BRIDGE as u8;
+        assert!(parse(message).is_none());
+    }
+
+    #[test]
+    fn filter_collect() {
+        let responses = || {
+            let entry = |address: &str, index| {
+                let neighbor = Neighbor::new(address.parse().unwrap(), &link(index));
+                RtnlMessage::NewNeighbour(neighbor.to_message())
+            };
+            let mut no_destination = NeighbourMessage::default();
+            no_destination.header.family = libc::AF_INET as u8;
+            vec![
+                entry("10.0.0.1", 2),
+                entry("fe80::1", 2),
+                entry("10.0.0.2", 3),
+                RtnlMessage::NewNeighbour(no_destination),
+                RtnlMessage::NewLink(LinkMessage::default()),
+            ]
+        };
+        let collect = |filter: NeighborFilter| -> Vec<IpAddr> {
+            filter
+                .collect(responses(), None)
+                .unwrap()
+                .into_iter()
+                .map(|neighbor| neighbor.address)
+                .collect()
+        };
+        let address = |address: &str| address.parse::<IpAddr>().unwrap();
+
+        assert_eq!(
+            collect(NeighborFilter::new()),
+            [address("10.0.0.1"), address("fe80::1"), address("10.0.0.2")]
+        );
+        assert_eq!(
+            collect(NeighborFilter::new().family(IpFamily::V6)),
+            [address("fe80::1")]
+        );
+        assert_eq!(
+            collect(NeighborFilter::new().interface(&link(3))),
+            [address("10.0.0.2")]
+        );
+    }
+
+    #[test]
+    fn flush_filter() {
+        let neighbor = |state| Neighbor::new("10.0.0.1".parse().unwrap(), &link(2)).state(state);
+        let filter = NeighborFilter::new();
+        for state in [
+            NeighborState::Reachable,
+            NeighborState::Stale,
+            NeighborState::Failed,
+            NeighborState::Incomplete,
+        ] {
+            assert!(filter.flushes(&neighbor(state)));
+        }
+        for state in [
+            NeighborState::Permanent,
+            NeighborState::NoArp,
+            NeighborState::None,
+        ] {
+            assert!(!filter.flushes(&neighbor(state)));
+        }
+
+        // Proxy entries are reported with NUD_NONE, and are flushed regardless
+        let filter = NeighborFilter::new().proxy(true);
+        for state in [
+            NeighborState::None,
+            NeighborState::Permanent,
+            NeighborState::Reachable,
+        ] {
+            assert!(filter.flushes(&neighbor(state).proxy(true)));
+        }
+    }
+
+    #[test]
+    fn proxy_dump_request() {
+        let RtnlMessage::GetNeighbour(message) = NeighborFilter::new().proxy(true).to_message()
+        else {
+            panic!("expected a neighbor dump");
+        };
+        assert_eq!(message.header.flags, NTF_PROXY);
+
+        let RtnlMessage::GetNeighbour(message) = NeighborFilter::new().to_message() else {
+            panic!("expected a neighbor dump");
+        };
+        assert_eq!(message.header.flags, 0);
+    }
+
+    #[test]
+    fn proxy_entries() {
+        let neighbor = Neighbor::new("10.0.0.5".parse().unwrap(), &link(2)).proxy(true);
+        let (message, _) = NeighborOperation::Add.request(&neighbor);
+        let RtnlMessage::NewNeighbour(message) = message else {
+            panic!("expected a new neighbor");
+        };
+        assert_eq!(message.header.flags, NTF_PROXY);
+
+        // The kernel dumps proxy entries with NUD_NONE and without a link-layer address
+        let mut message = message;
+        message.header.state = NUD_NONE;
+        let parsed = parse(message).unwrap();
+        assert!(parsed.proxy);
+        assert!(!parsed.router);
+        assert_eq!(parsed.state, NeighborState::None);
+        assert_eq!(parsed.address, neighbor.address);
+
+        let (message, _) = NeighborOperation::Delete.request(&neighbor);
+        let RtnlMessage::DelNeighbour(message) = message else {
+            panic!("expected a neighbor deletion");
+        };
+        assert_eq!(message.header.flags, NTF_PROXY);
+    }
+}
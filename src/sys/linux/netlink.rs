@@ -0,0 +1,405 @@
+use super::NetnsHandle;
+use crate::{Error, ExtendedAck};
+use libc::{c_int, c_void, socklen_t, NETLINK_EXT_ACK, SOL_NETLINK};
+use log::debug;
+use netlink_packet_core::{NetlinkDeserializable, NetlinkSerializable};
+use netlink_packet_route::nlas::{DefaultNla, NlaBuffer, NlasIterator};
+use netlink_packet_route::traits::Parseable;
+use netlink_packet_route::{
+    DecodeError, ErrorMessage, NetlinkHeader, NetlinkMessage, NetlinkPayload, NLM_F_ACK,
+    NLM_F_REQUEST,
+};
+use netlink_sys::constants::NETLINK_ROUTE;
+use netlink_sys::{Socket, SocketAddr};
+use std::fmt::Debug;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static SEQUENCE_NUMBER: AtomicU32 = AtomicU32::new(1);
+
+const NLMSGERR_ATTR_MSG: u16 = 1;
+const NLMSGERR_ATTR_OFFS: u16 = 2;
+
+/// Opens rtnetlink socket in the given network namespace, or in the namespace of the calling
+/// thread, if `netns` is `None`.
+pub(crate) fn socket(netns: Option<&NetnsHandle>) -> Result<Socket, Error> {
+    open(netns, NETLINK_ROUTE)
+}
+
+/// Opens netlink socket of given protocol, like [`socket`] does.
+pub(crate) fn open(netns: Option<&NetnsHandle>, protocol: isize) -> Result<Socket, Error> {
+    let socket = match netns {
+        Some(netns) => netns.socket(protocol)?,
+        None => Socket::new(protocol)?,
+    };
+    connect(socket)
+}
+
+/// Binds a freshly created socket and connects it to the kernel.
+pub(crate) fn connect(mut socket: Socket) -> Result<Socket, Error> {
+    socket.bind_auto()?;
+    socket.connect(&SocketAddr::new(0, 0))?;
+    enable_extended_ack(&socket);
+    Ok(socket)
+}
+
+/// Asks the kernel to explain failures, see [`error`]. Kernels before 4.12 don't support it,
+/// which only results in less detailed errors.
+fn enable_extended_ack(socket: &Socket) {
+    let enable: c_int = 1;
+    unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            SOL_NETLINK,
+            NETLINK_EXT_ACK,
+            &enable as *const c_int as *const c_void,
+            mem::size_of::<c_int>() as socklen_t,
+        )
+    };
+}
+
+/// Sends a request and collects all messages, sent in response, until the request is acknowledged
+/// or the dump is done.
+pub(crate) fn request<T>(socket: &Socket, message: T, flags: u16) -> Result<Vec<T>, Error>
+where
+    T: NetlinkSerializable + NetlinkDeserializable + Debug,
+{
+    let (buf, sequence_number) = encode_request(message, flags);
+    socket.send(&buf, 0)?;
+
+    let mut responses = vec![];
+    loop {
+        let (buf, _) = socket.recv_from_full()?;
+        if decode_responses(&buf, sequence_number, &mut responses)? {
+            return Ok(responses);
+        }
+    }
+}
+
+/// Serializes a request with a new sequence number. Returns the message and its sequence number.
+pub(crate) fn encode_request<T>(message: T, flags: u16) -> (Vec<u8>, u32)
+where
+    T: NetlinkSerializable + Debug,
+{
+    let sequence_number = SEQUENCE_NUMBER.fetch_add(1, Ordering::Relaxed);
+    let mut buf = vec![];
+    encode_message(&mut buf, message, flags, sequence_number);
+    (buf, sequence_number)
+}
+
+/// Appends a request with given sequence number to `buf`.
+pub(crate) fn encode_message<T>(buf: &mut Vec<u8>, message: T, flags: u16, sequence_number: u32)
+where
+    T: NetlinkSerializable + Debug,
+{
+    let mut req = NetlinkMessage {
+        header: NetlinkHeader {
+            flags: NLM_F_REQUEST | NLM_F_ACK | flags,
+            sequence_number,
+            ..Default::default()
+        },
+        payload: NetlinkPayload::InnerMessage(message),
+    };
+
+    req.finalize();
+
+    let offset = buf.len();
+    let length = req.header.length as usize;
+    buf.resize(offset + align(length), 0);
+    req.serialize(&mut buf[offset..offset + length]);
+
+    debug!(">>> {:?}", req);
+}
+
+/// Serializes a message and parses it back, like a message, received from the kernel.
+#[cfg(test)]
+pub(crate) fn reparse<T>(message: T) -> T
+where
+    T: NetlinkSerializable + NetlinkDeserializable + Debug,
+{
+    let mut buf = vec![];
+    encode_message(&mut buf, message, 0, 1);
+    match NetlinkMessage::<T>::deserialize(&buf).unwrap().payload {
+        NetlinkPayload::InnerMessage(message) => message,
+        payload => panic!("unexpected payload: {payload:?}"),
+    }
+}
+
+/// Collects responses to the request with given sequence number from a received datagram.
+///
+/// Returns `true`, when the request is acknowledged or the dump is done.
+pub(crate) fn decode_responses<T>(
+    buf: &[u8],
+    sequence_number: u32,
+    responses: &mut Vec<T>,
+) -> Result<bool, Error>
+where
+    T: NetlinkDeserializable + Debug,
+{
+    let mut offset = 0;
+    while offset < buf.len() {
+        let response = NetlinkMessage::<T>::deserialize(&buf[offset..])?;
+        debug!("<<< {:?}", response);
+
+        if response.header.length == 0 {
+            break;
+        }
+        offset += align(response.header.length as _);
+
+        if response.header.sequence_number != sequence_number {
+            continue;
+        }
+
+        match response.payload {
+            NetlinkPayload::Done | NetlinkPayload::Ack(_) => return Ok(true),
+            NetlinkPayload::Error(e) => return Err(error(e)),
+            NetlinkPayload::InnerMessage(message) => responses.push(message),
+            _ => {}
+        }
+    }
+    Ok(false)
+}
+
+/// Collects notifications from a received datagram. Their sequence numbers are chosen by the
+/// kernel, so every message is taken, except for acknowledgements and errors.
+pub(crate) fn decode_notifications<T>(buf: &[u8], notifications: &mut Vec<T>) -> Result<(), Error>
+where
+    T: NetlinkDeserializable + Debug,
+{
+    let mut offset = 0;
+    while offset < buf.len() {
+        let notification = NetlinkMessage::<T>::deserialize(&buf[offset..])?;
+        debug!("<<< {:?}", notification);
+
+        if notification.header.length == 0 {
+            break;
+        }
+        offset += align(notification.header.length as _);
+
+        if let NetlinkPayload::InnerMessage(message) = notification.payload {
+            notifications.push(message);
+        }
+    }
+    Ok(())
+}
+
+/// Converts an error response by its code, e.g. `ENODEV` to [`Error::InterfaceNotFound`], and
+/// attaches the explanation from the extended acknowledgement.
+///
+/// The response echoes the request, followed by attributes: `NLMSGERR_ATTR_MSG` with the reason
+/// and `NLMSGERR_ATTR_OFFS` with the offset of the rejected attribute within the request. The
+/// explanation is wrapped into [`Error::Io`] as an [`ExtendedAck`]. Other variants can't carry
+/// it, so it's only logged.
+pub(crate) fn error(response: ErrorMessage) -> Error {
+    let payload = &response.header;
+    let request_length = match payload.get(..4) {
+        Some(bytes) => u32::from_ne_bytes(bytes.try_into().unwrap()) as usize,
+        None => 0,
+    };
+    if payload.len() > request_length {
+        let (mut message, mut attribute) = (None, None);
+        for nla in NlasIterator::new(&payload[align(request_length)..]).flatten() {
+            match nla.kind() {
+                NLMSGERR_ATTR_MSG => {
+                    let value = nla.value().split(|byte| *byte == 0).next();
+                    message = value.map(|value| String::from_utf8_lossy(value).into_owned());
+                }
+                NLMSGERR_ATTR_OFFS => {
+                    let offset = nla.value().try_into().map(u32::from_ne_bytes);
+                    attribute = offset
+                        .ok()
+                        .and_then(|offset| payload.get(offset as usize..request_length))
+                        .and_then(|bytes| NlaBuffer::new_checked(bytes).ok())
+                        .map(|nla| nla.kind());
+                }
+                _ => {}
+            }
+        }
+        if message.is_some() || attribute.is_some() {
+            return match Error::from(response) {
+                Error::Io(error) => Error::Io(
+                    ExtendedAck {
+                        error,
+                        message,
+                        attribute,
+                    }
+                    .into(),
+                ),
+                e => {
+                    debug!("{}: {:?} (attribute {:?})", e, message, attribute);
+                    e
+                }
+            };
+        }
+    }
+    response.into()
+}
+
+/// Message of a type, not supported by netlink-packet-route: a fixed-size header of `N` bytes,
+/// followed by attributes in wire format.
+#[derive(Debug, Clone)]
+pub(crate) struct RawMessage<const N: usize> {
+    pub message_type: u16,
+    pub header: [u8; N],
+    pub attributes: Vec<u8>,
+}
+
+impl<const N: usize> RawMessage<N> {
+    pub fn new(message_type: u16) -> Self {
+        Self {
+            message_type,
+            header: [0; N],
+            attributes: vec![],
+        }
+    }
+}
+
+impl<const N: usize> NetlinkSerializable for RawMessage<N> {
+    fn message_type(&self) -> u16 {
+        self.message_type
+    }
+
+    fn buffer_len(&self) -> usize {
+        N + self.attributes.len()
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) {
+        buffer[..N].copy_from_slice(&self.header);
+        buffer[N..].copy_from_slice(&self.attributes);
+    }
+}
+
+impl<const N: usize> NetlinkDeserializable for RawMessage<N> {
+    type Error = DecodeError;
+
+    fn deserialize(header: &NetlinkHeader, payload: &[u8]) -> Result<Self, Self::Error> {
+        if payload.len() < N {
+            return Err(DecodeError::from("message is shorter than its header"));
+        }
+        Ok(Self {
+            message_type: header.message_type,
+            header: payload[..N].try_into().unwrap(),
+            attributes: payload[N..].to_vec(),
+        })
+    }
+}
+
+/// Appends a netlink attribute in wire format, for messages, not supported by
+/// netlink-packet-route.
+pub(crate) fn put_attribute(buffer: &mut Vec<u8>, kind: u16, value: &[u8]) {
+    let length = 4 + value.len();
+    buffer.extend_from_slice(&(length as u16).to_ne_bytes());
+    buffer.extend_from_slice(&kind.to_ne_bytes());
+    buffer.extend_from_slice(value);
+    buffer.resize(buffer.len() + align(length) - length, 0);
+}
+
+/// Builds an attribute of a kind, not supported by netlink-packet-route.
+///
+/// `DefaultNla` has no public constructor, so the attribute is parsed from its wire format.
+pub(crate) fn default_nla(kind: u16, value: &[u8]) -> DefaultNla {
+    let mut bytes = vec![];
+    put_attribute(&mut bytes, kind, value);
+    DefaultNla::parse(&NlaBuffer::new(&bytes)).expect("attribute is well-formed")
+}
+
+pub(crate) fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+pub(crate) fn parse_u16(bytes: &[u8]) -> Result<u16, Error> {
+    Ok(u16::from_ne_bytes(
+        bytes.try_into().map_err(|_| Error::UnexpectedMetadata)?,
+    ))
+}
+
+pub(crate) fn parse_u32(bytes: &[u8]) -> Result<u32, Error> {
+    Ok(u32::from_ne_bytes(
+        bytes.try_into().map_err(|_| Error::UnexpectedMetadata)?,
+    ))
+}
+
+/// Parses a NUL-terminated string attribute.
+pub(crate) fn parse_string(bytes: &[u8]) -> Result<String, Error> {
+    let bytes = bytes.split(|byte| *byte == 0).next().unwrap_or_default();
+    String::from_utf8(bytes.to_vec()).map_err(|_| Error::UnexpectedMetadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    /// Builds an error response to a request with a single attribute of type 3, followed by given
+    /// extended acknowledgement attributes.
+    fn error_response(code: i32, ack: &[(u16, &[u8])]) -> ErrorMessage {
+        let mut request = vec![];
+        put_attribute(&mut request, 3, &7u32.to_ne_bytes());
+
+        let mut header = vec![];
+        header.extend_from_slice(&(16 + request.len() as u32).to_ne_bytes());
+        header.resize(16, 0);
+        header.extend_from_slice(&request);
+        for (kind, value) in ack {
+            put_attribute(&mut header, *kind, value);
+        }
+        ErrorMessage {
+            code: -code,
+            header,
+        }
+    }
+
+    #[test]
+    fn extended_ack() {
+        let response = error_response(
+            libc::EINVAL,
+            &[
+                (NLMSGERR_ATTR_MSG, b"Invalid prefix\0"),
+                (NLMSGERR_ATTR_OFFS, &16u32.to_ne_bytes()),
+            ],
+        );
+        let e = error(response);
+        assert_eq!(e.raw_os_error(), Some(libc::EINVAL));
+        assert!(e.to_string().ends_with(": Invalid prefix (attribute 3)"));
+
+        let Error::Io(e) = e else {
+            panic!("expected an I/O error");
+        };
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let ack = e.get_ref().unwrap().downcast_ref::<ExtendedAck>().unwrap();
+        assert_eq!(ack.message.as_deref(), Some("Invalid prefix"));
+        assert_eq!(ack.attribute, Some(3));
+    }
+
+    #[test]
+    fn extended_ack_for_missing_device() {
+        // The variant depends on the code only
+        for ack in [&[(NLMSGERR_ATTR_MSG, &b"Unknown device\0"[..])][..], &[]] {
+            let response = error_response(libc::ENODEV, ack);
+            assert!(matches!(error(response), Error::InterfaceNotFound));
+        }
+    }
+
+    #[test]
+    fn extended_ack_out_of_bounds() {
+        // Offset past the request and a truncated offset don't name an attribute
+        for offset in [&64u32.to_ne_bytes()[..], &[16, 0]] {
+            let response = error_response(libc::EINVAL, &[(NLMSGERR_ATTR_OFFS, offset)]);
+            assert!(matches!(error(response), Error::Io(_)));
+        }
+    }
+
+    #[test]
+    fn truncated_response() {
+        let mut response = error_response(libc::EINVAL, &[(NLMSGERR_ATTR_MSG, b"Invalid\0")]);
+        response.header.truncate(20);
+        assert!(matches!(error(response), Error::Io(_)));
+
+        let response = ErrorMessage {
+            code: -libc::EINVAL,
+            header: vec![1, 2],
+        };
+        assert!(matches!(error(response), Error::Io(_)));
+    }
+}
@@ -0,0 +1,150 @@
+use super::handle::list_interfaces_in;
+use crate::sys::InterfaceHandle;
+use crate::{Error, Interface};
+use ipnet::IpNet;
+use netlink_sys::Socket;
+use nix::sched::{setns, CloneFlags};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+/// Handle to a network namespace.
+///
+/// Netlink sockets are bound to the namespace they were created in, so every operation, performed
+/// through this handle, is executed inside the target namespace without switching the namespace of
+/// the calling thread. [`Interface`] values, obtained through this handle, remember their namespace.
+///
+/// Entering a namespace is done by a helper thread, that is spawned for every request and exits
+/// right after opening its socket. This costs an OS thread per call, so for many requests in a row
+/// prefer [`connection`](Self::connection), which keeps a single socket.
+#[derive(Clone)]
+pub struct NetnsHandle(Arc<File>);
+
+impl NetnsHandle {
+    /// Wraps an already opened namespace file descriptor.
+    pub fn from_file(file: File) -> Self {
+        Self(Arc::new(file))
+    }
+
+    /// Opens namespace by path, like `/proc/self/ns/net`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self::from_file(File::open(path)?))
+    }
+
+    /// Opens named namespace, created by `ip netns add`.
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        if name.is_empty() || name.contains('/') {
+            return Err(Error::InvalidParameter);
+        }
+        Self::from_path(Path::new("/run/netns").join(name))
+    }
+
+    /// Opens namespace of the process with given pid.
+    pub fn from_pid(pid: u32) -> Result<Self, Error> {
+        Self::from_path(format!("/proc/{pid}/ns/net"))
+    }
+
+    /// Opens namespace of the calling thread.
+    pub fn current() -> Result<Self, Error> {
+        Self::from_path("/proc/thread-self/ns/net")
+    }
+
+    pub fn list_interfaces(&self) -> Result<Vec<Interface>, Error> {
+        list_interfaces_in(Some(self))
+    }
+
+    pub fn list_addresses(&self) -> Result<Vec<IpNet>, Error> {
+        Ok(crate::collect_addresses(&self.list_interfaces()?))
+    }
+
+    /// Returns [`Interface`] inside this namespace from given index or Error if not found.
+    pub fn try_interface_from_index(&self, index: u32) -> Result<Interface, Error> {
+        let interface = Interface(InterfaceHandle {
+            index,
+            netns: Some(self.clone()),
+        });
+        interface.0.link()?;
+        Ok(interface)
+    }
+
+    /// Returns [`Interface`] inside this namespace from given name or Error if not found.
+    pub fn try_interface_from_name(&self, name: &str) -> Result<Interface, Error> {
+        InterfaceHandle::try_from_name_in(name, Some(self))
+    }
+
+    /// Creates a socket inside this namespace.
+    ///
+    /// The namespace is entered by a short-lived helper thread, so the namespace of the calling
+    /// thread stays untouched. The thread is spawned and joined on every call.
+    pub(crate) fn socket(&self, protocol: isize) -> io::Result<Socket> {
+        self.run_io(move || Socket::new(protocol))
+    }
+
+    /// Runs `f` on a short-lived helper thread, that entered this namespace.
+    ///
+    /// Used for namespace-dependent resources, other than netlink sockets, e.g. `/proc/sys/net` and
+    /// ethtool ioctls. Like [`socket`](Self::socket), it spawns a thread on every call.
+    pub(crate) fn run<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> io::Result<T> + Send + 'static,
+    {
+        Ok(self.run_io(f)?)
+    }
+
+    /// Like [`run`](Self::run), but fails with `io::Error`, which can be sent between threads.
+    fn run_io<T, F>(&self, f: F) -> io::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> io::Result<T> + Send + 'static,
+    {
+        let netns = self.clone();
+        thread::spawn(move || -> io::Result<T> {
+            setns(netns.as_raw_fd(), CloneFlags::CLONE_NEWNET)?;
+            f()
+        })
+        .join()
+        .map_err(|_| io::Error::from(io::ErrorKind::Other))?
+    }
+}
+
+/// Target network namespace for [`InterfaceExt::move_to_netns`](super::InterfaceExt::move_to_netns).
+#[derive(Debug, Clone)]
+pub enum NetnsTarget {
+    /// Namespace, referred by file descriptor (`IFLA_NET_NS_FD`).
+    Handle(NetnsHandle),
+    /// Namespace of the process with given pid (`IFLA_NET_NS_PID`).
+    Pid(u32),
+    /// Named namespace, created by `ip netns add`.
+    Name(String),
+}
+
+impl From<NetnsHandle> for NetnsTarget {
+    fn from(netns: NetnsHandle) -> Self {
+        Self::Handle(netns)
+    }
+}
+
+impl From<&NetnsHandle> for NetnsTarget {
+    fn from(netns: &NetnsHandle) -> Self {
+        Self::Handle(netns.clone())
+    }
+}
+
+impl AsRawFd for NetnsHandle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for NetnsHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("NetnsHandle")
+            .field(&self.as_raw_fd())
+            .finish()
+    }
+}
@@ -0,0 +1,643 @@
+use super::handle::parse_ip;
+use super::netlink::{self, parse_u16, parse_u32, RawMessage};
+use super::route::ip_to_vec;
+use super::{IpFamily, NetnsHandle, RouteProtocol};
+use crate::sys::InterfaceHandle;
+use crate::{Error, Interface};
+use netlink_packet_route::nlas::{NlasIterator, NLA_F_NESTED};
+use netlink_packet_route::{NLM_F_CREATE, NLM_F_DUMP, NLM_F_EXCL, NLM_F_REPLACE, RTNH_F_ONLINK};
+use std::net::IpAddr;
+use std::time::Duration;
+
+// Nexthop objects are not supported by netlink-packet-route, see include/uapi/linux/nexthop.h
+const RTM_NEWNEXTHOP: u16 = 104;
+const RTM_DELNEXTHOP: u16 = 105;
+const RTM_GETNEXTHOP: u16 = 106;
+
+const NHA_ID: u16 = 1;
+const NHA_GROUP: u16 = 2;
+const NHA_GROUP_TYPE: u16 = 3;
+const NHA_BLACKHOLE: u16 = 4;
+const NHA_OIF: u16 = 5;
+const NHA_GATEWAY: u16 = 6;
+const NHA_RES_GROUP: u16 = 12;
+
+const NHA_RES_GROUP_BUCKETS: u16 = 1;
+const NHA_RES_GROUP_IDLE_TIMER: u16 = 2;
+const NHA_RES_GROUP_UNBALANCED_TIMER: u16 = 3;
+
+const NEXTHOP_GRP_TYPE_MPATH: u16 = 0;
+const NEXTHOP_GRP_TYPE_RES: u16 = 1;
+
+/// Size of `struct nhmsg`.
+const NEXTHOP_HEADER_LEN: usize = 8;
+/// Size of `struct nexthop_grp`.
+const NEXTHOP_GROUP_ENTRY_LEN: usize = 8;
+/// Timers of resilient groups are reported in `clock_t` units.
+const USER_HZ: u64 = 100;
+
+/// Nexthop object (Linux 5.3 and later).
+///
+/// Routes refer to nexthop objects by id (see [`RouteSpec::nexthop_id`]), so many routes can be
+/// moved to another gateway by replacing a single object.
+///
+/// [`RouteSpec::nexthop_id`]: super::RouteSpec::nexthop_id
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct Nexthop {
+    /// Object id, must be non-zero.
+    pub id: u32,
+    /// Address family of the next hop, `None` for groups.
+    pub family: Option<IpFamily>,
+    pub kind: NexthopKind,
+    /// Gateway is directly reachable through the interface, even if no prefix covers it.
+    pub onlink: bool,
+    /// Origin of the object. Unset protocol is reported as static when the object is created.
+    pub protocol: Option<RouteProtocol>,
+}
+
+/// What a [`Nexthop`] forwards packets to.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub enum NexthopKind {
+    /// Gateway, device or both.
+    Single {
+        gateway: Option<IpAddr>,
+        interface: Option<Interface>,
+    },
+    /// Packets are silently dropped.
+    Blackhole,
+    /// Traffic is balanced over other nexthop objects.
+    Group(NexthopGroup),
+}
+
+/// Members of a nexthop group, and the way traffic is balanced over them.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NexthopGroup {
+    pub members: Vec<NexthopGroupMember>,
+    /// Parameters of a resilient group, `None` for hash-threshold groups.
+    ///
+    /// Resilient groups keep flows on their next hops, when members are added or removed.
+    pub resilient: Option<ResilientGroup>,
+}
+
+/// Member of a nexthop group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NexthopGroupMember {
+    /// Id of a single nexthop object.
+    pub id: u32,
+    /// Relative share of traffic, sent via this member, from 1 to 256.
+    pub weight: u16,
+}
+
+/// Parameters of a resilient nexthop group.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResilientGroup {
+    /// Number of hash buckets, distributed among members.
+    pub buckets: u16,
+    /// Time, after which an idle bucket may be migrated to another member.
+    pub idle_timer: Option<Duration>,
+    /// Time, after which buckets are migrated even if busy, when the group stays unbalanced.
+    pub unbalanced_timer: Option<Duration>,
+}
+
+impl NexthopGroupMember {
+    /// Creates a member with given weight, clamped to the range from 1 to 256.
+    pub fn new(id: u32, weight: u16) -> Self {
+        Self {
+            id,
+            weight: weight.clamp(1, 256),
+        }
+    }
+}
+
+impl ResilientGroup {
+    pub fn new(buckets: u16) -> Self {
+        Self {
+            buckets,
+            idle_timer: None,
+            unbalanced_timer: None,
+        }
+    }
+
+    pub fn idle_timer(mut self, timer: Duration) -> Self {
+        self.idle_timer = Some(timer);
+        self
+    }
+
+    pub fn unbalanced_timer(mut self, timer: Duration) -> Self {
+        self.unbalanced_timer = Some(timer);
+        self
+    }
+}
+
+impl Nexthop {
+    /// Creates a next hop via `gateway`, reachable through `interface`.
+    pub fn via(id: u32, gateway: IpAddr, interface: &Interface) -> Self {
+        Self::new(
+            id,
+            Some(gateway.into()),
+            NexthopKind::Single {
+                gateway: Some(gateway),
+                interface: Some(interface.clone()),
+            },
+        )
+    }
+
+    /// Creates a next hop, sending packets of given family directly to `interface`.
+    pub fn device(id: u32, family: IpFamily, interface: &Interface) -> Self {
+        Self::new(
+            id,
+            Some(family),
+            NexthopKind::Single {
+                gateway: None,
+                interface: Some(interface.clone()),
+            },
+        )
+    }
+
+    /// Creates a next hop, dropping packets of given family.
+    pub fn blackhole(id: u32, family: IpFamily) -> Self {
+        Self::new(id, Some(family), NexthopKind::Blackhole)
+    }
+
+    /// Creates a hash-threshold group of other nexthop objects.
+    pub fn group(id: u32, members: Vec<NexthopGroupMember>) -> Self {
+        Self::new(
+            id,
+            None,
+            NexthopKind::Group(NexthopGroup {
+                members,
+                resilient: None,
+            }),
+        )
+    }
+
+    /// Creates a resilient group of other nexthop objects.
+    pub fn resilient_group(
+        id: u32,
+        members: Vec<NexthopGroupMember>,
+        resilient: ResilientGroup,
+    ) -> Self {
+        Self::new(
+            id,
+            None,
+            NexthopKind::Group(NexthopGroup {
+                members,
+                resilient: Some(resilient),
+            }),
+        )
+    }
+
+    fn new(id: u32, family: Option<IpFamily>, kind: NexthopKind) -> Self {
+        Self {
+            id,
+            family,
+            kind,
+            onlink: false,
+            protocol: None,
+        }
+    }
+
+    pub fn onlink(mut self, onlink: bool) -> Self {
+        self.onlink = onlink;
+        self
+    }
+
+    pub fn protocol(mut self, protocol: RouteProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    fn from_message(
+        message: NexthopMessage,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Option<Self>, Error> {
+        let mut id = None;
+        let mut gateway = None;
+        let mut interface = None;
+        let mut blackhole = false;
+        let mut members = None;
+        let mut group_type = NEXTHOP_GRP_TYPE_MPATH;
+        let mut resilient = ResilientGroup::new(0);
+
+        for nla in NlasIterator::new(&message.attributes) {
+            let nla = nla?;
+            let value = nla.value();
+            match nla.kind() {
+                NHA_ID => id = Some(parse_u32(value)?),
+                NHA_GATEWAY => gateway = parse_ip(value),
+                NHA_OIF => {
+                    interface = Some(Interface(InterfaceHandle {
+                        index: parse_u32(value)?,
+                        netns: netns.cloned(),
+                    }))
+                }
+                NHA_BLACKHOLE => blackhole = true,
+                NHA_GROUP => members = Some(parse_group(value)?),
+                NHA_GROUP_TYPE => group_type = parse_u16(value)?,
+                NHA_RES_GROUP => {
+                    for nla in NlasIterator::new(value) {
+                        let nla = nla?;
+                        let value = nla.value();
+                        match nla.kind() {
+                            NHA_RES_GROUP_BUCKETS => resilient.buckets = parse_u16(value)?,
+                            NHA_RES_GROUP_IDLE_TIMER => {
+                                resilient.idle_timer = Some(clock_to_duration(parse_u32(value)?))
+                            }
+                            NHA_RES_GROUP_UNBALANCED_TIMER => {
+                                let timer = parse_u32(value)?;
+                                resilient.unbalanced_timer =
+                                    (timer != 0).then(|| clock_to_duration(timer));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        let kind = match members {
+            Some(members) => NexthopKind::Group(NexthopGroup {
+                members,
+                resilient: (group_type == NEXTHOP_GRP_TYPE_RES).then_some(resilient),
+            }),
+            None if blackhole => NexthopKind::Blackhole,
+            None => NexthopKind::Single { gateway, interface },
+        };
+
+        let flags = u32::from_ne_bytes(message.header[4..8].try_into().unwrap());
+
+        Ok(Some(Self {
+            id,
+            family: IpFamily::from_af(message.header[0]),
+            kind,
+            onlink: flags & RTNH_F_ONLINK as u32 != 0,
+            protocol: Some(message.header[2].into()),
+        }))
+    }
+
+    fn to_message(&self, message_type: u16) -> NexthopMessage {
+        let mut message = NexthopMessage::new(message_type);
+        message.header[0] = self.family.map_or(0, IpFamily::af);
+        message.header[2] = self.protocol.unwrap_or(RouteProtocol::Static).into();
+        let flags = if self.onlink { RTNH_F_ONLINK as u32 } else { 0 };
+        message.header[4..8].copy_from_slice(&flags.to_ne_bytes());
+
+        let attributes = &mut message.attributes;
+        netlink::put_attribute(attributes, NHA_ID, &self.id.to_ne_bytes());
+
+        match &self.kind {
+            NexthopKind::Single { gateway, interface } => {
+                if let Some(gateway) = gateway {
+                    netlink::put_attribute(attributes, NHA_GATEWAY, &ip_to_vec(*gateway));
+                }
+                if let Some(interface) = interface {
+                    netlink::put_attribute(attributes, NHA_OIF, &interface.0.index.to_ne_bytes());
+                }
+            }
+            NexthopKind::Blackhole => netlink::put_attribute(attributes, NHA_BLACKHOLE, &[]),
+            NexthopKind::Group(group) => {
+                let mut members = vec![];
+                for member in &group.members {
+                    // struct nexthop_grp, weight is stored minus one
+                    members.extend_from_slice(&member.id.to_ne_bytes());
+                    members.push((member.weight.clamp(1, 256) - 1) as u8);
+                    members.extend_from_slice(&[0; 3]);
+                }
+                netlink::put_attribute(attributes, NHA_GROUP, &members);
+
+                if let Some(resilient) = &group.resilient {
+                    netlink::put_attribute(
+                        attributes,
+                        NHA_GROUP_TYPE,
+                        &NEXTHOP_GRP_TYPE_RES.to_ne_bytes(),
+                    );
+
+                    let mut nested = vec![];
+                    netlink::put_attribute(
+                        &mut nested,
+                        NHA_RES_GROUP_BUCKETS,
+                        &resilient.buckets.to_ne_bytes(),
+                    );
+                    if let Some(timer) = resilient.idle_timer {
+                        netlink::put_attribute(
+                            &mut nested,
+                            NHA_RES_GROUP_IDLE_TIMER,
+                            &duration_to_clock(timer).to_ne_bytes(),
+                        );
+                    }
+                    if let Some(timer) = resilient.unbalanced_timer {
+                        netlink::put_attribute(
+                            &mut nested,
+                            NHA_RES_GROUP_UNBALANCED_TIMER,
+                            &duration_to_clock(timer).to_ne_bytes(),
+                        );
+                    }
+                    netlink::put_attribute(attributes, NHA_RES_GROUP | NLA_F_NESTED, &nested);
+                }
+            }
+        }
+
+        message
+    }
+}
+
+/// `struct nhmsg`: family, scope, protocol, padding and flags, followed by attributes.
+type NexthopMessage = RawMessage<NEXTHOP_HEADER_LEN>;
+
+/// Parses an array of `struct nexthop_grp`.
+fn parse_group(bytes: &[u8]) -> Result<Vec<NexthopGroupMember>, Error> {
+    if bytes.len() % NEXTHOP_GROUP_ENTRY_LEN != 0 {
+        return Err(Error::UnexpectedMetadata);
+    }
+    Ok(bytes
+        .chunks_exact(NEXTHOP_GROUP_ENTRY_LEN)
+        .map(|entry| NexthopGroupMember {
+            id: u32::from_ne_bytes(entry[0..4].try_into().unwrap()),
+            weight: entry[4] as u16 + 1,
+        })
+        .collect())
+}
+
+fn clock_to_duration(ticks: u32) -> Duration {
+    Duration::from_millis(ticks as u64 * 1000 / USER_HZ)
+}
+
+fn duration_to_clock(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis() * USER_HZ as u128 / 1000).unwrap_or(u32::MAX)
+}
+
+/// Returns all nexthop objects. Fails on kernels older than 5.3.
+pub fn list_nexthops() -> Result<Vec<Nexthop>, Error> {
+    list_nexthops_in(None)
+}
+
+/// Adds a nexthop object. Fails, if an object with the same id already exists.
+pub fn add_nexthop(nexthop: &Nexthop) -> Result<(), Error> {
+    add_nexthop_in(None, nexthop, false)
+}
+
+/// Adds a nexthop object or replaces the existing one with the same id.
+///
+/// Routes, referring to the object, switch to the new next hop at once.
+pub fn replace_nexthop(nexthop: &Nexthop) -> Result<(), Error> {
+    add_nexthop_in(None, nexthop, true)
+}
+
+/// Deletes a nexthop object. Routes, referring to it, are deleted by the kernel too.
+pub fn delete_nexthop(id: u32) -> Result<(), Error> {
+    delete_nexthop_in(None, id)
+}
+
+pub(crate) fn list_nexthops_in(netns: Option<&NetnsHandle>) -> Result<Vec<Nexthop>, Error> {
+    let message = NexthopMessage::new(RTM_GETNEXTHOP);
+
+    let socket = netlink::socket(netns)?;
+    let mut nexthops = vec![];
+    for response in netlink::request(&socket, message, NLM_F_DUMP)? {
+        if response.message_type == RTM_NEWNEXTHOP {
+            nexthops.extend(Nexthop::from_message(response, netns)?);
+        }
+    }
+    Ok(nexthops)
+}
+
+pub(crate) fn add_nexthop_in(
+    netns: Option<&NetnsHandle>,
+    nexthop: &Nexthop,
+    replace: bool,
+) -> Result<(), Error> {
+    let flags = if replace {
+        NLM_F_CREATE | NLM_F_REPLACE
+    } else {
+        NLM_F_CREATE | NLM_F_EXCL
+    };
+
+    let socket = netlink::socket(netns)?;
+    netlink::request(&socket, nexthop.to_message(RTM_NEWNEXTHOP), flags)?;
+    Ok(())
+}
+
+pub(crate) fn delete_nexthop_in(netns: Option<&NetnsHandle>, id: u32) -> Result<(), Error> {
+    let mut message = NexthopMessage::new(RTM_DELNEXTHOP);
+    netlink::put_attribute(&mut message.attributes, NHA_ID, &id.to_ne_bytes());
+
+    let socket = netlink::socket(netns)?;
+    netlink::request(&socket, message, 0)?;
+    Ok(())
+}
+
+impl NetnsHandle {
+    /// Returns all nexthop objects inside this namespace.
+    pub fn list_nexthops(&self) -> Result<Vec<Nexthop>, Error> {
+        list_nexthops_in(Some(self))
+    }
+
+    pub fn add_nexthop(&self, nexthop: &Nexthop) -> Result<(), Error> {
+        add_nexthop_in(Some(self), nexthop, false)
+    }
+
+    pub fn replace_nexthop(&self, nexthop: &Nexthop) -> Result<(), Error> {
+        add_nexthop_in(Some(self), nexthop, true)
+    }
+
+    pub fn delete_nexthop(&self, id: u32) -> Result<(), Error> {
+        delete_nexthop_in(Some(self), id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(index: u32) -> Interface {
+        Interface(InterfaceHandle { index, netns: None })
+    }
+
+    /// Sends a nexthop object through the kernel format and parses it back.
+    fn round_trip(nexthop: &Nexthop) -> Nexthop {
+        let message = netlink::reparse(nexthop.to_message(RTM_NEWNEXTHOP));
+        assert_eq!(message.message_type, RTM_NEWNEXTHOP);
+        Nexthop::from_message(message, None).unwrap().unwrap()
+    }
+
+    fn attribute(message: &NexthopMessage, kind: u16) -> Option<Vec<u8>> {
+        NlasIterator::new(&message.attributes)
+            .map(Result::unwrap)
+            .find(|nla| nla.kind() == kind)
+            .map(|nla| nla.value().to_vec())
+    }
+
+    fn group(nexthop: &Nexthop) -> &NexthopGroup {
+        match &nexthop.kind {
+            NexthopKind::Group(group) => group,
+            kind => panic!("expected a group, got {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn group_layout() {
+        let nexthop = Nexthop::group(
+            10,
+            vec![
+                NexthopGroupMember::new(1, 1),
+                NexthopGroupMember::new(2, 256),
+            ],
+        );
+        let message = nexthop.to_message(RTM_NEWNEXTHOP);
+
+        // struct nexthop_grp: id, weight minus one, reserved bytes
+        let mut expected = 1u32.to_ne_bytes().to_vec();
+        expected.extend_from_slice(&[0, 0, 0, 0]);
+        expected.extend_from_slice(&2u32.to_ne_bytes());
+        expected.extend_from_slice(&[255, 0, 0, 0]);
+        assert_eq!(attribute(&message, NHA_GROUP).unwrap(), expected);
+        assert_eq!(attribute(&message, NHA_GROUP_TYPE), None);
+        assert_eq!(message.header[0], 0);
+
+        let parsed = round_trip(&nexthop);
+        assert_eq!(parsed.id, 10);
+        assert_eq!(parsed.family, None);
+        assert_eq!(group(&parsed), group(&nexthop));
+    }
+
+    #[test]
+    fn parse_group_entries() {
+        let mut bytes = 7u32.to_ne_bytes().to_vec();
+        bytes.extend_from_slice(&[4, 0xff, 0xff, 0xff]);
+        assert_eq!(
+            parse_group(&bytes).unwrap(),
+            [NexthopGroupMember::new(7, 5)]
+        );
+
+        assert!(parse_group(&[]).unwrap().is_empty());
+        assert!(parse_group(&bytes[..6]).is_err());
+    }
+
+    #[test]
+    fn resilient_group_round_trip() {
+        let resilient = ResilientGroup::new(32)
+            .idle_timer(Duration::from_secs(1))
+            .unbalanced_timer(Duration::from_millis(2500));
+        let nexthop = Nexthop::resilient_group(10, vec![NexthopGroupMember::new(1, 3)], resilient);
+
+        let message = nexthop.to_message(RTM_NEWNEXTHOP);
+        assert_eq!(
+            attribute(&message, NHA_GROUP_TYPE).unwrap(),
+            NEXTHOP_GRP_TYPE_RES.to_ne_bytes()
+        );
+        let nested = attribute(&message, NHA_RES_GROUP).unwrap();
+        let nested: Vec<_> = NlasIterator::new(&nested)
+            .map(|nla| {
+                let nla = nla.unwrap();
+                (nla.kind(), nla.value().to_vec())
+            })
+            .collect();
+        // Timers are passed in clock_t units
+        assert_eq!(
+            nested,
+            [
+                (NHA_RES_GROUP_BUCKETS, 32u16.to_ne_bytes().to_vec()),
+                (NHA_RES_GROUP_IDLE_TIMER, 100u32.to_ne_bytes().to_vec()),
+                (
+                    NHA_RES_GROUP_UNBALANCED_TIMER,
+                    250u32.to_ne_bytes().to_vec()
+                ),
+            ]
+        );
+
+        assert_eq!(group(&round_trip(&nexthop)), group(&nexthop));
+    }
+
+    #[test]
+    fn resilient_group_without_unbalanced_timer() {
+        // The kernel reports a disabled unbalanced timer as 0
+        let mut nested = vec![];
+        netlink::put_attribute(&mut nested, NHA_RES_GROUP_BUCKETS, &8u16.to_ne_bytes());
+        netlink::put_attribute(
+            &mut nested,
+            NHA_RES_GROUP_IDLE_TIMER,
+            &12000u32.to_ne_bytes(),
+        );
+        netlink::put_attribute(
+            &mut nested,
+            NHA_RES_GROUP_UNBALANCED_TIMER,
+            &0u32.to_ne_bytes(),
+        );
+
+        let mut message = NexthopMessage::new(RTM_NEWNEXTHOP);
+        let attributes = &mut message.attributes;
+        netlink::put_attribute(attributes, NHA_ID, &3u32.to_ne_bytes());
+        netlink::put_attribute(attributes, NHA_GROUP, &[1, 0, 0, 0, 0, 0, 0, 0]);
+        netlink::put_attribute(
+            attributes,
+            NHA_GROUP_TYPE,
+            &NEXTHOP_GRP_TYPE_RES.to_ne_bytes(),
+        );
+        netlink::put_attribute(attributes, NHA_RES_GROUP | NLA_F_NESTED, &nested);
+
+        let nexthop = Nexthop::from_message(message, None).unwrap().unwrap();
+        let resilient = group(&nexthop).resilient.unwrap();
+        assert_eq!(resilient.buckets, 8);
+        assert_eq!(resilient.idle_timer, Some(Duration::from_secs(120)));
+        assert_eq!(resilient.unbalanced_timer, None);
+    }
+
+    #[test]
+    fn single_round_trip() {
+        let nexthop = Nexthop::via(1, "fe80::1".parse().unwrap(), &link(3))
+            .onlink(true)
+            .protocol(RouteProtocol::Bgp);
+        let message = nexthop.to_message(RTM_NEWNEXTHOP);
+        assert_eq!(message.header[0], libc::AF_INET6 as u8);
+        assert_eq!(message.header[2], u8::from(RouteProtocol::Bgp));
+        assert_eq!(message.header[4..8], (RTNH_F_ONLINK as u32).to_ne_bytes());
+
+        let parsed = round_trip(&nexthop);
+        assert_eq!(parsed.id, 1);
+        assert_eq!(parsed.family, Some(IpFamily::V6));
+        assert!(parsed.onlink);
+        assert_eq!(parsed.protocol, Some(RouteProtocol::Bgp));
+        let NexthopKind::Single { gateway, interface } = parsed.kind else {
+            panic!("expected a single next hop");
+        };
+        assert_eq!(gateway, Some("fe80::1".parse().unwrap()));
+        assert_eq!(interface.map(|iface| iface.0.index), Some(3));
+
+        let parsed = round_trip(&Nexthop::device(2, IpFamily::V4, &link(4)));
+        assert_eq!(parsed.family, Some(IpFamily::V4));
+        assert!(!parsed.onlink);
+        // Unset protocol is sent as static
+        assert_eq!(parsed.protocol, Some(RouteProtocol::Static));
+        let NexthopKind::Single { gateway, interface } = parsed.kind else {
+            panic!("expected a single next hop");
+        };
+        assert_eq!(gateway, None);
+        assert_eq!(interface.map(|iface| iface.0.index), Some(4));
+
+        let parsed = round_trip(&Nexthop::blackhole(5, IpFamily::V4));
+        assert!(matches!(parsed.kind, NexthopKind::Blackhole));
+    }
+
+    #[test]
+    fn message_without_id() {
+        let message = NexthopMessage::new(RTM_NEWNEXTHOP);
+        assert!(Nexthop::from_message(message, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn clock_conversion() {
+        assert_eq!(clock_to_duration(150), Duration::from_millis(1500));
+        assert_eq!(duration_to_clock(Duration::from_millis(1500)), 150);
+        // Fractions of a tick are truncated, values beyond the range saturate
+        assert_eq!(duration_to_clock(Duration::from_millis(1234)), 123);
+        assert_eq!(duration_to_clock(Duration::from_secs(u64::MAX)), u32::MAX);
+    }
+}
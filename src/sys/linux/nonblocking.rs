@@ -0,0 +1,581 @@
+//! Async API for the tokio runtime, enabled by the `tokio` feature.
+//!
+//! Requests are sent over non-blocking netlink sockets, registered with the tokio reactor, so
+//! awaiting them doesn't block runtime threads. Sockets inside other namespaces are created on the
+//! blocking thread pool. Everything here must be used from within a tokio runtime.
+//!
+//! An [`Interface`] keeps the [`Connection`], it was obtained with, so repeated calls reuse one
+//! socket. Like their blocking counterparts, the free functions open a socket per call.
+
+use super::handle::{
+    address_network, link_name, make_address_message, make_link_message, LinkChange,
+};
+use super::monitor::{AddressInfo, Event, LinkInfo, MonitorGroup};
+use super::wait::{Satisfied, WaitCondition};
+use super::{netlink, Neighbor, NeighborFilter, NetnsHandle, Route, RouteFilter};
+use crate::sys::InterfaceHandle;
+use crate::Error;
+use advmac::MacAddr6;
+use ipnet::IpNet;
+use libc::{IFF_LOWER_UP, IFF_RUNNING, IFF_UP};
+use netlink_packet_core::{NetlinkDeserializable, NetlinkSerializable};
+use netlink_packet_route::link::nlas::Nla as LinkNla;
+use netlink_packet_route::{
+    AddressMessage, LinkMessage, RtnlMessage, NLM_F_CREATE, NLM_F_DUMP, NLM_F_REPLACE,
+};
+use netlink_sys::constants::NETLINK_ROUTE;
+use netlink_sys::Socket;
+use std::fmt::{self, Debug};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+use tokio::sync::Mutex;
+
+/// Non-blocking rtnetlink socket, registered with the tokio reactor.
+struct AsyncSocket {
+    socket: AsyncFd<Socket>,
+    sequence_number: u32,
+}
+
+impl AsyncSocket {
+    async fn new(netns: Option<&NetnsHandle>) -> Result<Self, Error> {
+        let socket = open(netns).await?;
+        socket.set_non_blocking(true)?;
+        Ok(Self {
+            socket: register(socket, Interest::READABLE | Interest::WRITABLE)?,
+            sequence_number: 0,
+        })
+    }
+
+    /// Async counterpart of [`netlink::request`]. Requests are numbered per socket, like
+    /// [`super::Connection`] does, so responses to earlier requests, that were cancelled, are
+    /// discarded.
+    async fn request<T>(&mut self, message: T, flags: u16) -> Result<Vec<T>, Error>
+    where
+        T: NetlinkSerializable + NetlinkDeserializable + Debug,
+    {
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+        let sequence_number = self.sequence_number;
+        let mut buf = vec![];
+        netlink::encode_message(&mut buf, message, flags, sequence_number);
+        loop {
+            let mut guard = self.socket.writable().await?;
+            if let Ok(result) = guard.try_io(|socket| socket.get_ref().send(&buf, 0)) {
+                result?;
+                break;
+            }
+        }
+
+        let mut responses = vec![];
+        loop {
+            let mut guard = self.socket.readable().await?;
+            let Ok(result) = guard.try_io(|socket| socket.get_ref().recv_from_full()) else {
+                continue;
+            };
+            let (buf, _) = result?;
+            if netlink::decode_responses(&buf, sequence_number, &mut responses)? {
+                return Ok(responses);
+            }
+        }
+    }
+}
+
+/// Registers `inner` with the reactor.
+///
+/// Newer versions of tokio deprecate the safe constructors of `AsyncFd` in favour of the unsafe
+/// `AsyncFd::register`, which older versions lack. `inner` owns its descriptor, which is what
+/// `register` requires.
+#[allow(deprecated)]
+fn register<T: AsRawFd>(inner: T, interest: Interest) -> io::Result<AsyncFd<T>> {
+    AsyncFd::with_interest(inner, interest)
+}
+
+/// Async counterpart of [`netlink::socket`]. Entering a namespace blocks on a helper thread, so
+/// it's done on the blocking thread pool.
+async fn open(netns: Option<&NetnsHandle>) -> Result<Socket, Error> {
+    let socket = match netns {
+        Some(netns) => {
+            let netns = netns.clone();
+            tokio::task::spawn_blocking(move || netns.socket(NETLINK_ROUTE))
+                .await
+                .map_err(io::Error::from)??
+        }
+        None => Socket::new(NETLINK_ROUTE)?,
+    };
+    netlink::connect(socket)
+}
+
+/// Async counterpart of [`super::Connection`].
+///
+/// Keeps one rtnetlink socket, opened on first use, for all requests. Requests are sent one at a
+/// time. Clones share the socket.
+#[derive(Clone)]
+pub struct Connection(Arc<ConnectionInner>);
+
+struct ConnectionInner {
+    netns: Option<NetnsHandle>,
+    socket: Mutex<Option<AsyncSocket>>,
+}
+
+impl Connection {
+    /// Connects to the namespace of the calling thread.
+    pub fn new() -> Self {
+        Self::new_in(None)
+    }
+
+    /// Connects to given namespace.
+    pub fn with_netns(netns: &NetnsHandle) -> Self {
+        Self::new_in(Some(netns))
+    }
+
+    fn new_in(netns: Option<&NetnsHandle>) -> Self {
+        Self(Arc::new(ConnectionInner {
+            netns: netns.cloned(),
+            socket: Mutex::new(None),
+        }))
+    }
+
+    /// Returns the namespace, this Connection is bound to, or `None` for the namespace of the
+    /// thread, that uses it first.
+    pub fn netns(&self) -> Option<&NetnsHandle> {
+        self.0.netns.as_ref()
+    }
+
+    pub async fn list_interfaces(&self) -> Result<Vec<LinkInfo>, Error> {
+        Ok(self
+            .dump_links()
+            .await?
+            .into_iter()
+            .map(|link| LinkInfo::from_message(link, self.netns()))
+            .collect())
+    }
+
+    /// Returns routes from all routing tables, matching given filter.
+    pub async fn list_routes(&self, filter: &RouteFilter) -> Result<Vec<Route>, Error> {
+        let responses = self.request(filter.to_message(), NLM_F_DUMP).await?;
+        filter.collect(responses, self.netns())
+    }
+
+    /// Returns neighbor cache entries, matching given filter.
+    pub async fn list_neighbors(&self, filter: &NeighborFilter) -> Result<Vec<Neighbor>, Error> {
+        let responses = self.request(filter.to_message(), NLM_F_DUMP).await?;
+        filter.collect(responses, self.netns())
+    }
+
+    /// Returns the Interface with given index, which shares this Connection. The Interface isn't
+    /// checked to exist.
+    pub fn interface(&self, index: u32) -> Interface {
+        let handle = InterfaceHandle {
+            index,
+            netns: self.0.netns.clone(),
+        };
+        Interface {
+            inner: crate::Interface(handle),
+            connection: self.clone(),
+        }
+    }
+
+    /// Sends a request, and waits for the responses, while other requests wait for their turn.
+    async fn request(&self, message: RtnlMessage, flags: u16) -> Result<Vec<RtnlMessage>, Error> {
+        let mut socket = self.0.socket.lock().await;
+        if socket.is_none() {
+            *socket = Some(AsyncSocket::new(self.netns()).await?);
+        }
+        socket.as_mut().unwrap().request(message, flags).await
+    }
+
+    /// Async counterpart of [`dump_links`](super::handle::dump_links).
+    async fn dump_links(&self) -> Result<Vec<LinkMessage>, Error> {
+        let responses = self
+            .request(RtnlMessage::GetLink(LinkMessage::default()), NLM_F_DUMP)
+            .await?;
+
+        Ok(responses
+            .into_iter()
+            .filter_map(|response| match response {
+                RtnlMessage::NewLink(link) => Some(link),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Connection")
+            .field("netns", &self.netns().is_some())
+            .finish()
+    }
+}
+
+/// Async counterpart of [`crate::Interface`].
+///
+/// Converts from and into [`crate::Interface`], so that it can also be obtained from a namespace.
+/// Requests are sent over the [`Connection`], shared with the clones of this Interface.
+#[derive(Debug, Clone)]
+pub struct Interface {
+    inner: crate::Interface,
+    connection: Connection,
+}
+
+impl Interface {
+    /// Returns Interface with given index, or Error if not found.
+    pub async fn try_from_index(index: u32) -> Result<Self, Error> {
+        let interface = Connection::new().interface(index);
+        interface.link().await?;
+        Ok(interface)
+    }
+
+    /// Returns Interface with given name, or Error if not found.
+    pub async fn try_from_name(name: &str) -> Result<Self, Error> {
+        let mut message = LinkMessage::default();
+        message.nlas.push(LinkNla::IfName(name.to_string()));
+
+        let connection = Connection::new();
+        let link = connection
+            .request(RtnlMessage::GetLink(message), 0)
+            .await?
+            .into_iter()
+            .find_map(|response| match response {
+                RtnlMessage::NewLink(link) => Some(link),
+                _ => None,
+            })
+            .ok_or(Error::InterfaceNotFound)?;
+
+        Ok(connection.interface(link.header.index))
+    }
+
+    pub fn as_std(&self) -> &crate::Interface {
+        &self.inner
+    }
+
+    pub fn into_std(self) -> crate::Interface {
+        self.inner
+    }
+
+    /// Returns the Connection, requests of this Interface are sent over.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn index(&self) -> Result<u32, Error> {
+        Ok(self.handle().index)
+    }
+
+    pub async fn name(&self) -> Result<String, Error> {
+        link_name(&self.link().await?)
+            .map(str::to_string)
+            .ok_or(Error::UnexpectedMetadata)
+    }
+
+    /// Returns flags, MTU and other attributes of this Interface at once.
+    pub async fn link_info(&self) -> Result<LinkInfo, Error> {
+        Ok(LinkInfo::from_message(
+            self.link().await?,
+            self.handle().netns.as_ref(),
+        ))
+    }
+
+    /// Returns array of IP addresses, assigned to this Interface
+    pub async fn addresses(&self) -> Result<Vec<IpNet>, Error> {
+        let responses = self
+            .request(
+                RtnlMessage::GetAddress(AddressMessage::default()),
+                NLM_F_DUMP,
+            )
+            .await?;
+
+        let mut result = vec![];
+        for response in responses {
+            let RtnlMessage::NewAddress(message) = response else {
+                continue;
+            };
+            if message.header.index != self.handle().index {
+                continue;
+            }
+            if let Some(network) = address_network(&message)? {
+                result.push(network);
+            }
+        }
+        Ok(result)
+    }
+
+    pub async fn add_address(&self, network: IpNet) -> Result<(), Error> {
+        let message = make_address_message(self.handle().index, network);
+        self.request(
+            RtnlMessage::NewAddress(message),
+            NLM_F_CREATE | NLM_F_REPLACE,
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_address(&self, network: IpNet) -> Result<(), Error> {
+        let message = make_address_message(self.handle().index, network);
+        self.request(RtnlMessage::DelAddress(message), 0).await?;
+        Ok(())
+    }
+
+    pub async fn mtu(&self) -> Result<u32, Error> {
+        self.link_info().await?.mtu.ok_or(Error::UnexpectedMetadata)
+    }
+
+    pub async fn set_mtu(&self, mtu: u32) -> Result<(), Error> {
+        self.change_link(LinkChange::Mtu(mtu)).await
+    }
+
+    /// Returns MAC address, assigned to this Interface
+    pub async fn hwaddress(&self) -> Result<MacAddr6, Error> {
+        self.link_info()
+            .await?
+            .hwaddress
+            .ok_or(Error::UnexpectedMetadata)
+    }
+
+    pub async fn set_hwaddress(&self, hwaddress: MacAddr6) -> Result<(), Error> {
+        self.change_link(LinkChange::HwAddress(hwaddress)).await
+    }
+
+    pub async fn set_up(&self, v: bool) -> Result<(), Error> {
+        self.change_link(LinkChange::Flag(IFF_UP as _, v)).await
+    }
+
+    pub async fn set_running(&self, v: bool) -> Result<(), Error> {
+        self.change_link(LinkChange::Flag(IFF_RUNNING as _, v))
+            .await
+    }
+
+    fn handle(&self) -> &InterfaceHandle {
+        &self.inner.0
+    }
+
+    fn link_message(&self) -> LinkMessage {
+        let mut message = LinkMessage::default();
+        message.header.index = self.handle().index;
+        message
+    }
+
+    /// Sends a request to the namespace, this interface belongs to.
+    async fn request(&self, message: RtnlMessage, flags: u16) -> Result<Vec<RtnlMessage>, Error> {
+        self.connection.request(message, flags).await
+    }
+
+    async fn link(&self) -> Result<LinkMessage, Error> {
+        self.request(RtnlMessage::GetLink(self.link_message()), 0)
+            .await?
+            .into_iter()
+            .find_map(|response| match response {
+                RtnlMessage::NewLink(link) => Some(link),
+                _ => None,
+            })
+            .ok_or(Error::InterfaceNotFound)
+    }
+
+    async fn change_link(&self, change: LinkChange) -> Result<(), Error> {
+        let message = make_link_message(self.handle().index, change);
+        self.request(RtnlMessage::SetLink(message), 0).await?;
+        Ok(())
+    }
+}
+
+impl From<crate::Interface> for Interface {
+    /// Wraps an Interface with a new [`Connection`] to its namespace.
+    fn from(interface: crate::Interface) -> Self {
+        let connection = Connection::new_in(interface.0.netns.as_ref());
+        Self {
+            inner: interface,
+            connection,
+        }
+    }
+}
+
+impl From<Interface> for crate::Interface {
+    fn from(interface: Interface) -> Self {
+        interface.inner
+    }
+}
+
+/// Returns all interfaces. They share one [`Connection`].
+pub async fn list_interfaces() -> Result<Vec<Interface>, Error> {
+    let connection = Connection::new();
+    Ok(connection
+        .dump_links()
+        .await?
+        .into_iter()
+        .map(|link| connection.interface(link.header.index))
+        .collect())
+}
+
+/// Returns routes from all routing tables, matching given filter.
+pub async fn list_routes(filter: &RouteFilter) -> Result<Vec<Route>, Error> {
+    Connection::new().list_routes(filter).await
+}
+
+/// Returns ARP and NDP neighbor cache entries, matching given filter.
+pub async fn list_neighbors(filter: &NeighborFilter) -> Result<Vec<Neighbor>, Error> {
+    Connection::new().list_neighbors(filter).await
+}
+
+/// Async counterpart of [`super::wait_for_interface`].
+pub async fn wait_for_interface(name: &str, timeout: Duration) -> Result<Interface, Error> {
+    let connection = Connection::new();
+    let satisfied = wait(&connection, WaitCondition::Interface(name), timeout).await?;
+    let index = satisfied.into_interface()?.0.index;
+    Ok(connection.interface(index))
+}
+
+/// Async counterpart of [`super::wait_until_up`].
+pub async fn wait_until_up(interface: &Interface, timeout: Duration) -> Result<(), Error> {
+    let condition = WaitCondition::Flags(interface.handle().index, IFF_UP as _);
+    wait(&interface.connection, condition, timeout).await?;
+    Ok(())
+}
+
+/// Async counterpart of [`super::wait_for_carrier`].
+pub async fn wait_for_carrier(interface: &Interface, timeout: Duration) -> Result<(), Error> {
+    let condition = WaitCondition::Flags(interface.handle().index, IFF_LOWER_UP as _);
+    wait(&interface.connection, condition, timeout).await?;
+    Ok(())
+}
+
+/// Async counterpart of [`super::wait_for_address`].
+pub async fn wait_for_address<P>(
+    interface: &Interface,
+    mut predicate: P,
+    timeout: Duration,
+) -> Result<AddressInfo, Error>
+where
+    P: FnMut(&AddressInfo) -> bool + Send,
+{
+    let condition = WaitCondition::Address(interface.handle().index, &mut predicate);
+    wait(&interface.connection, condition, timeout)
+        .await?
+        .into_address()
+}
+
+/// Async counterpart of [`super::wait_until_removed`].
+pub async fn wait_until_removed(interface: &Interface, timeout: Duration) -> Result<(), Error> {
+    let condition = WaitCondition::Removed(interface.handle().index);
+    wait(&interface.connection, condition, timeout).await?;
+    Ok(())
+}
+
+/// Waits for `condition` inside the namespace of `connection`, which is used to check the state.
+async fn wait(
+    connection: &Connection,
+    condition: WaitCondition<'_>,
+    timeout: Duration,
+) -> Result<Satisfied, Error> {
+    tokio::time::timeout(timeout, wait_without_timeout(connection, condition))
+        .await
+        .map_err(|_| Error::Timeout)?
+}
+
+async fn wait_without_timeout(
+    connection: &Connection,
+    mut condition: WaitCondition<'_>,
+) -> Result<Satisfied, Error> {
+    // Subscribed before the state is checked, so that no change is missed in between. Links aren't
+    // listed, like in super::wait.
+    let netns = connection.netns();
+    let groups = condition.groups();
+    let monitor = super::Monitor::subscribe(open(netns).await?, netns, groups, &[])?;
+    let mut monitor = Monitor::with_connection(monitor, connection.clone())?;
+
+    let mut event = Event::Resync;
+    loop {
+        let satisfied = match event {
+            Event::Resync => {
+                let (message, flags) = condition.query();
+                condition.check(connection.request(message, flags).await, netns)?
+            }
+            event => condition.event(&event)?,
+        };
+        if let Some(satisfied) = satisfied {
+            return Ok(satisfied);
+        }
+        event = monitor.next_event().await?;
+    }
+}
+
+/// Async counterpart of [`super::Monitor`].
+pub struct Monitor {
+    monitor: AsyncFd<super::Monitor>,
+    /// Re-reads links after an overrun.
+    connection: Connection,
+}
+
+impl Monitor {
+    /// Subscribes to all groups in the namespace of the calling thread.
+    pub async fn new() -> Result<Self, Error> {
+        Self::new_in(None, &MonitorGroup::ALL).await
+    }
+
+    /// Subscribes to given groups in the namespace of the calling thread.
+    pub async fn with_groups(groups: &[MonitorGroup]) -> Result<Self, Error> {
+        Self::new_in(None, groups).await
+    }
+
+    /// Subscribes to given groups inside a namespace.
+    pub async fn with_netns(netns: &NetnsHandle, groups: &[MonitorGroup]) -> Result<Self, Error> {
+        Self::new_in(Some(netns), groups).await
+    }
+
+    async fn new_in(netns: Option<&NetnsHandle>, groups: &[MonitorGroup]) -> Result<Self, Error> {
+        // Links are listed before subscribing, like super::Monitor::new_in does
+        let connection = Connection::new_in(netns);
+        let links = connection.dump_links().await?;
+        let socket = open(netns).await?;
+        let monitor = super::Monitor::subscribe(socket, netns, groups, &links)?;
+        Self::with_connection(monitor, connection)
+    }
+
+    /// Registers a blocking Monitor with the tokio reactor. Unlike [`with_netns`](Self::with_netns),
+    /// [`NetnsHandle::monitor`] blocks while it lists links, so it's better called outside of the
+    /// runtime.
+    pub fn from_std(monitor: super::Monitor) -> Result<Self, Error> {
+        let connection = Connection::new_in(monitor.netns());
+        Self::with_connection(monitor, connection)
+    }
+
+    fn with_connection(monitor: super::Monitor, connection: Connection) -> Result<Self, Error> {
+        monitor.socket().set_non_blocking(true)?;
+        Ok(Self {
+            monitor: register(monitor, Interest::READABLE)?,
+            connection,
+        })
+    }
+
+    /// Returns the next event, waiting until it arrives.
+    pub async fn next_event(&mut self) -> Result<Event, Error> {
+        loop {
+            if let Some(event) = self.monitor.get_mut().pending_event() {
+                return Ok(event);
+            }
+
+            let mut guard = self.monitor.readable_mut().await?;
+            let Ok(result) = guard.try_io(|monitor| monitor.get_ref().socket().recv_from_full())
+            else {
+                continue;
+            };
+            let event = guard.get_inner_mut().receive(result)?;
+            drop(guard);
+            if let Some(event) = event {
+                if let Event::Resync = event {
+                    let links = self.connection.dump_links().await?;
+                    self.monitor.get_mut().set_links(&links);
+                }
+                return Ok(event);
+            }
+        }
+    }
+}
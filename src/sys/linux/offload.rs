@@ -0,0 +1,301 @@
+use super::ethtool::{connection, get, ioctl, parse_bitset, put_bitset, set};
+use super::netlink::parse_string;
+use crate::sys::InterfaceHandle;
+use crate::Error;
+use netlink_packet_route::nlas::NlasIterator;
+use std::collections::BTreeMap;
+use std::io;
+use std::mem;
+
+const ETHTOOL_MSG_FEATURES_GET: u8 = 11;
+const ETHTOOL_MSG_FEATURES_SET: u8 = 12;
+
+const ETHTOOL_A_FEATURES_HW: u16 = 2;
+const ETHTOOL_A_FEATURES_WANTED: u16 = 3;
+const ETHTOOL_A_FEATURES_ACTIVE: u16 = 4;
+const ETHTOOL_A_FEATURES_NOCHANGE: u16 = 5;
+
+const ETHTOOL_GSTRINGS: u32 = 0x1b;
+const ETHTOOL_GSSET_INFO: u32 = 0x37;
+const ETHTOOL_GFEATURES: u32 = 0x3a;
+const ETHTOOL_SFEATURES: u32 = 0x3b;
+
+const ETH_SS_FEATURES: u32 = 4;
+const ETH_GSTRING_LEN: usize = 32;
+
+/// Feature of a network device, as shown by `ethtool -k`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Feature {
+    /// Name, e.g. `rx-gro` or `tx-tcp-segmentation`.
+    pub name: String,
+    pub active: bool,
+    /// Whether the feature is requested. A requested feature stays inactive, while features, it
+    /// depends on, are off.
+    pub requested: bool,
+    /// `false` for features, that the device lacks or can't turn off, shown as `[fixed]`.
+    pub changeable: bool,
+}
+
+impl Feature {
+    /// Builds a feature from its bits in the sets of hardware, requested, active and never
+    /// changeable features.
+    fn new(name: String, [hw, requested, active, nochange]: [bool; 4]) -> Self {
+        Self {
+            name,
+            active,
+            requested,
+            changeable: hw && !nochange,
+        }
+    }
+}
+
+/// Offloads, as toggled by `ethtool -K`. Most of them stand for a group of [`Feature`]s, e.g.
+/// checksumming of every protocol.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Offload {
+    /// `rx`
+    RxChecksum,
+    /// `tx`
+    TxChecksum,
+    /// `sg`
+    ScatterGather,
+    /// `tso`, for IPv4 and IPv6
+    Tso,
+    /// `gso`
+    Gso,
+    /// `gro`
+    Gro,
+    /// `lro`
+    Lro,
+    /// `rxvlan`
+    RxVlan,
+    /// `txvlan`
+    TxVlan,
+    /// `ntuple`
+    Ntuple,
+    /// `rxhash`
+    RxHash,
+}
+
+impl Offload {
+    /// Names of features, this offload stands for, with an optional wildcard.
+    fn pattern(self) -> &'static str {
+        match self {
+            Self::RxChecksum => "rx-checksum",
+            Self::TxChecksum => "tx-checksum-*",
+            Self::ScatterGather => "tx-scatter-gather",
+            Self::Tso => "tx-tcp*-segmentation",
+            Self::Gso => "tx-generic-segmentation",
+            Self::Gro => "rx-gro",
+            Self::Lro => "rx-lro",
+            Self::RxVlan => "rx-vlan-hw-parse",
+            Self::TxVlan => "tx-vlan-hw-insert",
+            Self::Ntuple => "rx-ntuple-filter",
+            Self::RxHash => "rx-hashing",
+        }
+    }
+
+    fn matches(self, name: &str) -> bool {
+        match self.pattern().split_once('*') {
+            Some((prefix, suffix)) => {
+                name.len() >= prefix.len() + suffix.len()
+                    && name.starts_with(prefix)
+                    && name.ends_with(suffix)
+            }
+            None => name == self.pattern(),
+        }
+    }
+}
+
+/// `struct ethtool_sset_info`, with room for the size of a single string set.
+#[repr(C)]
+struct EthtoolSsetInfo {
+    cmd: u32,
+    reserved: u32,
+    sset_mask: u64,
+    data: u32,
+}
+
+impl InterfaceHandle {
+    /// Returns features of the device in the order of their bits. Features, that the device lacks
+    /// and that are neither requested nor active, are omitted.
+    pub fn features(&self) -> Result<Vec<Feature>, Error> {
+        let Some(mut connection) = connection(self.netns.as_ref())? else {
+            return self.features_from_ioctl();
+        };
+        let reply = get(&mut connection, ETHTOOL_MSG_FEATURES_GET, self.index)?;
+
+        // Sets may come with or without a mask, so cleared bits may or may not be listed. Only
+        // features with a bit set in any of them are kept, like the ioctl fallback does.
+        let mut sets: BTreeMap<u32, (String, [bool; 4])> = BTreeMap::new();
+        for nla in NlasIterator::new(&reply) {
+            let nla = nla?;
+            let set = match nla.kind() {
+                ETHTOOL_A_FEATURES_HW => 0,
+                ETHTOOL_A_FEATURES_WANTED => 1,
+                ETHTOOL_A_FEATURES_ACTIVE => 2,
+                ETHTOOL_A_FEATURES_NOCHANGE => 3,
+                _ => continue,
+            };
+            for (index, name, value) in parse_bitset(nla.value())? {
+                let Some(index) = index else {
+                    continue;
+                };
+                sets.entry(index).or_insert((name, [false; 4])).1[set] |= value;
+            }
+        }
+        Ok(sets
+            .into_values()
+            .filter(|(name, sets)| !name.is_empty() && sets.contains(&true))
+            .map(|(name, sets)| Feature::new(name, sets))
+            .collect())
+    }
+
+    /// Requests features to be turned on or off by name.
+    pub fn set_features(&self, features: &[(&str, bool)]) -> Result<(), Error> {
+        let Some(mut connection) = connection(self.netns.as_ref())? else {
+            return self.set_features_with_ioctl(features);
+        };
+        let mut attributes = vec![];
+        put_bitset(&mut attributes, ETHTOOL_A_FEATURES_WANTED, features);
+        set(
+            &mut connection,
+            ETHTOOL_MSG_FEATURES_SET,
+            self.index,
+            attributes,
+        )
+    }
+
+    pub fn offload(&self, offload: Offload) -> Result<bool, Error> {
+        Ok(self
+            .features()?
+            .iter()
+            .any(|feature| feature.active && offload.matches(&feature.name)))
+    }
+
+    pub fn set_offload(&self, offload: Offload, v: bool) -> Result<(), Error> {
+        let features = self.features()?;
+        let changes: Vec<_> = features
+            .iter()
+            .filter(|feature| feature.changeable && offload.matches(&feature.name))
+            .map(|feature| (feature.name.as_str(), v))
+            .collect();
+        if changes.is_empty() {
+            return Err(io::Error::from_raw_os_error(libc::EOPNOTSUPP).into());
+        }
+        self.set_features(&changes)
+    }
+
+    /// Returns names of features, known to the kernel, by bit index.
+    fn feature_names(&self) -> Result<Vec<String>, Error> {
+        let mut info = EthtoolSsetInfo {
+            cmd: ETHTOOL_GSSET_INFO,
+            reserved: 0,
+            sset_mask: 1 << ETH_SS_FEATURES,
+            data: 0,
+        };
+        unsafe { ioctl(self, &mut info)? };
+        if info.sset_mask == 0 {
+            return Ok(vec![]);
+        }
+        let count = info.data as usize;
+
+        // `struct ethtool_gstrings`: command, string set and count, followed by the strings
+        let mut data = vec![0u32; 3 + count * ETH_GSTRING_LEN / mem::size_of::<u32>()];
+        data[..3].copy_from_slice(&[ETHTOOL_GSTRINGS, ETH_SS_FEATURES, count as u32]);
+        unsafe { ioctl(self, data.as_mut_slice())? };
+        let bytes: Vec<u8> = data[3..]
+            .iter()
+            .flat_map(|word| word.to_ne_bytes())
+            .collect();
+        bytes.chunks(ETH_GSTRING_LEN).map(parse_string).collect()
+    }
+
+    fn features_from_ioctl(&self) -> Result<Vec<Feature>, Error> {
+        let names = self.feature_names()?;
+
+        // `struct ethtool_gfeatures`: command and number of blocks, each holding 32 bits of the
+        // hardware, requested, active and never changeable sets
+        let blocks = (names.len() + 31) / 32;
+        let mut data = vec![0u32; 2 + 4 * blocks];
+        data[..2].copy_from_slice(&[ETHTOOL_GFEATURES, blocks as u32]);
+        unsafe { ioctl(self, data.as_mut_slice())? };
+
+        let mut features = vec![];
+        for (index, name) in names.into_iter().enumerate() {
+            let block = &data[2 + index / 32 * 4..][..4];
+            let mask = 1 << (index % 32);
+            let sets = [0, 1, 2, 3].map(|set| block[set] & mask != 0);
+            if !name.is_empty() && sets.contains(&true) {
+                features.push(Feature::new(name, sets));
+            }
+        }
+        Ok(features)
+    }
+
+    fn set_features_with_ioctl(&self, features: &[(&str, bool)]) -> Result<(), Error> {
+        let names = self.feature_names()?;
+
+        // `struct ethtool_sfeatures`: command and number of blocks, each holding 32 bits of the
+        // mask and the requested values
+        let blocks = (names.len() + 31) / 32;
+        let mut data = vec![0u32; 2 + 2 * blocks];
+        data[..2].copy_from_slice(&[ETHTOOL_SFEATURES, blocks as u32]);
+        for (name, v) in features {
+            let index = names
+                .iter()
+                .position(|known| known == name)
+                .ok_or(Error::InvalidParameter)?;
+            let block = 2 + index / 32 * 2;
+            let mask = 1 << (index % 32);
+            data[block] |= mask;
+            if *v {
+                data[block + 1] |= mask;
+            }
+        }
+        unsafe { ioctl(self, data.as_mut_slice())? };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offload_matches() {
+        assert!(Offload::TxChecksum.matches("tx-checksum-ipv4"));
+        assert!(Offload::TxChecksum.matches("tx-checksum-ip-generic"));
+        assert!(!Offload::TxChecksum.matches("rx-checksum"));
+
+        assert!(Offload::Tso.matches("tx-tcp-segmentation"));
+        assert!(Offload::Tso.matches("tx-tcp6-segmentation"));
+        assert!(Offload::Tso.matches("tx-tcp-mangleid-segmentation"));
+        assert!(!Offload::Tso.matches("tx-udp-segmentation"));
+
+        assert!(Offload::ScatterGather.matches("tx-scatter-gather"));
+        assert!(!Offload::ScatterGather.matches("tx-scatter-gather-fraglist"));
+        assert!(Offload::Gro.matches("rx-gro"));
+        assert!(!Offload::Gro.matches("rx-gro-hw"));
+    }
+
+    #[test]
+    fn bitset_round_trip() {
+        let bits = [("rx-gro", true), ("tx-scatter-gather", false)];
+        let mut buffer = vec![];
+        put_bitset(&mut buffer, ETHTOOL_A_FEATURES_WANTED, &bits);
+
+        let nla = NlasIterator::new(&buffer).next().unwrap().unwrap();
+        assert_eq!(nla.kind(), ETHTOOL_A_FEATURES_WANTED);
+        let parsed = parse_bitset(nla.value()).unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                (None, "rx-gro".to_string(), true),
+                (None, "tx-scatter-gather".to_string(), false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use super::handle::{dump_links, link_name};
+use super::stats::link_stats;
+use super::{InterfaceStats, NetnsHandle};
+use crate::Error;
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Traffic rates of a single interface, computed between two consecutive polls.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateSample {
+    pub index: u32,
+    pub name: String,
+    /// Time, elapsed since the previous poll of this interface.
+    pub elapsed: Duration,
+
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+    pub rx_packets_per_sec: f64,
+    pub tx_packets_per_sec: f64,
+    pub rx_errors_per_sec: f64,
+    pub tx_errors_per_sec: f64,
+    pub rx_dropped_per_sec: f64,
+    pub tx_dropped_per_sec: f64,
+}
+
+/// Outcome of polling an interface, that was present at the previous poll.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateEvent {
+    /// Rates since the previous poll.
+    Sample(RateSample),
+    /// Counters went backwards, e.g. because the driver was reloaded, or the index now belongs to
+    /// an interface with another name. This poll becomes the new baseline.
+    Reset { index: u32, name: String },
+    /// The interface disappeared since the previous poll.
+    Removed { index: u32, name: String },
+}
+
+#[derive(Debug, Clone)]
+enum Selection {
+    All,
+    Names(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    name: String,
+    stats: InterfaceStats,
+    at: Instant,
+}
+
+/// Periodically polls interface counters and computes traffic rates.
+///
+/// Interfaces are tracked by index, so an interface, that was removed and created again with the
+/// same name, starts from a fresh baseline instead of producing a bogus rate. Interfaces, that
+/// disappeared between polls, and counter resets are reported as [`RateEvent`]s.
+///
+/// Samples can be obtained by calling [`sample`](Self::sample) manually, by iterating over the
+/// sampler (every item is produced after sleeping for `interval`), or by [`run`](Self::run) with a
+/// callback.
+#[derive(Debug)]
+pub struct RateSampler {
+    selection: Selection,
+    netns: Option<NetnsHandle>,
+    interval: Duration,
+    next_poll: Option<Instant>,
+    previous: HashMap<u32, Snapshot>,
+}
+
+impl RateSampler {
+    /// Samples all interfaces, including the ones, that appear later.
+    pub fn all(interval: Duration) -> Self {
+        Self::new(Selection::All, interval)
+    }
+
+    /// Samples interfaces with given names.
+    pub fn for_interfaces<I, S>(names: I, interval: Duration) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::new(
+            Selection::Names(names.into_iter().map(Into::into).collect()),
+            interval,
+        )
+    }
+
+    /// Samples a single interface with given name.
+    pub fn for_interface(name: &str, interval: Duration) -> Self {
+        Self::for_interfaces([name], interval)
+    }
+
+    fn new(selection: Selection, interval: Duration) -> Self {
+        Self {
+            selection,
+            netns: None,
+            interval,
+            next_poll: None,
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Samples interfaces inside given network namespace instead of the namespace of the calling
+    /// thread.
+    pub fn with_netns(mut self, netns: NetnsHandle) -> Self {
+        self.netns = Some(netns);
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Polls counters once and returns rates since the previous poll.
+    ///
+    /// The first poll only records a baseline, so it returns no events. Interfaces, that appeared
+    /// since the previous poll, are skipped the same way.
+    pub fn sample(&mut self) -> Result<Vec<RateEvent>, Error> {
+        let links = dump_links(self.netns.as_ref())?;
+        let now = Instant::now();
+
+        let mut current = HashMap::with_capacity(links.len());
+        let mut events = vec![];
+
+        for link in &links {
+            let Some(name) = link_name(link) else {
+                continue;
+            };
+            if let Selection::Names(names) = &self.selection {
+                if !names.iter().any(|n| n == name) {
+                    continue;
+                }
+            }
+            let Some(stats) = link_stats(link)? else {
+                continue;
+            };
+
+            let index = link.header.index;
+            let snapshot = Snapshot {
+                name: name.to_string(),
+                stats,
+                at: now,
+            };
+
+            if let Some(previous) = self.previous.get(&index) {
+                events.extend(compare(index, previous, &snapshot));
+            }
+            current.insert(index, snapshot);
+        }
+
+        let mut removed: Vec<_> = self
+            .previous
+            .iter()
+            .filter(|(index, _)| !current.contains_key(index))
+            .map(|(index, snapshot)| RateEvent::Removed {
+                index: *index,
+                name: snapshot.name.clone(),
+            })
+            .collect();
+        removed.sort_by_key(|event| match event {
+            RateEvent::Removed { index, .. } => *index,
+            _ => 0,
+        });
+        events.extend(removed);
+
+        self.previous = current;
+        Ok(events)
+    }
+
+    /// Polls counters every `interval`, passing events to `callback` until it returns `false`.
+    pub fn run<F>(&mut self, mut callback: F) -> Result<(), Error>
+    where
+        F: FnMut(Vec<RateEvent>) -> bool,
+    {
+        for events in self {
+            if !callback(events?) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for RateSampler {
+    type Item = Result<Vec<RateEvent>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next_poll = match self.next_poll {
+            Some(next_poll) => next_poll,
+            None => {
+                if let Err(e) = self.sample() {
+                    return Some(Err(e));
+                }
+                Instant::now() + self.interval
+            }
+        };
+
+        thread::sleep(next_poll.saturating_duration_since(Instant::now()));
+        self.next_poll = Some(next_poll + self.interval);
+
+        Some(self.sample())
+    }
+}
+
+/// Compares two polls of the same index. Returns `None`, if no time has passed in between.
+fn compare(index: u32, previous: &Snapshot, current: &Snapshot) -> Option<RateEvent> {
+    let reset = RateEvent::Reset {
+        index,
+        name: current.name.clone(),
+    };
+    if previous.name != current.name {
+        return Some(reset);
+    }
+    let elapsed = current.at.duration_since(previous.at);
+    if elapsed.is_zero() {
+        return None;
+    }
+    Some(compute_rates(index, elapsed, previous, current).map_or(reset, RateEvent::Sample))
+}
+
+fn compute_rates(
+    index: u32,
+    elapsed: Duration,
+    previous: &Snapshot,
+    current: &Snapshot,
+) -> Option<RateSample> {
+    let seconds = elapsed.as_secs_f64();
+
+    // Any counter, that went backwards without wrapping, means that counters were reset
+    let rate =
+        |previous: u64, current: u64| Some(counter_delta(previous, current)? as f64 / seconds);
+    let (p, c) = (&previous.stats, &current.stats);
+
+    Some(RateSample {
+        index,
+        name: current.name.clone(),
+        elapsed,
+        rx_bytes_per_sec: rate(p.rx_bytes, c.rx_bytes)?,
+        tx_bytes_per_sec: rate(p.tx_bytes, c.tx_bytes)?,
+        rx_packets_per_sec: rate(p.rx_packets, c.rx_packets)?,
+        tx_packets_per_sec: rate(p.tx_packets, c.tx_packets)?,
+        rx_errors_per_sec: rate(p.rx_errors, c.rx_errors)?,
+        tx_errors_per_sec: rate(p.tx_errors, c.tx_errors)?,
+        rx_dropped_per_sec: rate(p.rx_dropped, c.rx_dropped)?,
+        tx_dropped_per_sec: rate(p.tx_dropped, c.tx_dropped)?,
+    })
+}
+
+/// Returns increment of a counter, or `None` if the counter was reset.
+///
+/// Some drivers keep 32-bit counters even in `IFLA_STATS64`, so a decrease is treated as a 32-bit
+/// wrap, if the resulting increment is plausible (less than a half of the 32-bit range).
+fn counter_delta(previous: u64, current: u64) -> Option<u64> {
+    const WRAP: u64 = 1 << 32;
+
+    if current >= previous {
+        Some(current - previous)
+    } else if previous < WRAP && current + (WRAP - previous) < WRAP / 2 {
+        Some(current + (WRAP - previous))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_increment() {
+        assert_eq!(counter_delta(100, 100), Some(0));
+        assert_eq!(counter_delta(100, 250), Some(150));
+        assert_eq!(counter_delta(1 << 40, (1 << 40) + 1), Some(1));
+    }
+
+    #[test]
+    fn counter_wrap() {
+        // 32-bit counter, wrapped around
+        assert_eq!(counter_delta(u32::MAX as u64 - 9, 5), Some(15));
+        assert_eq!(counter_delta(u32::MAX as u64, 0), Some(1));
+    }
+
+    #[test]
+    fn counter_reset() {
+        assert_eq!(counter_delta(1_000_000, 10), None);
+        // 64-bit counters don't wrap in practice
+        assert_eq!(counter_delta(1 << 40, 5), None);
+        // Too large for a plausible 32-bit wrap
+        assert_eq!(counter_delta(1 << 31, 1 << 30), None);
+    }
+
+    #[test]
+    fn reset_and_rename() {
+        let at = Instant::now();
+        let snapshot = |name: &str, rx_bytes, at| Snapshot {
+            name: name.to_string(),
+            stats: InterfaceStats {
+                rx_bytes,
+                ..Default::default()
+            },
+            at,
+        };
+        let later = at + Duration::from_secs(2);
+
+        let Some(RateEvent::Sample(sample)) =
+            compare(1, &snapshot("eth0", 100, at), &snapshot("eth0", 300, later))
+        else {
+            panic!("expected a sample");
+        };
+        assert_eq!(sample.rx_bytes_per_sec, 100.0);
+        assert_eq!(sample.elapsed, Duration::from_secs(2));
+
+        let reset = RateEvent::Reset {
+            index: 1,
+            name: "eth0".to_string(),
+        };
+        assert_eq!(
+            compare(
+                1,
+                &snapshot("eth0", 1 << 40, at),
+                &snapshot("eth0", 5, later)
+            ),
+            Some(reset.clone())
+        );
+        assert_eq!(
+            compare(1, &snapshot("eth1", 100, at), &snapshot("eth0", 300, later)),
+            Some(reset)
+        );
+        assert_eq!(
+            compare(1, &snapshot("eth0", 100, at), &snapshot("eth0", 300, at)),
+            None
+        );
+    }
+}
@@ -0,0 +1,1580 @@
+use super::handle::parse_ip;
+use super::{netlink, NetnsHandle};
+use crate::sys::InterfaceHandle;
+use crate::{Error, Interface};
+use ipnet::IpNet;
+use libc::{AF_INET, AF_INET6};
+use netlink_packet_route::nlas::{Nla, NlasIterator};
+use netlink_packet_route::route::{
+    NextHop as RtnlNextHop, NextHopBuffer, NextHopFlags, Nla as RouteNla,
+};
+use netlink_packet_route::traits::{Emitable, Parseable};
+use netlink_packet_route::{
+    RouteMessage, RtnlMessage, NLM_F_CREATE, NLM_F_DUMP, NLM_F_EXCL, NLM_F_REPLACE, RTAX_ADVMSS,
+    RTAX_CC_ALGO, RTAX_HOPLIMIT, RTAX_INITCWND, RTAX_INITRWND, RTAX_MTU, RTAX_QUICKACK, RTAX_RTT,
+    RTAX_RTTVAR, RTAX_WINDOW,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+/// `RTA_NH_ID`, not supported by netlink-packet-route.
+const RTA_NH_ID: u16 = 30;
+/// Size of `struct rtnexthop`.
+const NEXT_HOP_HEADER_LEN: usize = 8;
+
+/// IP protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    pub(crate) fn from_af(family: u8) -> Option<Self> {
+        match family as i32 {
+            AF_INET => Some(Self::V4),
+            AF_INET6 => Some(Self::V6),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn af(self) -> u8 {
+        match self {
+            Self::V4 => AF_INET as _,
+            Self::V6 => AF_INET6 as _,
+        }
+    }
+
+    /// Returns unspecified address (`0.0.0.0` or `::`) of this family.
+    pub fn unspecified(self) -> IpAddr {
+        match self {
+            Self::V4 => Ipv4Addr::UNSPECIFIED.into(),
+            Self::V6 => Ipv6Addr::UNSPECIFIED.into(),
+        }
+    }
+}
+
+impl From<IpAddr> for IpFamily {
+    fn from(address: IpAddr) -> Self {
+        match address {
+            IpAddr::V4(_) => Self::V4,
+            IpAddr::V6(_) => Self::V6,
+        }
+    }
+}
+
+impl From<IpNet> for IpFamily {
+    fn from(network: IpNet) -> Self {
+        network.addr().into()
+    }
+}
+
+u8_enum! {
+    /// Origin of a route (`rtm_protocol`).
+    pub enum RouteProtocol {
+        Unspec = 0,
+        /// Route installed by ICMP redirects.
+        Redirect = 1,
+        /// Route installed by the kernel.
+        Kernel = 2,
+        /// Route installed during boot.
+        Boot = 3,
+        /// Route installed by the administrator.
+        Static = 4,
+        Gated = 8,
+        /// Route installed by IPv6 router advertisements.
+        Ra = 9,
+        Mrt = 10,
+        Zebra = 11,
+        Bird = 12,
+        Dnrouted = 13,
+        Xorp = 14,
+        Ntk = 15,
+        Dhcp = 16,
+        Mrouted = 17,
+        Keepalived = 18,
+        Babel = 42,
+        Openr = 99,
+        Bgp = 186,
+        Isis = 187,
+        Ospf = 188,
+        Rip = 189,
+        Eigrp = 192,
+    }
+}
+
+u8_enum! {
+    /// Distance to the destination (`rtm_scope`).
+    pub enum RouteScope {
+        Universe = 0,
+        Site = 200,
+        Link = 253,
+        Host = 254,
+        Nowhere = 255,
+    }
+}
+
+u8_enum! {
+    /// Type of a route (`rtm_type`).
+    pub enum RouteType {
+        Unspec = 0,
+        /// Gateway or direct route.
+        Unicast = 1,
+        /// Local interface route.
+        Local = 2,
+        /// Local broadcast route, sent as broadcast.
+        Broadcast = 3,
+        /// Local broadcast route, sent as unicast.
+        Anycast = 4,
+        Multicast = 5,
+        /// Packets are silently dropped.
+        Blackhole = 6,
+        /// Destination is unreachable, ICMP "host unreachable" is generated.
+        Unreachable = 7,
+        /// Administratively prohibited, ICMP "communication prohibited" is generated.
+        Prohibit = 8,
+        /// Lookup continues in the next routing table.
+        Throw = 9,
+        Nat = 10,
+        Xresolve = 11,
+    }
+}
+
+/// Entry of a kernel routing table.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub destination: IpNet,
+    pub gateway: Option<IpAddr>,
+    /// Output interface.
+    pub interface: Option<Interface>,
+    /// Preferred source address (`RTA_PREFSRC`).
+    pub source: Option<IpAddr>,
+    /// Route priority (`RTA_PRIORITY`). Lower values are preferred.
+    pub metric: Option<u32>,
+    /// Type of service (`rtm_tos`). Routes, that differ only in it, are distinct.
+    pub tos: u8,
+    pub table: u32,
+    pub protocol: RouteProtocol,
+    pub scope: RouteScope,
+    pub route_type: RouteType,
+    /// Next hops of a multipath route (`RTA_MULTIPATH`). Empty for single-path routes.
+    pub next_hops: Vec<NextHop>,
+    /// Id of the nexthop object, used by the route.
+    pub nexthop_id: Option<u32>,
+    pub metrics: RouteMetrics,
+}
+
+impl Route {
+    /// Local routing table (`RT_TABLE_LOCAL`).
+    pub const TABLE_LOCAL: u32 = 255;
+    /// Main routing table (`RT_TABLE_MAIN`), used by default.
+    pub const TABLE_MAIN: u32 = 254;
+    /// Default routing table (`RT_TABLE_DEFAULT`).
+    pub const TABLE_DEFAULT: u32 = 253;
+
+    pub(crate) fn from_message(
+        message: RouteMessage,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Option<Self>, Error> {
+        let header = &message.header;
+        let Some(family) = IpFamily::from_af(header.address_family) else {
+            return Ok(None);
+        };
+
+        let mut route = Route {
+            destination: IpNet::new(family.unspecified(), header.destination_prefix_length)
+                .map_err(|_| Error::UnexpectedMetadata)?,
+            gateway: None,
+            interface: None,
+            source: None,
+            metric: None,
+            tos: header.tos,
+            table: header.table as _,
+            protocol: header.protocol.into(),
+            scope: header.scope.into(),
+            route_type: header.kind.into(),
+            next_hops: vec![],
+            nexthop_id: None,
+            metrics: RouteMetrics::default(),
+        };
+
+        for nla in message.nlas {
+            match nla {
+                RouteNla::Destination(bytes) => {
+                    let address = parse_ip(&bytes).ok_or(Error::UnexpectedMetadata)?;
+                    route.destination = IpNet::new(address, header.destination_prefix_length)
+                        .map_err(|_| Error::UnexpectedMetadata)?;
+                }
+                RouteNla::Gateway(bytes) => route.gateway = parse_ip(&bytes),
+                RouteNla::PrefSource(bytes) => route.source = parse_ip(&bytes),
+                RouteNla::Oif(index) => {
+                    route.interface = Some(Interface(InterfaceHandle {
+                        index,
+                        netns: netns.cloned(),
+                    }))
+                }
+                RouteNla::Priority(metric) => route.metric = Some(metric),
+                // RTA_TABLE carries table ids, that do not fit into rtm_table
+                RouteNla::Table(table) => route.table = table,
+                RouteNla::MultiPath(bytes) => route.next_hops = parse_next_hops(&bytes, netns)?,
+                RouteNla::Metrics(bytes) => route.metrics = RouteMetrics::parse(&bytes)?,
+                RouteNla::Other(nla) if nla.kind() == RTA_NH_ID => {
+                    let mut value = [0; 4];
+                    if nla.value_len() != value.len() {
+                        return Err(Error::UnexpectedMetadata);
+                    }
+                    nla.emit_value(&mut value);
+                    route.nexthop_id = Some(u32::from_ne_bytes(value));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Some(route))
+    }
+}
+
+/// Next hop of a multipath route (`struct rtnexthop`).
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct NextHop {
+    pub gateway: Option<IpAddr>,
+    pub interface: Option<Interface>,
+    /// Relative share of traffic, sent via this next hop, from 1 to 256.
+    pub weight: u16,
+    /// Gateway is directly reachable through the interface, even if no prefix covers it.
+    pub onlink: bool,
+    /// Next hop is unusable, e.g. because its interface is down. Ignored when a route is created.
+    pub dead: bool,
+    /// Interface of the next hop has no carrier. Ignored when a route is created.
+    pub link_down: bool,
+}
+
+impl NextHop {
+    /// Creates a next hop with weight 1.
+    pub fn new() -> Self {
+        Self {
+            gateway: None,
+            interface: None,
+            weight: 1,
+            onlink: false,
+            dead: false,
+            link_down: false,
+        }
+    }
+
+    pub fn gateway(mut self, gateway: IpAddr) -> Self {
+        self.gateway = Some(gateway);
+        self
+    }
+
+    pub fn interface(mut self, interface: &Interface) -> Self {
+        self.interface = Some(interface.clone());
+        self
+    }
+
+    /// Sets weight, clamped to the range from 1 to 256.
+    pub fn weight(mut self, weight: u16) -> Self {
+        self.weight = weight.clamp(1, 256);
+        self
+    }
+
+    pub fn onlink(mut self, onlink: bool) -> Self {
+        self.onlink = onlink;
+        self
+    }
+}
+
+impl Default for NextHop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_next_hops(bytes: &[u8], netns: Option<&NetnsHandle>) -> Result<Vec<NextHop>, Error> {
+    let mut next_hops = vec![];
+    let mut rest = bytes;
+
+    while !rest.is_empty() {
+        let buffer = NextHopBuffer::new_checked(&rest)?;
+        let length = buffer.length() as usize;
+        if length < NEXT_HOP_HEADER_LEN {
+            return Err(Error::UnexpectedMetadata);
+        }
+        let hop = RtnlNextHop::parse(&buffer)?;
+
+        let mut next_hop = NextHop {
+            // rtnh_hops is weight minus one
+            weight: hop.hops as u16 + 1,
+            onlink: hop.flags.contains(NextHopFlags::RTNH_F_ONLINK),
+            dead: hop.flags.contains(NextHopFlags::RTNH_F_DEAD),
+            link_down: hop.flags.contains(NextHopFlags::RTNH_F_LINKDOWN),
+            ..NextHop::new()
+        };
+        if hop.interface_id != 0 {
+            next_hop.interface = Some(Interface(InterfaceHandle {
+                index: hop.interface_id,
+                netns: netns.cloned(),
+            }));
+        }
+        for nla in hop.nlas {
+            match nla {
+                RouteNla::Gateway(bytes) => next_hop.gateway = parse_ip(&bytes),
+                // struct rtvia: address family, followed by the address, used by IPv4 routes with
+                // IPv6 gateways
+                RouteNla::Via(bytes) if bytes.len() > 2 => next_hop.gateway = parse_ip(&bytes[2..]),
+                _ => {}
+            }
+        }
+        next_hops.push(next_hop);
+
+        rest = rest.get(netlink::align(length)..).unwrap_or_default();
+    }
+    Ok(next_hops)
+}
+
+fn next_hops_to_vec(next_hops: &[NextHop]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for next_hop in next_hops {
+        let mut flags = NextHopFlags::empty();
+        flags.set(NextHopFlags::RTNH_F_ONLINK, next_hop.onlink);
+
+        let hop = RtnlNextHop {
+            flags,
+            hops: (next_hop.weight.clamp(1, 256) - 1) as u8,
+            interface_id: next_hop.interface.as_ref().map_or(0, |iface| iface.0.index),
+            nlas: next_hop
+                .gateway
+                .map(|gateway| RouteNla::Gateway(ip_to_vec(gateway)))
+                .into_iter()
+                .collect(),
+        };
+
+        let offset = bytes.len();
+        bytes.resize(offset + netlink::align(hop.buffer_len()), 0);
+        hop.emit(&mut bytes[offset..]);
+    }
+    bytes
+}
+
+/// Per-route transport parameters (`RTA_METRICS`). Unset values are inherited from the system
+/// defaults.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteMetrics {
+    pub mtu: Option<u32>,
+    /// Maximum TCP segment size, advertised to the peer.
+    pub advmss: Option<u32>,
+    /// Maximum TCP window, advertised to the peer.
+    pub window: Option<u32>,
+    /// Initial round trip time estimate.
+    pub rtt: Option<Duration>,
+    /// Initial round trip time variance estimate.
+    pub rttvar: Option<Duration>,
+    /// Initial TCP congestion window, in segments.
+    pub initcwnd: Option<u32>,
+    /// Initial TCP receive window, in segments.
+    pub initrwnd: Option<u32>,
+    pub hoplimit: Option<u32>,
+    /// Disables delayed TCP acknowledgements.
+    pub quickack: Option<bool>,
+    /// TCP congestion control algorithm, e.g. `bbr`.
+    pub congestion_control: Option<String>,
+}
+
+impl RouteMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mtu(mut self, mtu: u32) -> Self {
+        self.mtu = Some(mtu);
+        self
+    }
+
+    pub fn advmss(mut self, advmss: u32) -> Self {
+        self.advmss = Some(advmss);
+        self
+    }
+
+    pub fn window(mut self, window: u32) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    pub fn rtt(mut self, rtt: Duration) -> Self {
+        self.rtt = Some(rtt);
+        self
+    }
+
+    pub fn rttvar(mut self, rttvar: Duration) -> Self {
+        self.rttvar = Some(rttvar);
+        self
+    }
+
+    pub fn initcwnd(mut self, initcwnd: u32) -> Self {
+        self.initcwnd = Some(initcwnd);
+        self
+    }
+
+    pub fn initrwnd(mut self, initrwnd: u32) -> Self {
+        self.initrwnd = Some(initrwnd);
+        self
+    }
+
+    pub fn hoplimit(mut self, hoplimit: u32) -> Self {
+        self.hoplimit = Some(hoplimit);
+        self
+    }
+
+    pub fn quickack(mut self, quickack: bool) -> Self {
+        self.quickack = Some(quickack);
+        self
+    }
+
+    pub fn congestion_control(mut self, algorithm: &str) -> Self {
+        self.congestion_control = Some(algorithm.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let mut metrics = Self::default();
+
+        for nla in NlasIterator::new(bytes) {
+            let nla = nla?;
+            let value = nla.value();
+            if nla.kind() == RTAX_CC_ALGO {
+                let name = value.split(|&b| b == 0).next().unwrap_or_default();
+                metrics.congestion_control = Some(String::from_utf8_lossy(name).into_owned());
+                continue;
+            }
+
+            let value =
+                u32::from_ne_bytes(value.try_into().map_err(|_| Error::UnexpectedMetadata)?);
+            match nla.kind() {
+                RTAX_MTU => metrics.mtu = Some(value),
+                RTAX_ADVMSS => metrics.advmss = Some(value),
+                RTAX_WINDOW => metrics.window = Some(value),
+                // RTT is stored in 1/8 of a millisecond
+                RTAX_RTT => metrics.rtt = Some(Duration::from_micros(value as u64 * 125)),
+                // RTT variance is stored in 1/4 of a millisecond
+                RTAX_RTTVAR => metrics.rttvar = Some(Duration::from_micros(value as u64 * 250)),
+                RTAX_INITCWND => metrics.initcwnd = Some(value),
+                RTAX_INITRWND => metrics.initrwnd = Some(value),
+                RTAX_HOPLIMIT => metrics.hoplimit = Some(value),
+                RTAX_QUICKACK => metrics.quickack = Some(value != 0),
+                _ => {}
+            }
+        }
+        Ok(metrics)
+    }
+
+    fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut put = |kind: u16, value: Option<u32>| {
+            if let Some(value) = value {
+                netlink::put_attribute(&mut bytes, kind, &value.to_ne_bytes());
+            }
+        };
+
+        put(RTAX_MTU, self.mtu);
+        put(RTAX_ADVMSS, self.advmss);
+        put(RTAX_WINDOW, self.window);
+        put(
+            RTAX_RTT,
+            self.rtt
+                .map(|rtt| u32::try_from(rtt.as_micros() / 125).unwrap_or(u32::MAX)),
+        );
+        put(
+            RTAX_RTTVAR,
+            self.rttvar
+                .map(|rttvar| u32::try_from(rttvar.as_micros() / 250).unwrap_or(u32::MAX)),
+        );
+        put(RTAX_INITCWND, self.initcwnd);
+        put(RTAX_INITRWND, self.initrwnd);
+        put(RTAX_HOPLIMIT, self.hoplimit);
+        put(RTAX_QUICKACK, self.quickack.map(u32::from));
+
+        if let Some(algorithm) = &self.congestion_control {
+            let mut name = algorithm.as_bytes().to_vec();
+            name.push(0);
+            netlink::put_attribute(&mut bytes, RTAX_CC_ALGO, &name);
+        }
+        bytes
+    }
+}
+
+/// Parameters of a route to add, replace or delete.
+///
+/// Unset protocol and scope are chosen like `ip route` does when a route is created, and are not
+/// matched when a route is deleted.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct RouteSpec {
+    pub destination: IpNet,
+    pub gateway: Option<IpAddr>,
+    /// Index of the output interface.
+    pub interface: Option<u32>,
+    /// Preferred source address (`RTA_PREFSRC`).
+    pub source: Option<IpAddr>,
+    pub metric: Option<u32>,
+    /// Type of service (`rtm_tos`).
+    pub tos: u8,
+    pub table: u32,
+    pub protocol: Option<RouteProtocol>,
+    pub scope: Option<RouteScope>,
+    pub route_type: RouteType,
+    /// Next hops of a multipath route. If set, `gateway` and `interface` are usually left unset.
+    pub next_hops: Vec<NextHop>,
+    /// Id of a nexthop object, used instead of `gateway`, `interface` and `next_hops`.
+    pub nexthop_id: Option<u32>,
+    pub metrics: RouteMetrics,
+}
+
+impl RouteSpec {
+    /// Creates a unicast route to `destination` in the main table.
+    pub fn new(destination: IpNet) -> Self {
+        Self {
+            destination,
+            gateway: None,
+            interface: None,
+            source: None,
+            metric: None,
+            tos: 0,
+            table: Route::TABLE_MAIN,
+            protocol: None,
+            scope: None,
+            route_type: RouteType::Unicast,
+            next_hops: vec![],
+            nexthop_id: None,
+            metrics: RouteMetrics::default(),
+        }
+    }
+
+    pub fn gateway(mut self, gateway: IpAddr) -> Self {
+        self.gateway = Some(gateway);
+        self
+    }
+
+    pub fn interface(mut self, interface: &Interface) -> Self {
+        self.interface = Some(interface.0.index);
+        self
+    }
+
+    pub fn source(mut self, source: IpAddr) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn metric(mut self, metric: u32) -> Self {
+        self.metric = Some(metric);
+        self
+    }
+
+    pub fn tos(mut self, tos: u8) -> Self {
+        self.tos = tos;
+        self
+    }
+
+    pub fn table(mut self, table: u32) -> Self {
+        self.table = table;
+        self
+    }
+
+    pub fn protocol(mut self, protocol: RouteProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    pub fn scope(mut self, scope: RouteScope) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    pub fn route_type(mut self, route_type: RouteType) -> Self {
+        self.route_type = route_type;
+        self
+    }
+
+    /// Makes the route use a nexthop object (see [`Nexthop`](super::Nexthop)).
+    pub fn nexthop_id(mut self, id: u32) -> Self {
+        self.nexthop_id = Some(id);
+        self
+    }
+
+    pub fn metrics(mut self, metrics: RouteMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Adds a next hop, turning the route into a multipath one.
+    pub fn next_hop(mut self, next_hop: NextHop) -> Self {
+        self.next_hops.push(next_hop);
+        self
+    }
+
+    fn default_scope(&self) -> RouteScope {
+        match self.route_type {
+            RouteType::Local | RouteType::Nat => RouteScope::Host,
+            RouteType::Unicast | RouteType::Unspec
+                if self.gateway.is_none()
+                    && self.nexthop_id.is_none()
+                    && self.next_hops.iter().all(|hop| hop.gateway.is_none()) =>
+            {
+                RouteScope::Link
+            }
+            _ => RouteScope::Universe,
+        }
+    }
+
+    /// Fails with `InvalidParameter`, if a nexthop object is combined with a gateway, an interface
+    /// or next hops, which the kernel rejects.
+    pub(crate) fn to_message(&self, create: bool) -> Result<RouteMessage, Error> {
+        if self.nexthop_id.is_some()
+            && (self.gateway.is_some() || self.interface.is_some() || !self.next_hops.is_empty())
+        {
+            return Err(Error::InvalidParameter);
+        }
+
+        let mut message = RouteMessage::default();
+        let header = &mut message.header;
+
+        header.address_family = IpFamily::from(self.destination).af();
+        header.destination_prefix_length = self.destination.prefix_len();
+        header.kind = self.route_type.into();
+        header.tos = self.tos;
+        // rtm_table is 8 bits wide, larger ids are passed in RTA_TABLE only
+        header.table = u8::try_from(self.table).unwrap_or(0);
+
+        if create {
+            header.protocol = self.protocol.unwrap_or(RouteProtocol::Static).into();
+            header.scope = self.scope.unwrap_or_else(|| self.default_scope()).into();
+        } else {
+            header.protocol = self.protocol.map_or(0, Into::into);
+            header.scope = self.scope.unwrap_or(RouteScope::Nowhere).into();
+        }
+
+        if self.destination.prefix_len() > 0 {
+            message
+                .nlas
+                .push(RouteNla::Destination(ip_to_vec(self.destination.addr())));
+        }
+        if let Some(gateway) = self.gateway {
+            message.nlas.push(RouteNla::Gateway(ip_to_vec(gateway)));
+        }
+        if let Some(index) = self.interface {
+            message.nlas.push(RouteNla::Oif(index));
+        }
+        if let Some(source) = self.source {
+            message.nlas.push(RouteNla::PrefSource(ip_to_vec(source)));
+        }
+        if let Some(metric) = self.metric {
+            message.nlas.push(RouteNla::Priority(metric));
+        }
+        if !self.next_hops.is_empty() {
+            message
+                .nlas
+                .push(RouteNla::MultiPath(next_hops_to_vec(&self.next_hops)));
+        }
+        if let Some(id) = self.nexthop_id {
+            message.nlas.push(RouteNla::Other(netlink::default_nla(
+                RTA_NH_ID,
+                &id.to_ne_bytes(),
+            )));
+        }
+        if !self.metrics.is_empty() {
+            message.nlas.push(RouteNla::Metrics(self.metrics.to_vec()));
+        }
+        message.nlas.push(RouteNla::Table(self.table));
+
+        Ok(message)
+    }
+}
+
+impl From<&Route> for RouteSpec {
+    fn from(route: &Route) -> Self {
+        let mut spec = Self {
+            destination: route.destination,
+            gateway: route.gateway,
+            interface: route.interface.as_ref().map(|iface| iface.0.index),
+            source: route.source,
+            metric: route.metric,
+            tos: route.tos,
+            table: route.table,
+            protocol: Some(route.protocol),
+            scope: Some(route.scope),
+            route_type: route.route_type,
+            next_hops: route.next_hops.clone(),
+            nexthop_id: route.nexthop_id,
+            metrics: route.metrics.clone(),
+        };
+        // Routes using a nexthop object are reported along with the gateway and interface of the
+        // nexthop, but must be addressed by its id alone
+        if spec.nexthop_id.is_some() {
+            spec.gateway = None;
+            spec.interface = None;
+            spec.next_hops.clear();
+        }
+        spec
+    }
+}
+
+/// Criteria for [`list_routes`]. Every unset field matches all routes.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+pub struct RouteFilter {
+    pub family: Option<IpFamily>,
+    pub table: Option<u32>,
+    /// Index of the output interface.
+    pub interface: Option<u32>,
+}
+
+impl RouteFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn family(mut self, family: IpFamily) -> Self {
+        self.family = Some(family);
+        self
+    }
+
+    pub fn table(mut self, table: u32) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    pub fn interface(mut self, interface: &Interface) -> Self {
+        self.interface = Some(interface.0.index);
+        self
+    }
+
+    /// Returns the dump request for this filter.
+    pub(crate) fn to_message(&self) -> RtnlMessage {
+        let mut message = RouteMessage::default();
+        if let Some(family) = self.family {
+            message.header.address_family = family.af();
+        }
+        RtnlMessage::GetRoute(message)
+    }
+
+    /// Returns routes from the dump, matching this filter.
+    pub(crate) fn collect(
+        &self,
+        responses: Vec<RtnlMessage>,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Vec<Route>, Error> {
+        let mut routes = vec![];
+        for response in responses {
+            let RtnlMessage::NewRoute(message) = response else {
+                continue;
+            };
+            if let Some(route) = Route::from_message(message, netns)? {
+                if self.matches(&route) {
+                    routes.push(route);
+                }
+            }
+        }
+        Ok(routes)
+    }
+
+    fn matches(&self, route: &Route) -> bool {
+        let family_matches = self
+            .family
+            .map_or(true, |family| IpFamily::from(route.destination) == family);
+        let table_matches = self.table.map_or(true, |table| route.table == table);
+        let interface_matches = self.interface.map_or(true, |index| {
+            route.interface.as_ref().map(|iface| iface.0.index) == Some(index)
+        });
+
+        family_matches && table_matches && interface_matches
+    }
+}
+
+/// Optional parameters of a [`route_get`] lookup.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+pub struct RouteGetOptions {
+    pub source: Option<IpAddr>,
+    /// Index of the interface, the packet is received from. Turns the lookup into a forwarding
+    /// lookup, which usually requires `source` to be set as well.
+    pub input_interface: Option<u32>,
+    /// Firewall mark of the packet (`SO_MARK`).
+    pub mark: Option<u32>,
+    /// Owner of the socket, sending the packet, matched by `uidrange` policy rules.
+    pub uid: Option<u32>,
+}
+
+impl RouteGetOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn source(mut self, source: IpAddr) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn input_interface(mut self, interface: &Interface) -> Self {
+        self.input_interface = Some(interface.0.index);
+        self
+    }
+
+    pub fn mark(mut self, mark: u32) -> Self {
+        self.mark = Some(mark);
+        self
+    }
+
+    pub fn uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    fn to_message(&self, destination: IpAddr) -> RouteMessage {
+        let mut message = RouteMessage::default();
+        let header = &mut message.header;
+
+        header.address_family = IpFamily::from(destination).af();
+        header.destination_prefix_length = host_prefix_len(destination);
+        message
+            .nlas
+            .push(RouteNla::Destination(ip_to_vec(destination)));
+
+        if let Some(source) = self.source {
+            message.header.source_prefix_length = host_prefix_len(source);
+            message.nlas.push(RouteNla::Source(ip_to_vec(source)));
+        }
+        if let Some(index) = self.input_interface {
+            message.nlas.push(RouteNla::Iif(index));
+        }
+        if let Some(mark) = self.mark {
+            message.nlas.push(RouteNla::Mark(mark));
+        }
+        if let Some(uid) = self.uid {
+            message.nlas.push(RouteNla::Uid(uid.to_ne_bytes().to_vec()));
+        }
+
+        message
+    }
+}
+
+fn host_prefix_len(address: IpAddr) -> u8 {
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// Returns routes from all routing tables, matching given filter.
+pub fn list_routes(filter: &RouteFilter) -> Result<Vec<Route>, Error> {
+    list_routes_in(None, filter)
+}
+
+pub(crate) fn list_routes_in(
+    netns: Option<&NetnsHandle>,
+    filter: &RouteFilter,
+) -> Result<Vec<Route>, Error> {
+    let socket = netlink::socket(netns)?;
+    let responses = netlink::request(&socket, filter.to_message(), NLM_F_DUMP)?;
+    filter.collect(responses, netns)
+}
+
+/// Asks the kernel, which route would be used to reach `destination`, like `ip route get` does.
+///
+/// The result describes the resolved path rather than a routing table entry: `interface` is the
+/// output interface, and `source` is the address, that would be selected for outgoing packets.
+pub fn route_get(destination: IpAddr, options: &RouteGetOptions) -> Result<Route, Error> {
+    route_get_in(None, destination, options)
+}
+
+pub(crate) fn route_get_in(
+    netns: Option<&NetnsHandle>,
+    destination: IpAddr,
+    options: &RouteGetOptions,
+) -> Result<Route, Error> {
+    let message = RtnlMessage::GetRoute(options.to_message(destination));
+
+    let socket = netlink::socket(netns)?;
+    for response in netlink::request(&socket, message, 0)? {
+        if let RtnlMessage::NewRoute(message) = response {
+            if let Some(route) = Route::from_message(message, netns)? {
+                return Ok(route);
+            }
+        }
+    }
+    Err(Error::UnexpectedMetadata)
+}
+
+/// Adds a new route. Fails, if the same route already exists.
+pub fn add_route(spec: &RouteSpec) -> Result<(), Error> {
+    modify_route_in(None, spec, RouteOperation::Add)
+}
+
+/// Adds a new route or replaces the existing one with the same destination, table and metric.
+pub fn replace_route(spec: &RouteSpec) -> Result<(), Error> {
+    modify_route_in(None, spec, RouteOperation::Replace)
+}
+
+/// Deletes the first route, matching given spec.
+pub fn delete_route(spec: &RouteSpec) -> Result<(), Error> {
+    modify_route_in(None, spec, RouteOperation::Delete)
+}
+
+/// Returns IPv4 and IPv6 default routes from the main table, ordered by family and metric.
+pub fn default_routes() -> Result<Vec<Route>, Error> {
+    default_routes_in(None)
+}
+
+/// Makes `gateway` the default gateway for its address family.
+///
+/// The preferred default route, the one with the lowest metric in the main table, is replaced in
+/// place, so the host is never left without a default route. Other routes with the same metric are
+/// removed. Default routes with higher metrics, e.g. backup routes over other links, are left
+/// untouched. The namespace of `interface` is used.
+pub fn set_default_gateway(gateway: IpAddr, interface: &Interface) -> Result<(), Error> {
+    let netns = interface.0.netns.as_ref();
+    let family = IpFamily::from(gateway);
+
+    let existing: Vec<Route> = default_routes_in(netns)?
+        .into_iter()
+        .filter(|route| IpFamily::from(route.destination) == family)
+        .collect();
+    // The same metric is required to replace the preferred route instead of adding a new one
+    let metric = existing.first().and_then(|route| route.metric);
+
+    let mut spec = RouteSpec::new(IpNet::new(family.unspecified(), 0).unwrap())
+        .gateway(gateway)
+        .interface(interface);
+    if let Some(metric) = metric {
+        spec = spec.metric(metric);
+    }
+    modify_route_in(netns, &spec, RouteOperation::Replace)?;
+
+    let index = interface.0.index;
+    for route in existing.iter().skip(1) {
+        let is_new = route.gateway == Some(gateway)
+            && route.interface.as_ref().map(|iface| iface.0.index) == Some(index);
+        if route.metric != metric || is_new {
+            continue;
+        }
+        match modify_route_in(netns, &route.into(), RouteOperation::Delete) {
+            // Route may be gone already, e.g. replaced by the previous step
+            Ok(()) => {}
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn default_routes_in(netns: Option<&NetnsHandle>) -> Result<Vec<Route>, Error> {
+    let filter = RouteFilter::new().table(Route::TABLE_MAIN);
+
+    let mut routes: Vec<Route> = list_routes_in(netns, &filter)?
+        .into_iter()
+        .filter(|route| {
+            route.destination.prefix_len() == 0 && route.route_type == RouteType::Unicast
+        })
+        .collect();
+    routes.sort_by_key(|route| {
+        (
+            IpFamily::from(route.destination) == IpFamily::V6,
+            route.metric.unwrap_or(0),
+        )
+    });
+    Ok(routes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RouteOperation {
+    Add,
+    Replace,
+    Delete,
+}
+
+impl RouteOperation {
+    /// Returns the request and its flags, performing this operation.
+    pub(crate) fn request(self, spec: &RouteSpec) -> Result<(RtnlMessage, u16), Error> {
+        Ok(match self {
+            RouteOperation::Add => (
+                RtnlMessage::NewRoute(spec.to_message(true)?),
+                NLM_F_CREATE | NLM_F_EXCL,
+            ),
+            RouteOperation::Replace => (
+                RtnlMessage::NewRoute(spec.to_message(true)?),
+                NLM_F_CREATE | NLM_F_REPLACE,
+            ),
+            RouteOperation::Delete => (RtnlMessage::DelRoute(spec.to_message(false)?), 0),
+        })
+    }
+}
+
+pub(crate) fn modify_route_in(
+    netns: Option<&NetnsHandle>,
+    spec: &RouteSpec,
+    operation: RouteOperation,
+) -> Result<(), Error> {
+    let (message, flags) = operation.request(spec)?;
+    let socket = netlink::socket(netns)?;
+    netlink::request(&socket, message, flags)?;
+    Ok(())
+}
+
+pub(crate) fn ip_to_vec(address: IpAddr) -> Vec<u8> {
+    match address {
+        IpAddr::V4(address) => address.octets().to_vec(),
+        IpAddr::V6(address) => address.octets().to_vec(),
+    }
+}
+
+impl NetnsHandle {
+    /// Returns routes inside this namespace, matching given filter.
+    pub fn list_routes(&self, filter: &RouteFilter) -> Result<Vec<Route>, Error> {
+        list_routes_in(Some(self), filter)
+    }
+
+    /// Returns IPv4 and IPv6 default routes inside this namespace, ordered by family and metric.
+    pub fn default_routes(&self) -> Result<Vec<Route>, Error> {
+        default_routes_in(Some(self))
+    }
+
+    /// Asks the kernel, which route would be used inside this namespace to reach `destination`.
+    pub fn route_get(
+        &self,
+        destination: IpAddr,
+        options: &RouteGetOptions,
+    ) -> Result<Route, Error> {
+        route_get_in(Some(self), destination, options)
+    }
+
+    pub fn add_route(&self, spec: &RouteSpec) -> Result<(), Error> {
+        modify_route_in(Some(self), spec, RouteOperation::Add)
+    }
+
+    pub fn replace_route(&self, spec: &RouteSpec) -> Result<(), Error> {
+        modify_route_in(Some(self), spec, RouteOperation::Replace)
+    }
+
+    pub fn delete_route(&self, spec: &RouteSpec) -> Result<(), Error> {
+        modify_route_in(Some(self), spec, RouteOperation::Delete)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netlink_packet_route::{LinkMessage, RTAX_FASTOPEN_NO_COOKIE};
+
+    fn route_message(
+        family: i32,
+        prefix_length: u8,
+        table: u8,
+        nlas: Vec<RouteNla>,
+    ) -> RouteMessage {
+        let mut message = RouteMessage::default();
+        message.header.address_family = family as u8;
+        message.header.destination_prefix_length = prefix_length;
+        message.header.table = table;
+        message.header.protocol = RouteProtocol::Static.into();
+        message.header.kind = RouteType::Unicast.into();
+        message.nlas = nlas;
+        message
+    }
+
+    /// Parses a route, as if it was sent by the kernel.
+    fn parse(message: RouteMessage) -> Option<Route> {
+        let RtnlMessage::NewRoute(message) = netlink::reparse(RtnlMessage::NewRoute(message))
+        else {
+            panic!("expected a route");
+        };
+        Route::from_message(message, None).unwrap()
+    }
+
+    #[test]
+    fn route_from_new_route() {
+        let mut message = route_message(
+            AF_INET,
+            24,
+            Route::TABLE_MAIN as u8,
+            vec![
+                RouteNla::Destination(vec![10, 1, 2, 0]),
+                RouteNla::Gateway(vec![192, 168, 0, 1]),
+                RouteNla::PrefSource(vec![192, 168, 0, 2]),
+                RouteNla::Oif(3),
+                RouteNla::Priority(100),
+                RouteNla::Other(netlink::default_nla(RTA_NH_ID, &7u32.to_ne_bytes())),
+            ],
+        );
+        message.header.tos = 0x10;
+        message.header.scope = RouteScope::Universe.into();
+
+        let route = parse(message).unwrap();
+        assert_eq!(route.destination, "10.1.2.0/24".parse().unwrap());
+        assert_eq!(route.gateway, Some("192.168.0.1".parse().unwrap()));
+        assert_eq!(route.source, Some("192.168.0.2".parse().unwrap()));
+        assert_eq!(route.interface.map(|iface| iface.0.index), Some(3));
+        assert_eq!(route.metric, Some(100));
+        assert_eq!(route.tos, 0x10);
+        assert_eq!(route.table, Route::TABLE_MAIN);
+        assert_eq!(route.protocol, RouteProtocol::Static);
+        assert_eq!(route.scope, RouteScope::Universe);
+        assert_eq!(route.route_type, RouteType::Unicast);
+        assert_eq!(route.nexthop_id, Some(7));
+        assert!(route.next_hops.is_empty());
+        assert!(route.metrics.is_empty());
+    }
+
+    #[test]
+    fn default_route_without_destination() {
+        let route = parse(route_message(AF_INET6, 0, Route::TABLE_MAIN as u8, vec![])).unwrap();
+        assert_eq!(route.destination, "::/0".parse().unwrap());
+        assert_eq!(route.gateway, None);
+        assert_eq!(route.interface.map(|iface| iface.0.index), None);
+    }
+
+    #[test]
+    fn table_from_attribute() {
+        // Tables above 255 are reported as RT_TABLE_COMPAT in the header
+        let message = route_message(AF_INET, 0, 252, vec![RouteNla::Table(1000)]);
+        assert_eq!(parse(message).unwrap().table, 1000);
+
+        let message = route_message(AF_INET, 0, 100, vec![]);
+        assert_eq!(parse(message).unwrap().table, 100);
+    }
+
+    #[test]
+    fn other_families_are_skipped() {
+        let message = route_message(libc::AF_MPLS, 20, Route::TABLE_MAIN as u8, vec![]);
+        assert!(parse(message).is_none());
+    }
+
+    #[test]
+    fn malformed_route() {
+        let message = route_message(AF_INET, 33, Route::TABLE_MAIN as u8, vec![]);
+        assert!(Route::from_message(message, None).is_err());
+
+        let message = route_message(
+            AF_INET,
+            24,
+            Route::TABLE_MAIN as u8,
+            vec![RouteNla::Destination(vec![10, 1, 2])],
+        );
+        assert!(Route::from_message(message, None).is_err());
+    }
+
+    #[test]
+    fn filter_collect() {
+        let responses = || {
+            vec![
+                RtnlMessage::NewRoute(route_message(
+                    AF_INET,
+                    0,
+                    Route::TABLE_MAIN as u8,
+                    vec![RouteNla::Oif(2)],
+                )),
+                RtnlMessage::NewRoute(route_message(
+                    AF_INET6,
+                    0,
+                    Route::TABLE_MAIN as u8,
+                    vec![RouteNla::Oif(3)],
+                )),
+                RtnlMessage::NewRoute(route_message(
+                    AF_INET,
+                    8,
+                    252,
+                    vec![
+                        RouteNla::Destination(vec![10, 0, 0, 0]),
+                        RouteNla::Table(1000),
+                    ],
+                )),
+                RtnlMessage::NewRoute(route_message(libc::AF_MPLS, 20, 0, vec![])),
+                RtnlMessage::NewLink(LinkMessage::default()),
+            ]
+        };
+        let collect = |filter: RouteFilter| -> Vec<(IpNet, u32)> {
+            filter
+                .collect(responses(), None)
+                .unwrap()
+                .into_iter()
+                .map(|route| (route.destination, route.table))
+                .collect()
+        };
+
+        let v4_default = ("0.0.0.0/0".parse().unwrap(), Route::TABLE_MAIN);
+        let v6_default = ("::/0".parse().unwrap(), Route::TABLE_MAIN);
+        let custom = ("10.0.0.0/8".parse().unwrap(), 1000);
+
+        assert_eq!(
+            collect(RouteFilter::new()),
+            [v4_default, v6_default, custom]
+        );
+        assert_eq!(
+            collect(RouteFilter::new().family(IpFamily::V4)),
+            [v4_default, custom]
+        );
+        assert_eq!(
+            collect(RouteFilter::new().family(IpFamily::V6)),
+            [v6_default]
+        );
+        assert_eq!(collect(RouteFilter::new().table(1000)), [custom]);
+        assert_eq!(collect(RouteFilter::new().table(252)), []);
+
+        let mut filter = RouteFilter::new().table(Route::TABLE_MAIN);
+        filter.interface = Some(3);
+        assert_eq!(collect(filter), [v6_default]);
+    }
+
+    fn table_attribute(message: &RouteMessage) -> Option<u32> {
+        message.nlas.iter().find_map(|nla| match nla {
+            RouteNla::Table(table) => Some(*table),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn spec_to_message() {
+        let spec = RouteSpec::new("10.1.0.0/16".parse().unwrap())
+            .gateway("192.168.0.1".parse().unwrap())
+            .metric(100)
+            .tos(0x10);
+        let message = spec.to_message(true).unwrap();
+        assert_eq!(message.header.address_family, AF_INET as u8);
+        assert_eq!(message.header.destination_prefix_length, 16);
+        assert_eq!(message.header.tos, 0x10);
+        assert_eq!(message.header.table, Route::TABLE_MAIN as u8);
+        assert_eq!(message.header.protocol, u8::from(RouteProtocol::Static));
+        assert_eq!(message.header.kind, u8::from(RouteType::Unicast));
+
+        // The spec survives the trip through the kernel format
+        let route = parse(message).unwrap();
+        assert_eq!(route.destination, spec.destination);
+        assert_eq!(route.gateway, spec.gateway);
+        assert_eq!(route.metric, Some(100));
+        assert_eq!(route.tos, 0x10);
+        assert_eq!(route.table, Route::TABLE_MAIN);
+        assert_eq!(route.scope, RouteScope::Universe);
+    }
+
+    #[test]
+    fn spec_default_scope() {
+        let scope = |spec: RouteSpec| spec.to_message(true).unwrap().header.scope;
+        let destination = "10.1.0.0/16".parse().unwrap();
+
+        let spec = RouteSpec::new(destination);
+        assert_eq!(scope(spec), u8::from(RouteScope::Link));
+        let spec = RouteSpec::new(destination).gateway("192.168.0.1".parse().unwrap());
+        assert_eq!(scope(spec), u8::from(RouteScope::Universe));
+        let spec = RouteSpec::new(destination).nexthop_id(7);
+        assert_eq!(scope(spec), u8::from(RouteScope::Universe));
+        let spec = RouteSpec::new(destination).route_type(RouteType::Local);
+        assert_eq!(scope(spec), u8::from(RouteScope::Host));
+        let spec = RouteSpec::new(destination).route_type(RouteType::Blackhole);
+        assert_eq!(scope(spec), u8::from(RouteScope::Universe));
+        let spec = RouteSpec::new(destination).scope(RouteScope::Site);
+        assert_eq!(scope(spec), u8::from(RouteScope::Site));
+    }
+
+    #[test]
+    fn delete_matches_any_protocol_and_scope() {
+        let spec = RouteSpec::new("10.1.0.0/16".parse().unwrap());
+        let (message, flags) = RouteOperation::Delete.request(&spec).unwrap();
+        let RtnlMessage::DelRoute(message) = message else {
+            panic!("expected a route deletion");
+        };
+        assert_eq!(flags, 0);
+        assert_eq!(message.header.scope, u8::from(RouteScope::Nowhere));
+        assert_eq!(message.header.protocol, 0);
+
+        // Explicitly set values are matched
+        let spec = spec.protocol(RouteProtocol::Dhcp).scope(RouteScope::Link);
+        let message = spec.to_message(false).unwrap();
+        assert_eq!(message.header.scope, u8::from(RouteScope::Link));
+        assert_eq!(message.header.protocol, u8::from(RouteProtocol::Dhcp));
+    }
+
+    #[test]
+    fn add_and_replace_flags() {
+        let spec = RouteSpec::new("10.1.0.0/16".parse().unwrap());
+        let (message, flags) = RouteOperation::Add.request(&spec).unwrap();
+        assert!(matches!(message, RtnlMessage::NewRoute(_)));
+        assert_eq!(flags, NLM_F_CREATE | NLM_F_EXCL);
+
+        let (message, flags) = RouteOperation::Replace.request(&spec).unwrap();
+        assert!(matches!(message, RtnlMessage::NewRoute(_)));
+        assert_eq!(flags, NLM_F_CREATE | NLM_F_REPLACE);
+    }
+
+    #[test]
+    fn spec_table_attribute() {
+        for create in [true, false] {
+            let spec = RouteSpec::new("10.1.0.0/16".parse().unwrap()).table(1000);
+            let message = spec.to_message(create).unwrap();
+            assert_eq!(message.header.table, 0);
+            assert_eq!(table_attribute(&message), Some(1000));
+            assert_eq!(parse(message).unwrap().table, 1000);
+
+            let spec = RouteSpec::new("10.1.0.0/16".parse().unwrap()).table(100);
+            let message = spec.to_message(create).unwrap();
+            assert_eq!(message.header.table, 100);
+            assert_eq!(table_attribute(&message), Some(100));
+        }
+    }
+
+    #[test]
+    fn invalid_spec() {
+        let destination = "10.1.0.0/16".parse().unwrap();
+        let invalid = [
+            RouteSpec::new(destination)
+                .nexthop_id(7)
+                .gateway("192.168.0.1".parse().unwrap()),
+            RouteSpec::new(destination)
+                .nexthop_id(7)
+                .next_hop(NextHop::new()),
+            RouteSpec {
+                interface: Some(2),
+                ..RouteSpec::new(destination).nexthop_id(7)
+            },
+        ];
+        for spec in invalid {
+            for operation in [
+                RouteOperation::Add,
+                RouteOperation::Replace,
+                RouteOperation::Delete,
+            ] {
+                assert!(matches!(
+                    operation.request(&spec),
+                    Err(Error::InvalidParameter)
+                ));
+            }
+        }
+
+        // A nexthop object alone is fine
+        let message = RouteSpec::new(destination)
+            .nexthop_id(7)
+            .to_message(true)
+            .unwrap();
+        assert_eq!(parse(message).unwrap().nexthop_id, Some(7));
+    }
+
+    #[test]
+    fn next_hop_layout() {
+        let next_hop = NextHop {
+            gateway: Some("10.0.0.1".parse().unwrap()),
+            interface: Some(Interface(InterfaceHandle {
+                index: 3,
+                netns: None,
+            })),
+            ..NextHop::new()
+        }
+        .weight(5)
+        .onlink(true);
+
+        let bytes = next_hops_to_vec(&[next_hop]);
+        let mut expected = vec![];
+        // struct rtnexthop: length, flags, hops (weight - 1), interface index
+        expected.extend_from_slice(&16u16.to_ne_bytes());
+        expected.push(NextHopFlags::RTNH_F_ONLINK.bits());
+        expected.push(4);
+        expected.extend_from_slice(&3i32.to_ne_bytes());
+        // RTA_GATEWAY
+        expected.extend_from_slice(&8u16.to_ne_bytes());
+        expected.extend_from_slice(&5u16.to_ne_bytes());
+        expected.extend_from_slice(&[10, 0, 0, 1]);
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn next_hops_round_trip() {
+        let interface = |index| Interface(InterfaceHandle { index, netns: None });
+        let next_hops = [
+            NextHop::new()
+                .gateway("fe80::1".parse().unwrap())
+                .interface(&interface(2)),
+            NextHop::new()
+                .gateway("10.0.0.1".parse().unwrap())
+                .weight(256)
+                .onlink(true),
+            NextHop::new().interface(&interface(4)).weight(1000),
+        ];
+
+        let parsed = parse_next_hops(&next_hops_to_vec(&next_hops), None).unwrap();
+        let summary: Vec<_> = parsed
+            .iter()
+            .map(|hop| {
+                let index = hop.interface.as_ref().map(|iface| iface.0.index);
+                (hop.gateway, index, hop.weight, hop.onlink)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Some("fe80::1".parse().unwrap()), Some(2), 1, false),
+                (Some("10.0.0.1".parse().unwrap()), None, 256, true),
+                (None, Some(4), 256, false),
+            ]
+        );
+        assert!(parsed.iter().all(|hop| !hop.dead && !hop.link_down));
+    }
+
+    #[test]
+    fn next_hop_state_and_via() {
+        let mut nlas = vec![];
+        // struct rtvia: address family, followed by an IPv6 address
+        let mut via = (AF_INET6 as u16).to_ne_bytes().to_vec();
+        via.extend_from_slice(&"fe80::1".parse::<Ipv6Addr>().unwrap().octets());
+        netlink::put_attribute(&mut nlas, 18, &via);
+
+        let flags = NextHopFlags::RTNH_F_DEAD | NextHopFlags::RTNH_F_LINKDOWN;
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&(8 + nlas.len() as u16).to_ne_bytes());
+        bytes.push(flags.bits());
+        bytes.push(0);
+        bytes.extend_from_slice(&2i32.to_ne_bytes());
+        bytes.extend_from_slice(&nlas);
+
+        let parsed = parse_next_hops(&bytes, None).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].gateway, Some("fe80::1".parse().unwrap()));
+        assert_eq!(parsed[0].weight, 1);
+        assert!(parsed[0].dead);
+        assert!(parsed[0].link_down);
+        assert!(!parsed[0].onlink);
+    }
+
+    #[test]
+    fn malformed_next_hops() {
+        // Length, shorter than the header
+        let mut bytes = 4u16.to_ne_bytes().to_vec();
+        bytes.extend_from_slice(&[0; 6]);
+        assert!(parse_next_hops(&bytes, None).is_err());
+
+        // Truncated header
+        assert!(parse_next_hops(&[8, 0, 0], None).is_err());
+    }
+
+    #[test]
+    fn multipath_route_round_trip() {
+        let spec = RouteSpec::new("10.1.0.0/16".parse().unwrap())
+            .next_hop(NextHop::new().gateway("10.0.0.1".parse().unwrap()))
+            .next_hop(
+                NextHop::new()
+                    .gateway("10.0.0.2".parse().unwrap())
+                    .weight(3),
+            );
+        let route = parse(spec.to_message(true).unwrap()).unwrap();
+        let hops: Vec<_> = route
+            .next_hops
+            .iter()
+            .map(|hop| (hop.gateway, hop.weight))
+            .collect();
+        assert_eq!(
+            hops,
+            [
+                (Some("10.0.0.1".parse().unwrap()), 1),
+                (Some("10.0.0.2".parse().unwrap()), 3),
+            ]
+        );
+    }
+
+    fn metric_attributes(metrics: &RouteMetrics) -> Vec<(u16, Vec<u8>)> {
+        NlasIterator::new(&metrics.to_vec()[..])
+            .map(|nla| {
+                let nla = nla.unwrap();
+                (nla.kind(), nla.value().to_vec())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn metrics_units() {
+        let metrics = RouteMetrics::new()
+            .rtt(Duration::from_millis(10))
+            .rttvar(Duration::from_millis(10));
+        // RTT is stored in 1/8 ms, its variance in 1/4 ms
+        assert_eq!(
+            metric_attributes(&metrics),
+            [
+                (RTAX_RTT, 80u32.to_ne_bytes().to_vec()),
+                (RTAX_RTTVAR, 40u32.to_ne_bytes().to_vec()),
+            ]
+        );
+
+        let mut bytes = vec![];
+        netlink::put_attribute(&mut bytes, RTAX_RTT, &3u32.to_ne_bytes());
+        netlink::put_attribute(&mut bytes, RTAX_RTTVAR, &3u32.to_ne_bytes());
+        let metrics = RouteMetrics::parse(&bytes).unwrap();
+        assert_eq!(metrics.rtt, Some(Duration::from_micros(375)));
+        assert_eq!(metrics.rttvar, Some(Duration::from_micros(750)));
+
+        // Values, too large for the kernel, are saturated
+        let metrics = RouteMetrics::new().rtt(Duration::from_secs(u64::MAX));
+        assert_eq!(
+            metric_attributes(&metrics),
+            [(RTAX_RTT, u32::MAX.to_ne_bytes().to_vec())]
+        );
+    }
+
+    #[test]
+    fn metrics_round_trip() {
+        let metrics = RouteMetrics::new()
+            .mtu(1400)
+            .advmss(1360)
+            .window(65535)
+            .rtt(Duration::from_micros(1500))
+            .rttvar(Duration::from_micros(500))
+            .initcwnd(10)
+            .initrwnd(20)
+            .hoplimit(64)
+            .quickack(true)
+            .congestion_control("bbr");
+        assert_eq!(RouteMetrics::parse(&metrics.to_vec()).unwrap(), metrics);
+
+        let metrics = RouteMetrics::new().quickack(false);
+        assert_eq!(RouteMetrics::parse(&metrics.to_vec()).unwrap(), metrics);
+
+        assert!(RouteMetrics::new().to_vec().is_empty());
+        assert!(RouteMetrics::parse(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn congestion_control_string() {
+        let metrics = RouteMetrics::new().congestion_control("cubic");
+        assert_eq!(
+            metric_attributes(&metrics),
+            [(RTAX_CC_ALGO, b"cubic\0".to_vec())]
+        );
+
+        // The name ends at the first NUL, padding is ignored
+        let mut bytes = vec![];
+        netlink::put_attribute(&mut bytes, RTAX_CC_ALGO, b"bbr\0\0\0\0");
+        let metrics = RouteMetrics::parse(&bytes).unwrap();
+        assert_eq!(metrics.congestion_control.as_deref(), Some("bbr"));
+    }
+
+    #[test]
+    fn malformed_metrics() {
+        let mut bytes = vec![];
+        netlink::put_attribute(&mut bytes, RTAX_MTU, &[0; 2]);
+        assert!(RouteMetrics::parse(&bytes).is_err());
+
+        // Unknown metrics are skipped
+        let mut bytes = vec![];
+        netlink::put_attribute(&mut bytes, RTAX_FASTOPEN_NO_COOKIE, &1u32.to_ne_bytes());
+        assert!(RouteMetrics::parse(&bytes).unwrap().is_empty());
+    }
+
+    #[test]
+    fn route_metrics_round_trip() {
+        let metrics = RouteMetrics::new().mtu(1400).rtt(Duration::from_millis(2));
+        let spec = RouteSpec::new("10.1.0.0/16".parse().unwrap()).metrics(metrics.clone());
+        assert_eq!(
+            parse(spec.to_message(true).unwrap()).unwrap().metrics,
+            metrics
+        );
+    }
+}
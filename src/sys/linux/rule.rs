@@ -0,0 +1,532 @@
+use super::handle::parse_ip;
+use super::route::ip_to_vec;
+use super::{netlink, IpFamily, NetnsHandle, RouteProtocol};
+use crate::Error;
+use ipnet::IpNet;
+use netlink_packet_route::rule::Nla as RuleNla;
+use netlink_packet_route::{
+    RtnlMessage, RuleMessage, FIB_RULE_INVERT, FRA_IIFNAME, FRA_OIFNAME, FR_ACT_BLACKHOLE,
+    FR_ACT_GOTO, FR_ACT_NOP, FR_ACT_PROHIBIT, FR_ACT_TO_TBL, FR_ACT_UNREACHABLE, NLM_F_CREATE,
+    NLM_F_DUMP, NLM_F_EXCL,
+};
+use std::ops::RangeInclusive;
+
+/// What happens to a packet, matching a routing policy rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleAction {
+    /// Route lookup in given table.
+    Table(u32),
+    /// Evaluation continues with the rule of given priority.
+    Goto(u32),
+    Nop,
+    /// Packets are silently dropped.
+    Blackhole,
+    Unreachable,
+    Prohibit,
+    /// Action (`FR_ACT_*`), unknown to this crate.
+    Other(u8),
+}
+
+/// Routing policy rule, as shown by `ip rule`.
+///
+/// The same type is used to describe rules to add or delete. Unset selectors match any packet.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub family: IpFamily,
+    /// Rules are evaluated in the order of increasing priority. Unset priority is chosen by the
+    /// kernel when a rule is added.
+    pub priority: Option<u32>,
+    pub from: Option<IpNet>,
+    pub to: Option<IpNet>,
+    /// Name of the interface, packets are received from. Rules may refer to missing interfaces.
+    pub input_interface: Option<String>,
+    /// Name of the interface, bound by the sending socket.
+    pub output_interface: Option<String>,
+    pub fwmark: Option<u32>,
+    pub fwmask: Option<u32>,
+    /// Range of socket owner uids.
+    pub uid_range: Option<RangeInclusive<u32>>,
+    /// IP protocol number, e.g. `6` for TCP.
+    pub ip_protocol: Option<u8>,
+    pub source_ports: Option<RangeInclusive<u16>>,
+    pub destination_ports: Option<RangeInclusive<u16>>,
+    /// Rule matches packets, not matching the selectors (`ip rule add not ...`).
+    pub invert: bool,
+    /// Origin of the rule.
+    pub protocol: Option<RouteProtocol>,
+    pub action: RuleAction,
+}
+
+impl Rule {
+    /// Creates a rule, matching all packets of given family.
+    pub fn new(family: IpFamily, action: RuleAction) -> Self {
+        Self {
+            family,
+            priority: None,
+            from: None,
+            to: None,
+            input_interface: None,
+            output_interface: None,
+            fwmark: None,
+            fwmask: None,
+            uid_range: None,
+            ip_protocol: None,
+            source_ports: None,
+            destination_ports: None,
+            invert: false,
+            protocol: None,
+            action,
+        }
+    }
+
+    pub fn priority(mut self, priority: u32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    pub fn from(mut self, from: IpNet) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn to(mut self, to: IpNet) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    pub fn input_interface(mut self, name: &str) -> Self {
+        self.input_interface = Some(name.to_string());
+        self
+    }
+
+    pub fn output_interface(mut self, name: &str) -> Self {
+        self.output_interface = Some(name.to_string());
+        self
+    }
+
+    pub fn fwmark(mut self, fwmark: u32, fwmask: Option<u32>) -> Self {
+        self.fwmark = Some(fwmark);
+        self.fwmask = fwmask;
+        self
+    }
+
+    pub fn uid_range(mut self, uids: RangeInclusive<u32>) -> Self {
+        self.uid_range = Some(uids);
+        self
+    }
+
+    pub fn ip_protocol(mut self, protocol: u8) -> Self {
+        self.ip_protocol = Some(protocol);
+        self
+    }
+
+    pub fn source_ports(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.source_ports = Some(ports);
+        self
+    }
+
+    pub fn destination_ports(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.destination_ports = Some(ports);
+        self
+    }
+
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    pub fn protocol(mut self, protocol: RouteProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    pub(crate) fn from_message(message: RuleMessage) -> Result<Option<Self>, Error> {
+        let header = &message.header;
+        let Some(family) = IpFamily::from_af(header.family) else {
+            return Ok(None);
+        };
+
+        let mut table = header.table as u32;
+        let mut goto = None;
+        let mut rule = Rule::new(family, RuleAction::Nop);
+        rule.invert = header.flags & FIB_RULE_INVERT != 0;
+        // FRA_PRIORITY is omitted for priority 0
+        rule.priority = Some(0);
+
+        for nla in message.nlas {
+            match nla {
+                RuleNla::Source(bytes) => {
+                    let address = parse_ip(&bytes).ok_or(Error::UnexpectedMetadata)?;
+                    rule.from = Some(
+                        IpNet::new(address, header.src_len)
+                            .map_err(|_| Error::UnexpectedMetadata)?,
+                    );
+                }
+                RuleNla::Destination(bytes) => {
+                    let address = parse_ip(&bytes).ok_or(Error::UnexpectedMetadata)?;
+                    rule.to = Some(
+                        IpNet::new(address, header.dst_len)
+                            .map_err(|_| Error::UnexpectedMetadata)?,
+                    );
+                }
+                RuleNla::Priority(priority) => rule.priority = Some(priority),
+                RuleNla::Iifname(name) => rule.input_interface = Some(name),
+                RuleNla::OifName(name) => rule.output_interface = Some(name),
+                RuleNla::FwMark(fwmark) => rule.fwmark = Some(fwmark),
+                RuleNla::FwMask(fwmask) => rule.fwmask = Some(fwmask),
+                RuleNla::UidRange(bytes) => rule.uid_range = Some(parse_uid_range(&bytes)?),
+                RuleNla::IpProto(protocol) => rule.ip_protocol = Some(protocol),
+                RuleNla::SourcePortRange(bytes) => {
+                    rule.source_ports = Some(parse_port_range(&bytes)?)
+                }
+                RuleNla::DestinationPortRange(bytes) => {
+                    rule.destination_ports = Some(parse_port_range(&bytes)?)
+                }
+                RuleNla::Protocol(protocol) => rule.protocol = Some(protocol.into()),
+                // FRA_TABLE carries table ids, that do not fit into the header
+                RuleNla::Table(id) => table = id,
+                RuleNla::Goto(target) => goto = Some(target),
+                _ => {}
+            }
+        }
+
+        rule.action = match header.action {
+            FR_ACT_TO_TBL => RuleAction::Table(table),
+            FR_ACT_GOTO => RuleAction::Goto(goto.ok_or(Error::UnexpectedMetadata)?),
+            FR_ACT_NOP => RuleAction::Nop,
+            FR_ACT_BLACKHOLE => RuleAction::Blackhole,
+            FR_ACT_UNREACHABLE => RuleAction::Unreachable,
+            FR_ACT_PROHIBIT => RuleAction::Prohibit,
+            other => RuleAction::Other(other),
+        };
+
+        Ok(Some(rule))
+    }
+
+    pub(crate) fn to_message(&self) -> RuleMessage {
+        let mut message = RuleMessage::default();
+        let header = &mut message.header;
+        let nlas = &mut message.nlas;
+
+        header.family = self.family.af();
+        if self.invert {
+            header.flags |= FIB_RULE_INVERT;
+        }
+
+        header.action = match self.action {
+            RuleAction::Table(table) => {
+                // Table ids, that do not fit into the header, are passed in FRA_TABLE only
+                header.table = u8::try_from(table).unwrap_or(0);
+                nlas.push(RuleNla::Table(table));
+                FR_ACT_TO_TBL
+            }
+            RuleAction::Goto(target) => {
+                nlas.push(RuleNla::Goto(target));
+                FR_ACT_GOTO
+            }
+            RuleAction::Nop => FR_ACT_NOP,
+            RuleAction::Blackhole => FR_ACT_BLACKHOLE,
+            RuleAction::Unreachable => FR_ACT_UNREACHABLE,
+            RuleAction::Prohibit => FR_ACT_PROHIBIT,
+            RuleAction::Other(action) => action,
+        };
+
+        if let Some(from) = self.from {
+            header.src_len = from.prefix_len();
+            nlas.push(RuleNla::Source(ip_to_vec(from.addr())));
+        }
+        if let Some(to) = self.to {
+            header.dst_len = to.prefix_len();
+            nlas.push(RuleNla::Destination(ip_to_vec(to.addr())));
+        }
+        if let Some(priority) = self.priority {
+            nlas.push(RuleNla::Priority(priority));
+        }
+        if let Some(name) = &self.input_interface {
+            nlas.push(string_nla(FRA_IIFNAME, name));
+        }
+        if let Some(name) = &self.output_interface {
+            nlas.push(string_nla(FRA_OIFNAME, name));
+        }
+        if let Some(fwmark) = self.fwmark {
+            nlas.push(RuleNla::FwMark(fwmark));
+        }
+        if let Some(fwmask) = self.fwmask {
+            nlas.push(RuleNla::FwMask(fwmask));
+        }
+        if let Some(uids) = &self.uid_range {
+            // struct fib_rule_uid_range
+            let mut bytes = uids.start().to_ne_bytes().to_vec();
+            bytes.extend_from_slice(&uids.end().to_ne_bytes());
+            nlas.push(RuleNla::UidRange(bytes));
+        }
+        if let Some(protocol) = self.ip_protocol {
+            nlas.push(RuleNla::IpProto(protocol));
+        }
+        if let Some(ports) = &self.source_ports {
+            nlas.push(RuleNla::SourcePortRange(port_range_to_vec(ports)));
+        }
+        if let Some(ports) = &self.destination_ports {
+            nlas.push(RuleNla::DestinationPortRange(port_range_to_vec(ports)));
+        }
+        if let Some(protocol) = self.protocol {
+            nlas.push(RuleNla::Protocol(protocol.into()));
+        }
+
+        message
+    }
+}
+
+/// Builds a NUL-terminated string attribute.
+///
+/// `Nla::Iifname` and `Nla::OifName` can't be used: they reserve space for the terminator, but don't
+/// write it, which makes serialization panic.
+fn string_nla(kind: u16, value: &str) -> RuleNla {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+    RuleNla::Other(netlink::default_nla(kind, &bytes))
+}
+
+/// Parses `struct fib_rule_uid_range`.
+fn parse_uid_range(bytes: &[u8]) -> Result<RangeInclusive<u32>, Error> {
+    let start = bytes.get(0..4).ok_or(Error::UnexpectedMetadata)?;
+    let end = bytes.get(4..8).ok_or(Error::UnexpectedMetadata)?;
+    Ok(u32::from_ne_bytes(start.try_into().unwrap())..=u32::from_ne_bytes(end.try_into().unwrap()))
+}
+
+/// Parses `struct fib_rule_port_range`.
+fn parse_port_range(bytes: &[u8]) -> Result<RangeInclusive<u16>, Error> {
+    let start = bytes.get(0..2).ok_or(Error::UnexpectedMetadata)?;
+    let end = bytes.get(2..4).ok_or(Error::UnexpectedMetadata)?;
+    Ok(u16::from_ne_bytes(start.try_into().unwrap())..=u16::from_ne_bytes(end.try_into().unwrap()))
+}
+
+fn port_range_to_vec(ports: &RangeInclusive<u16>) -> Vec<u8> {
+    let mut bytes = ports.start().to_ne_bytes().to_vec();
+    bytes.extend_from_slice(&ports.end().to_ne_bytes());
+    bytes
+}
+
+/// Returns routing policy rules of both families, ordered by priority.
+pub fn list_rules() -> Result<Vec<Rule>, Error> {
+    list_rules_in(None)
+}
+
+/// Adds a routing policy rule. Fails, if the same rule already exists.
+pub fn add_rule(rule: &Rule) -> Result<(), Error> {
+    modify_rule_in(None, rule, true)
+}
+
+/// Deletes the first routing policy rule, matching given one.
+pub fn delete_rule(rule: &Rule) -> Result<(), Error> {
+    modify_rule_in(None, rule, false)
+}
+
+pub(crate) fn list_rules_in(netns: Option<&NetnsHandle>) -> Result<Vec<Rule>, Error> {
+    let socket = netlink::socket(netns)?;
+    let responses = netlink::request(
+        &socket,
+        RtnlMessage::GetRule(RuleMessage::default()),
+        NLM_F_DUMP,
+    )?;
+
+    let mut rules = vec![];
+    for response in responses {
+        let RtnlMessage::NewRule(message) = response else {
+            continue;
+        };
+        rules.extend(Rule::from_message(message)?);
+    }
+    // The kernel dumps IPv4 rules before IPv6 ones, the relative order is kept for equal priorities
+    rules.sort_by_key(|rule| rule.priority.unwrap_or(0));
+    Ok(rules)
+}
+
+pub(crate) fn modify_rule_in(
+    netns: Option<&NetnsHandle>,
+    rule: &Rule,
+    add: bool,
+) -> Result<(), Error> {
+    let (message, flags) = if add {
+        (
+            RtnlMessage::NewRule(rule.to_message()),
+            NLM_F_CREATE | NLM_F_EXCL,
+        )
+    } else {
+        (RtnlMessage::DelRule(rule.to_message()), 0)
+    };
+
+    let socket = netlink::socket(netns)?;
+    netlink::request(&socket, message, flags)?;
+    Ok(())
+}
+
+impl NetnsHandle {
+    /// Returns routing policy rules inside this namespace, ordered by priority.
+    pub fn list_rules(&self) -> Result<Vec<Rule>, Error> {
+        list_rules_in(Some(self))
+    }
+
+    pub fn add_rule(&self, rule: &Rule) -> Result<(), Error> {
+        modify_rule_in(Some(self), rule, true)
+    }
+
+    pub fn delete_rule(&self, rule: &Rule) -> Result<(), Error> {
+        modify_rule_in(Some(self), rule, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::linux::Route;
+    use netlink_packet_route::nlas::Nla;
+    use netlink_packet_route::{FRA_DPORT_RANGE, FRA_SPORT_RANGE, FRA_UID_RANGE};
+
+    /// Sends a rule through the kernel format and parses it back.
+    fn round_trip(rule: &Rule) -> Rule {
+        let RtnlMessage::NewRule(message) =
+            netlink::reparse(RtnlMessage::NewRule(rule.to_message()))
+        else {
+            panic!("expected a rule");
+        };
+        Rule::from_message(message).unwrap().unwrap()
+    }
+
+    /// Returns the value of the attribute of given kind, as written to the kernel.
+    fn attribute(message: &RuleMessage, kind: u16) -> Option<Vec<u8>> {
+        message
+            .nlas
+            .iter()
+            .find(|nla| nla.kind() == kind)
+            .map(|nla| {
+                let mut value = vec![0; nla.value_len()];
+                nla.emit_value(&mut value);
+                value
+            })
+    }
+
+    #[test]
+    fn rule_round_trip() {
+        let rule = Rule::new(IpFamily::V4, RuleAction::Table(1000))
+            .priority(100)
+            .from("10.0.0.0/8".parse().unwrap())
+            .to("192.168.1.0/24".parse().unwrap())
+            .input_interface("eth0")
+            .output_interface("wg0")
+            .fwmark(0x10, Some(0xff))
+            .uid_range(1000..=1999)
+            .ip_protocol(6)
+            .source_ports(1024..=2048)
+            .destination_ports(443..=443)
+            .invert(true)
+            .protocol(RouteProtocol::Static);
+        assert_eq!(round_trip(&rule), rule);
+
+        let rule = Rule::new(IpFamily::V6, RuleAction::Prohibit)
+            .priority(5)
+            .from("fd00::/64".parse().unwrap());
+        assert_eq!(round_trip(&rule), rule);
+    }
+
+    #[test]
+    fn actions_round_trip() {
+        for action in [
+            RuleAction::Table(Route::TABLE_MAIN),
+            RuleAction::Table(1000),
+            RuleAction::Goto(32766),
+            RuleAction::Nop,
+            RuleAction::Blackhole,
+            RuleAction::Unreachable,
+            RuleAction::Prohibit,
+            RuleAction::Other(42),
+        ] {
+            let rule = Rule::new(IpFamily::V4, action).priority(10);
+            assert_eq!(round_trip(&rule).action, action);
+        }
+    }
+
+    #[test]
+    fn table_in_header_and_attribute() {
+        let message = Rule::new(IpFamily::V4, RuleAction::Table(100)).to_message();
+        assert_eq!(message.header.table, 100);
+        assert_eq!(message.header.action, FR_ACT_TO_TBL);
+        assert!(message.nlas.contains(&RuleNla::Table(100)));
+
+        // Ids above 255 don't fit into the header
+        let message = Rule::new(IpFamily::V4, RuleAction::Table(1000)).to_message();
+        assert_eq!(message.header.table, 0);
+        assert!(message.nlas.contains(&RuleNla::Table(1000)));
+
+        // Dumps report such tables as RT_TABLE_COMPAT in the header
+        let mut message = message;
+        message.header.table = 252;
+        assert_eq!(
+            Rule::from_message(message).unwrap().unwrap().action,
+            RuleAction::Table(1000)
+        );
+    }
+
+    #[test]
+    fn interface_names_are_terminated() {
+        let message = Rule::new(IpFamily::V4, RuleAction::Nop)
+            .input_interface("eth0")
+            .output_interface("veth-1")
+            .to_message();
+        assert_eq!(attribute(&message, FRA_IIFNAME).unwrap(), b"eth0\0");
+        assert_eq!(attribute(&message, FRA_OIFNAME).unwrap(), b"veth-1\0");
+
+        // The message can be serialized, and the names are read back without the terminator
+        let rule = round_trip(&Rule::new(IpFamily::V4, RuleAction::Nop).input_interface("eth0"));
+        assert_eq!(rule.input_interface.as_deref(), Some("eth0"));
+    }
+
+    #[test]
+    fn range_layout() {
+        let message = Rule::new(IpFamily::V4, RuleAction::Nop)
+            .uid_range(1000..=1999)
+            .source_ports(1024..=2048)
+            .destination_ports(443..=443)
+            .to_message();
+
+        let mut uids = 1000u32.to_ne_bytes().to_vec();
+        uids.extend_from_slice(&1999u32.to_ne_bytes());
+        assert_eq!(attribute(&message, FRA_UID_RANGE).unwrap(), uids);
+
+        let mut ports = 1024u16.to_ne_bytes().to_vec();
+        ports.extend_from_slice(&2048u16.to_ne_bytes());
+        assert_eq!(attribute(&message, FRA_SPORT_RANGE).unwrap(), ports);
+
+        let mut ports = 443u16.to_ne_bytes().to_vec();
+        ports.extend_from_slice(&443u16.to_ne_bytes());
+        assert_eq!(attribute(&message, FRA_DPORT_RANGE).unwrap(), ports);
+    }
+
+    #[test]
+    fn truncated_ranges() {
+        assert!(parse_uid_range(&[0; 7]).is_err());
+        assert!(parse_port_range(&[0; 3]).is_err());
+        assert_eq!(parse_port_range(&[0; 4]).unwrap(), 0..=0);
+    }
+
+    #[test]
+    fn rule_from_message() {
+        // FRA_PRIORITY is omitted for priority 0
+        let mut message = RuleMessage::default();
+        message.header.family = libc::AF_INET as u8;
+        message.header.action = FR_ACT_TO_TBL;
+        message.header.table = Route::TABLE_LOCAL as u8;
+        let rule = Rule::from_message(message.clone()).unwrap().unwrap();
+        assert_eq!(rule.priority, Some(0));
+        assert_eq!(rule.action, RuleAction::Table(Route::TABLE_LOCAL));
+        assert!(!rule.invert);
+
+        // Goto without a target
+        message.header.action = FR_ACT_GOTO;
+        assert!(Rule::from_message(message.clone()).is_err());
+
+        message.header.family = libc::AF_MPLS as u8;
+        assert!(Rule::from_message(message).unwrap().is_none());
+    }
+}
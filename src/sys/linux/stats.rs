@@ -0,0 +1,256 @@
+use crate::sys::{InterfaceHandle, InterfaceName};
+use crate::Error;
+use netlink_packet_route::link::nlas::{
+    Nla as LinkNla, Stats, Stats64, Stats64Buffer, StatsBuffer,
+};
+use netlink_packet_route::traits::Parseable;
+use netlink_packet_route::LinkMessage;
+use std::fs;
+
+/// Interface counters, as reported by the kernel (`struct rtnl_link_stats64`).
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceStats {
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+    pub multicast: u64,
+    pub collisions: u64,
+
+    // detailed rx_errors
+    pub rx_length_errors: u64,
+    pub rx_over_errors: u64,
+    pub rx_crc_errors: u64,
+    pub rx_frame_errors: u64,
+    pub rx_fifo_errors: u64,
+    pub rx_missed_errors: u64,
+
+    // detailed tx_errors
+    pub tx_aborted_errors: u64,
+    pub tx_carrier_errors: u64,
+    pub tx_fifo_errors: u64,
+    pub tx_heartbeat_errors: u64,
+    pub tx_window_errors: u64,
+
+    pub rx_compressed: u64,
+    pub tx_compressed: u64,
+    pub rx_nohandler: u64,
+}
+
+macro_rules! stats_from {
+    ($src:ty, $($field:ident),+) => {
+        impl From<$src> for InterfaceStats {
+            fn from(stats: $src) -> Self {
+                Self {
+                    $($field: stats.$field as _,)+
+                }
+            }
+        }
+    };
+}
+
+stats_from!(
+    Stats64,
+    rx_packets,
+    tx_packets,
+    rx_bytes,
+    tx_bytes,
+    rx_errors,
+    tx_errors,
+    rx_dropped,
+    tx_dropped,
+    multicast,
+    collisions,
+    rx_length_errors,
+    rx_over_errors,
+    rx_crc_errors,
+    rx_frame_errors,
+    rx_fifo_errors,
+    rx_missed_errors,
+    tx_aborted_errors,
+    tx_carrier_errors,
+    tx_fifo_errors,
+    tx_heartbeat_errors,
+    tx_window_errors,
+    rx_compressed,
+    tx_compressed,
+    rx_nohandler
+);
+
+stats_from!(
+    Stats,
+    rx_packets,
+    tx_packets,
+    rx_bytes,
+    tx_bytes,
+    rx_errors,
+    tx_errors,
+    rx_dropped,
+    tx_dropped,
+    multicast,
+    collisions,
+    rx_length_errors,
+    rx_over_errors,
+    rx_crc_errors,
+    rx_frame_errors,
+    rx_fifo_errors,
+    rx_missed_errors,
+    tx_aborted_errors,
+    tx_carrier_errors,
+    tx_fifo_errors,
+    tx_heartbeat_errors,
+    tx_window_errors,
+    rx_compressed,
+    tx_compressed,
+    rx_nohandler
+);
+
+impl InterfaceStats {
+    /// Parses interface counters from `/proc/net/dev`.
+    ///
+    /// This file contains only aggregated error counters, so the detailed breakdown is partial:
+    /// `frame` is reported as `rx_frame_errors` and `carrier` as `tx_carrier_errors`.
+    fn from_proc_net_dev(contents: &str, name: &str) -> Option<Self> {
+        contents.lines().skip(2).find_map(|line| {
+            let (iface, counters) = line.split_once(':')?;
+            if iface.trim() != name {
+                return None;
+            }
+
+            // Receive: bytes packets errs drop fifo frame compressed multicast
+            // Transmit: bytes packets errs drop fifo colls carrier compressed
+            let counters = counters
+                .split_whitespace()
+                .map(|counter| counter.parse::<u64>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            if counters.len() < 16 {
+                return None;
+            }
+
+            Some(Self {
+                rx_bytes: counters[0],
+                rx_packets: counters[1],
+                rx_errors: counters[2],
+                rx_dropped: counters[3],
+                rx_fifo_errors: counters[4],
+                rx_frame_errors: counters[5],
+                rx_compressed: counters[6],
+                multicast: counters[7],
+                tx_bytes: counters[8],
+                tx_packets: counters[9],
+                tx_errors: counters[10],
+                tx_dropped: counters[11],
+                tx_fifo_errors: counters[12],
+                collisions: counters[13],
+                tx_carrier_errors: counters[14],
+                tx_compressed: counters[15],
+                ..Default::default()
+            })
+        })
+    }
+}
+
+impl InterfaceHandle {
+    pub fn stats(&self) -> Result<InterfaceStats, Error> {
+        let link = match self.link() {
+            Ok(link) => link,
+            // netlink is unavailable, /proc/net/dev describes the namespace of the calling thread only
+            Err(Error::Io(_)) if self.netns.is_none() => return self.stats_from_proc(),
+            Err(e) => return Err(e),
+        };
+
+        match link_stats(&link)? {
+            Some(stats) => Ok(stats),
+            None if self.netns.is_none() => self.stats_from_proc(),
+            None => Err(Error::UnexpectedMetadata),
+        }
+    }
+
+    fn stats_from_proc(&self) -> Result<InterfaceStats, Error> {
+        // if_indextoname() is ioctl-based, so it works without netlink
+        let mut buf = InterfaceName::default();
+        if unsafe { libc::if_indextoname(self.index, buf.as_mut_ptr()) }.is_null() {
+            return Err(Error::InterfaceNotFound);
+        }
+        let name = String::try_from(buf).map_err(|_| Error::UnexpectedMetadata)?;
+        let contents = fs::read_to_string("/proc/net/dev")?;
+
+        InterfaceStats::from_proc_net_dev(&contents, &name).ok_or(Error::InterfaceNotFound)
+    }
+}
+
+/// Extracts interface counters from link information.
+pub(crate) fn link_stats(link: &LinkMessage) -> Result<Option<InterfaceStats>, Error> {
+    let mut stats = None;
+    for nla in &link.nlas {
+        match nla {
+            LinkNla::Stats64(bytes) => {
+                return Ok(Some(
+                    Stats64::parse(&Stats64Buffer::new_checked(bytes)?)?.into(),
+                ))
+            }
+            // IFLA_STATS is used only as a fallback for kernels without IFLA_STATS64
+            LinkNla::Stats(bytes) => {
+                stats = Some(Stats::parse(&StatsBuffer::new_checked(bytes)?)?.into())
+            }
+            _ => {}
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    5120      64    0    0    0     0          0         0     5120      64    0    0    0     0       0          0
+  eth0: 1000000    2000    1    2    3     4          5         6   500000    1000    7    8    9    10      11         12
+";
+
+    #[test]
+    fn proc_net_dev() {
+        let stats = InterfaceStats::from_proc_net_dev(PROC_NET_DEV, "eth0").unwrap();
+        assert_eq!(stats.rx_bytes, 1000000);
+        assert_eq!(stats.rx_packets, 2000);
+        assert_eq!(stats.rx_errors, 1);
+        assert_eq!(stats.rx_dropped, 2);
+        assert_eq!(stats.rx_fifo_errors, 3);
+        assert_eq!(stats.rx_frame_errors, 4);
+        assert_eq!(stats.rx_compressed, 5);
+        assert_eq!(stats.multicast, 6);
+        assert_eq!(stats.tx_bytes, 500000);
+        assert_eq!(stats.tx_packets, 1000);
+        assert_eq!(stats.tx_errors, 7);
+        assert_eq!(stats.tx_dropped, 8);
+        assert_eq!(stats.tx_fifo_errors, 9);
+        assert_eq!(stats.collisions, 10);
+        assert_eq!(stats.tx_carrier_errors, 11);
+        assert_eq!(stats.tx_compressed, 12);
+        assert_eq!(stats.rx_crc_errors, 0);
+    }
+
+    #[test]
+    fn proc_net_dev_missing() {
+        assert_eq!(
+            InterfaceStats::from_proc_net_dev(PROC_NET_DEV, "eth1"),
+            None
+        );
+        // Header lines are never taken for interfaces
+        assert_eq!(
+            InterfaceStats::from_proc_net_dev(PROC_NET_DEV, "face"),
+            None
+        );
+        assert_eq!(
+            InterfaceStats::from_proc_net_dev("\n\n  eth0: 1 2 3\n", "eth0"),
+            None
+        );
+    }
+}
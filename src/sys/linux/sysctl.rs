@@ -0,0 +1,84 @@
+use super::NetnsHandle;
+use crate::sys::InterfaceHandle;
+use crate::Error;
+use std::fs;
+use std::path::PathBuf;
+
+impl InterfaceHandle {
+    pub fn proxy_arp(&self) -> Result<bool, Error> {
+        self.conf_flag("ipv4", "proxy_arp")
+    }
+
+    pub fn set_proxy_arp(&self, v: bool) -> Result<(), Error> {
+        self.set_conf_flag("ipv4", "proxy_arp", v)
+    }
+
+    pub fn proxy_ndp(&self) -> Result<bool, Error> {
+        self.conf_flag("ipv6", "proxy_ndp")
+    }
+
+    pub fn set_proxy_ndp(&self, v: bool) -> Result<(), Error> {
+        self.set_conf_flag("ipv6", "proxy_ndp", v)
+    }
+
+    fn conf_flag(&self, protocol: &str, key: &str) -> Result<bool, Error> {
+        let path = conf_path(protocol, &self.name()?, key);
+        let value = read(self.netns.as_ref(), path)?;
+        Ok(value.trim() != "0")
+    }
+
+    fn set_conf_flag(&self, protocol: &str, key: &str, v: bool) -> Result<(), Error> {
+        let value = if v { "1" } else { "0" };
+        write(
+            self.netns.as_ref(),
+            conf_path(protocol, &self.name()?, key),
+            value,
+        )
+    }
+}
+
+/// Returns path of a per-interface sysctl, e.g. `/proc/sys/net/ipv4/conf/eth0/proxy_arp`.
+fn conf_path(protocol: &str, name: &str, key: &str) -> PathBuf {
+    PathBuf::from("/proc/sys/net")
+        .join(protocol)
+        .join("conf")
+        .join(name)
+        .join(key)
+}
+
+/// Reads a sysctl. `/proc/sys/net` reflects the namespace of the thread, opening the file.
+pub(crate) fn read(netns: Option<&NetnsHandle>, path: PathBuf) -> Result<String, Error> {
+    match netns {
+        Some(netns) => netns.run(move || fs::read_to_string(path)),
+        None => Ok(fs::read_to_string(path)?),
+    }
+}
+
+/// Writes a sysctl inside given namespace.
+pub(crate) fn write(
+    netns: Option<&NetnsHandle>,
+    path: PathBuf,
+    value: &'static str,
+) -> Result<(), Error> {
+    match netns {
+        Some(netns) => netns.run(move || fs::write(path, value)),
+        None => Ok(fs::write(path, value)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxy_paths() {
+        assert_eq!(
+            conf_path("ipv4", "eth0", "proxy_arp"),
+            PathBuf::from("/proc/sys/net/ipv4/conf/eth0/proxy_arp")
+        );
+        assert_eq!(
+            conf_path("ipv6", "eth0", "proxy_ndp"),
+            PathBuf::from("/proc/sys/net/ipv6/conf/eth0/proxy_ndp")
+        );
+    }
+}
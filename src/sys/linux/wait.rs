@@ -0,0 +1,373 @@
+use super::monitor::{AddressInfo, Event, LinkInfo, Monitor, MonitorGroup};
+use super::{netlink, NetnsHandle};
+use crate::{Error, Interface};
+use libc::{IFF_LOWER_UP, IFF_UP};
+use netlink_packet_route::link::nlas::Nla as LinkNla;
+use netlink_packet_route::{AddressMessage, LinkMessage, RtnlMessage, NLM_F_DUMP};
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Predicate, an address has to match in [`wait_for_address`].
+pub(crate) type AddressPredicate<'a> = &'a mut (dyn FnMut(&AddressInfo) -> bool + Send);
+
+/// Condition to wait for. Evaluated once against the current state, then against every event,
+/// until satisfied.
+pub(crate) enum WaitCondition<'a> {
+    /// Interface with given name exists.
+    Interface(&'a str),
+    /// Interface has all of given `IFF_*` flags.
+    Flags(u32, u32),
+    /// Interface has a usable address, matching the predicate.
+    Address(u32, AddressPredicate<'a>),
+    /// Interface doesn't exist.
+    Removed(u32),
+}
+
+/// Value, a satisfied [`WaitCondition`] resolves to.
+pub(crate) enum Satisfied {
+    Interface(Interface),
+    Address(AddressInfo),
+    Done,
+}
+
+impl Satisfied {
+    /// Returns the interface, [`WaitCondition::Interface`] resolves to.
+    pub(crate) fn into_interface(self) -> Result<Interface, Error> {
+        match self {
+            Satisfied::Interface(interface) => Ok(interface),
+            Satisfied::Address(_) | Satisfied::Done => Err(Error::UnexpectedMetadata),
+        }
+    }
+
+    /// Returns the address, [`WaitCondition::Address`] resolves to.
+    pub(crate) fn into_address(self) -> Result<AddressInfo, Error> {
+        match self {
+            Satisfied::Address(info) => Ok(info),
+            Satisfied::Interface(_) | Satisfied::Done => Err(Error::UnexpectedMetadata),
+        }
+    }
+}
+
+impl<'a> WaitCondition<'a> {
+    pub(crate) fn groups(&self) -> &'static [MonitorGroup] {
+        match self {
+            WaitCondition::Address(..) => &[
+                MonitorGroup::Link,
+                MonitorGroup::Ipv4Address,
+                MonitorGroup::Ipv6Address,
+            ],
+            _ => &[MonitorGroup::Link],
+        }
+    }
+
+    /// Returns request and its flags, which query the current state.
+    pub(crate) fn query(&self) -> (RtnlMessage, u16) {
+        let mut link = LinkMessage::default();
+        match self {
+            WaitCondition::Interface(name) => {
+                link.nlas.push(LinkNla::IfName(name.to_string()));
+            }
+            WaitCondition::Flags(index, _) | WaitCondition::Removed(index) => {
+                link.header.index = *index;
+            }
+            WaitCondition::Address(..) => {
+                return (
+                    RtnlMessage::GetAddress(AddressMessage::default()),
+                    NLM_F_DUMP,
+                )
+            }
+        }
+        (RtnlMessage::GetLink(link), 0)
+    }
+
+    /// Evaluates the response to [`query`](Self::query).
+    pub(crate) fn check(
+        &mut self,
+        responses: Result<Vec<RtnlMessage>, Error>,
+        netns: Option<&NetnsHandle>,
+    ) -> Result<Option<Satisfied>, Error> {
+        let responses = match (&*self, responses) {
+            (WaitCondition::Interface(_), Err(Error::InterfaceNotFound)) => return Ok(None),
+            (WaitCondition::Removed(_), Err(Error::InterfaceNotFound)) => {
+                return Ok(Some(Satisfied::Done))
+            }
+            (_, responses) => responses?,
+        };
+
+        for response in responses {
+            let event = match response {
+                RtnlMessage::NewLink(link) => {
+                    Event::LinkChanged(LinkInfo::from_message(link, netns))
+                }
+                RtnlMessage::NewAddress(address) => {
+                    match AddressInfo::from_message(address, netns)? {
+                        Some(info) => Event::AddressAdded(info),
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+            if let Some(satisfied) = self.event(&event)? {
+                return Ok(Some(satisfied));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Evaluates a change event.
+    pub(crate) fn event(&mut self, event: &Event) -> Result<Option<Satisfied>, Error> {
+        let satisfied = match (self, event) {
+            (WaitCondition::Interface(name), Event::LinkAdded(info) | Event::LinkChanged(info))
+                if info.name == *name =>
+            {
+                Satisfied::Interface(info.interface.clone())
+            }
+            (
+                WaitCondition::Flags(index, flags),
+                Event::LinkAdded(info) | Event::LinkChanged(info),
+            ) if info.interface.0.index == *index && info.flags & *flags == *flags => {
+                Satisfied::Done
+            }
+            (WaitCondition::Address(index, predicate), Event::AddressAdded(info))
+                if info.interface.0.index == *index =>
+            {
+                // Failed addresses stay tentative
+                let dad_failed = info.is_dad_failed();
+                if (info.is_tentative() && !dad_failed) || !predicate(info) {
+                    return Ok(None);
+                }
+                if dad_failed {
+                    return Err(io::Error::from(io::ErrorKind::AddrInUse).into());
+                }
+                Satisfied::Address(info.clone())
+            }
+            (WaitCondition::Removed(index), Event::LinkRemoved(info))
+                if info.interface.0.index == *index =>
+            {
+                Satisfied::Done
+            }
+            (
+                WaitCondition::Flags(index, _) | WaitCondition::Address(index, _),
+                Event::LinkRemoved(info),
+            ) if info.interface.0.index == *index => return Err(Error::InterfaceNotFound),
+            _ => return Ok(None),
+        };
+        Ok(Some(satisfied))
+    }
+}
+
+/// Waits until an interface with given name appears, and returns it.
+pub fn wait_for_interface(name: &str, timeout: Duration) -> Result<Interface, Error> {
+    wait_for_interface_in(None, name, timeout)
+}
+
+/// Waits until the interface is administratively up (`IFF_UP`).
+pub fn wait_until_up(interface: &Interface, timeout: Duration) -> Result<(), Error> {
+    wait_for_flags(interface, IFF_UP as _, timeout)
+}
+
+/// Waits until the interface has carrier (`IFF_LOWER_UP`), e.g. a cable is plugged in.
+pub fn wait_for_carrier(interface: &Interface, timeout: Duration) -> Result<(), Error> {
+    wait_for_flags(interface, IFF_LOWER_UP as _, timeout)
+}
+
+/// Waits until the interface has a usable address, matching the predicate, and returns it.
+///
+/// IPv6 addresses are only passed to the predicate once duplicate address detection has finished.
+/// Fails with [`io::ErrorKind::AddrInUse`], if it has detected a conflict for a matching address.
+pub fn wait_for_address<P>(
+    interface: &Interface,
+    mut predicate: P,
+    timeout: Duration,
+) -> Result<AddressInfo, Error>
+where
+    P: FnMut(&AddressInfo) -> bool + Send,
+{
+    let condition = WaitCondition::Address(interface.0.index, &mut predicate);
+    wait(interface.0.netns.as_ref(), condition, timeout)?.into_address()
+}
+
+/// Waits until the interface is deleted or moved to another namespace.
+pub fn wait_until_removed(interface: &Interface, timeout: Duration) -> Result<(), Error> {
+    let condition = WaitCondition::Removed(interface.0.index);
+    wait(interface.0.netns.as_ref(), condition, timeout)?;
+    Ok(())
+}
+
+pub(crate) fn wait_for_interface_in(
+    netns: Option<&NetnsHandle>,
+    name: &str,
+    timeout: Duration,
+) -> Result<Interface, Error> {
+    wait(netns, WaitCondition::Interface(name), timeout)?.into_interface()
+}
+
+fn wait_for_flags(interface: &Interface, flags: u32, timeout: Duration) -> Result<(), Error> {
+    let condition = WaitCondition::Flags(interface.0.index, flags);
+    wait(interface.0.netns.as_ref(), condition, timeout)?;
+    Ok(())
+}
+
+fn wait(
+    netns: Option<&NetnsHandle>,
+    mut condition: WaitCondition,
+    timeout: Duration,
+) -> Result<Satisfied, Error> {
+    let deadline = Instant::now() + timeout;
+
+    // Subscribed before the state is checked, so that no change is missed in between. Links aren't
+    // listed for the Monitor, as conditions don't tell added links from changed ones.
+    let mut monitor = Monitor::subscribe(netlink::socket(netns)?, netns, condition.groups(), &[])?;
+    let socket = netlink::socket(netns)?;
+    let check = |condition: &mut WaitCondition| {
+        let (message, flags) = condition.query();
+        condition.check(netlink::request(&socket, message, flags), netns)
+    };
+
+    if let Some(satisfied) = check(&mut condition)? {
+        return Ok(satisfied);
+    }
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let satisfied = match monitor.next_event_timeout(remaining)? {
+            None => return Err(Error::Timeout),
+            Some(Event::Resync) => check(&mut condition)?,
+            Some(event) => condition.event(&event)?,
+        };
+        if let Some(satisfied) = satisfied {
+            return Ok(satisfied);
+        }
+    }
+}
+
+impl NetnsHandle {
+    /// Waits until an interface with given name appears inside this namespace, and returns it.
+    pub fn wait_for_interface(&self, name: &str, timeout: Duration) -> Result<Interface, Error> {
+        wait_for_interface_in(Some(self), name, timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipnet::IpNet;
+    use netlink_packet_route::address::Nla as AddressNla;
+    use netlink_packet_route::{IFA_F_DADFAILED, IFA_F_TENTATIVE};
+    use std::net::Ipv6Addr;
+
+    fn link(index: u32, flags: u32) -> LinkInfo {
+        LinkInfo {
+            interface: Interface::from_index_unchecked(index),
+            name: format!("test{index}"),
+            flags,
+            mtu: None,
+            hwaddress: None,
+        }
+    }
+
+    fn address(index: u32, flags: u32) -> AddressInfo {
+        AddressInfo {
+            interface: Interface::from_index_unchecked(index),
+            network: "fd00::1/64".parse().unwrap(),
+            flags,
+        }
+    }
+
+    #[test]
+    fn flags() {
+        let mut condition = WaitCondition::Flags(1, (IFF_UP | IFF_LOWER_UP) as _);
+        let up = Event::LinkChanged(link(1, IFF_UP as _));
+        assert!(condition.event(&up).unwrap().is_none());
+        let other = Event::LinkChanged(link(2, (IFF_UP | IFF_LOWER_UP) as _));
+        assert!(condition.event(&other).unwrap().is_none());
+        let running = Event::LinkChanged(link(1, (IFF_UP | IFF_LOWER_UP) as _));
+        assert!(matches!(
+            condition.event(&running).unwrap(),
+            Some(Satisfied::Done)
+        ));
+    }
+
+    #[test]
+    fn tentative_address() {
+        let mut predicate = |_: &AddressInfo| true;
+        let mut condition = WaitCondition::Address(1, &mut predicate);
+        let tentative = Event::AddressAdded(address(1, IFA_F_TENTATIVE));
+        assert!(condition.event(&tentative).unwrap().is_none());
+
+        let satisfied = condition.event(&Event::AddressAdded(address(1, 0)));
+        let info = satisfied.unwrap().unwrap().into_address().unwrap();
+        assert_eq!(info.network, "fd00::1/64".parse::<IpNet>().unwrap());
+    }
+
+    #[test]
+    fn dad_failed() {
+        let mut predicate = |_: &AddressInfo| true;
+        let mut condition = WaitCondition::Address(1, &mut predicate);
+        let failed = Event::AddressAdded(address(1, IFA_F_TENTATIVE | IFA_F_DADFAILED));
+        match condition.event(&failed) {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::AddrInUse),
+            _ => panic!("conflict not reported"),
+        }
+
+        // Conflicts of addresses, that don't match, are ignored
+        let mut predicate = |_: &AddressInfo| false;
+        let mut condition = WaitCondition::Address(1, &mut predicate);
+        assert!(condition.event(&failed).unwrap().is_none());
+    }
+
+    #[test]
+    fn link_removed() {
+        let removed = Event::LinkRemoved(link(1, 0));
+
+        let mut condition = WaitCondition::Flags(1, IFF_UP as _);
+        assert!(matches!(
+            condition.event(&removed),
+            Err(Error::InterfaceNotFound)
+        ));
+
+        let mut predicate = |_: &AddressInfo| true;
+        let mut condition = WaitCondition::Address(1, &mut predicate);
+        assert!(matches!(
+            condition.event(&removed),
+            Err(Error::InterfaceNotFound)
+        ));
+
+        let mut condition = WaitCondition::Removed(1);
+        assert!(matches!(
+            condition.event(&removed).unwrap(),
+            Some(Satisfied::Done)
+        ));
+        let mut condition = WaitCondition::Removed(2);
+        assert!(condition.event(&removed).unwrap().is_none());
+    }
+
+    #[test]
+    fn check_missing_interface() {
+        let mut condition = WaitCondition::Interface("test1");
+        let result = condition.check(Err(Error::InterfaceNotFound), None);
+        assert!(result.unwrap().is_none());
+
+        let mut condition = WaitCondition::Removed(1);
+        let result = condition.check(Err(Error::InterfaceNotFound), None);
+        assert!(matches!(result.unwrap(), Some(Satisfied::Done)));
+
+        let mut condition = WaitCondition::Flags(1, IFF_UP as _);
+        let result = condition.check(Err(Error::InterfaceNotFound), None);
+        assert!(matches!(result, Err(Error::InterfaceNotFound)));
+    }
+
+    #[test]
+    fn check_tentative_address() {
+        let mut predicate = |_: &AddressInfo| true;
+        let mut condition = WaitCondition::Address(1, &mut predicate);
+        let mut message = AddressMessage::default();
+        message.header.family = libc::AF_INET6 as u8;
+        message.header.prefix_len = 64;
+        message.header.index = 1;
+        let ip: Ipv6Addr = "fd00::1".parse().unwrap();
+        message.nlas.push(AddressNla::Address(ip.octets().to_vec()));
+        message.nlas.push(AddressNla::Flags(IFA_F_TENTATIVE));
+        let responses = vec![RtnlMessage::NewAddress(message)];
+        assert!(condition.check(Ok(responses), None).unwrap().is_none());
+    }
+}
@@ -3,9 +3,19 @@ use crate::Interface;
 #[derive(Debug, Clone)]
 pub(crate) struct InterfaceHandle {
     pub(crate) index: u32,
+    #[cfg(target_os = "linux")]
+    pub(crate) netns: Option<NetnsHandle>,
 }
 
 impl InterfaceHandle {
+    pub(crate) fn new(index: u32) -> Self {
+        Self {
+            index,
+            #[cfg(target_os = "linux")]
+            netns: None,
+        }
+    }
+
     #[allow(unused)]
     fn interface(&self) -> Interface {
         Interface(self.clone())
@@ -21,7 +31,24 @@ cfg_if::cfg_if! {
         mod linux;
         #[allow(unused)]
         pub(crate) use linux::*;
-        pub use linux::InterfaceExt;
+        pub use linux::{
+            add_fdb, add_mdb, add_neighbor, add_nexthop, add_route, add_rule, default_routes,
+            delete_fdb, delete_mdb, delete_neighbor, delete_nexthop, delete_route, delete_rule,
+            flush_neighbors, list_fdb, list_mdb, list_neighbors, list_nexthops, list_routes,
+            list_rules, replace_fdb, replace_neighbor, replace_nexthop, replace_route,
+            resolve_family, route_get, set_default_gateway, wait_for_address, wait_for_carrier,
+            wait_for_interface, wait_until_removed, wait_until_up, AddressInfo, Batch, BridgeFilter,
+            Cache, Channels, Coalesce, Connection, DriverInfo, Duplex, Event, FdbEntry, FdbState,
+            Feature, GenericConnection, GenericFamily, GenericMessage, GenericRequest, InterfaceExt,
+            InterfaceKind, InterfaceStats, IpFamily, LinkInfo, LinkSettings, MdbEntry, Monitor,
+            MonitorGroup, MulticastGroup, Neighbor, NeighborFilter, NeighborState, NetnsHandle,
+            NetnsTarget, NextHop, Nexthop, NexthopGroup, NexthopGroupMember, NexthopKind, Offload,
+            PortType, RateEvent, RateSample, RateSampler, ResilientGroup, Rings, Route, RouteFilter,
+            RouteGetOptions, RouteMetrics, RouteProtocol, RouteScope, RouteSpec, RouteType, Rule,
+            RuleAction,
+        };
+        #[cfg(feature = "tokio")]
+        pub use linux::nonblocking;
     } else if #[cfg(target_os = "macos")] {
         mod darwin;
         #[allow(unused)]
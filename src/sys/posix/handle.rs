@@ -16,7 +16,9 @@ impl InterfaceHandle {
         let name = self.name()?;
 
         for interface in getifaddrs()?.filter(|x| x.interface_name == name) {
-            let (Some(address), Some(netmask)) = (interface.address, interface.netmask) else { continue; };
+            let (Some(address), Some(netmask)) = (interface.address, interface.netmask) else {
+                continue;
+            };
 
             let (address, netmask) = match (address.family(), netmask.family()) {
                 (Some(Inet), Some(Inet)) => (
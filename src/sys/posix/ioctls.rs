@@ -15,6 +15,9 @@ cfg_if::cfg_if! {
         nix::ioctl_read_bad!(siocgifbrdaddr, libc::SIOCGIFBRDADDR, super::ifreq::ifreq);
         nix::ioctl_read_bad!(siocgifnetmask, libc::SIOCGIFNETMASK, super::ifreq::ifreq);
         nix::ioctl_read_bad!(siocgifhwaddr, libc::SIOCGIFHWADDR, super::ifreq::ifreq);
+
+        // SIOCETHTOOL, not defined by libc
+        nix::ioctl_readwrite_bad!(siocethtool, 0x8946, super::ifreq::ifreq);
     } else if #[cfg(target_os = "macos")] {
         nix::ioctl_readwrite!(siocgifmtu, b'i', 51, super::ifreq::ifreq);
         nix::ioctl_write_ptr!(siocsifmtu, b'i', 52, super::ifreq::ifreq);
@@ -1,3 +1,4 @@
+#[cfg(not(target_os = "linux"))]
 mod handle;
 mod ifacename;
 pub mod ifreq;